@@ -0,0 +1,134 @@
+use halo2_base::gates::{
+    flex_gate::{FlexGateConfig, GateStrategy},
+    GateInstructions,
+};
+use halo2_base::halo2_proofs::{
+    arithmetic::Field,
+    circuit::*,
+    dev::MockProver,
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::*,
+    poly::kzg::{
+        commitment::{KZGCommitmentScheme, ParamsKZG},
+        multiopen::ProverSHPLONK,
+    },
+    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+};
+use halo2_base::{
+    AdviceAllocStrategy, Context, ContextParams, QuantumCell::Witness, SKIP_FIRST_PASS,
+};
+use itertools::Itertools;
+use rand::rngs::OsRng;
+use std::marker::PhantomData;
+
+use criterion::{criterion_group, criterion_main};
+use criterion::{BenchmarkId, Criterion};
+
+use pprof::criterion::{Output, PProfProfiler};
+// Thanks to the example provided by @jebbow in his article
+// https://www.jibbow.com/posts/criterion-flamegraphs/
+
+#[derive(Clone, Default)]
+struct MyCircuit<F> {
+    advice_alloc_strategy: AdviceAllocStrategy,
+    _marker: PhantomData<F>,
+}
+
+const NUM_ADVICE: usize = 4;
+const K: u32 = 16;
+
+impl Circuit<Fr> for MyCircuit<Fr> {
+    type Config = FlexGateConfig<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self { advice_alloc_strategy: self.advice_alloc_strategy, _marker: PhantomData }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        FlexGateConfig::configure(meta, GateStrategy::Vertical, &[NUM_ADVICE], 1, 0, K as usize)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let mut first_pass = SKIP_FIRST_PASS;
+
+        layouter.assign_region(
+            || "gate",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = Context::new(
+                    region,
+                    ContextParams {
+                        max_rows: config.max_rows,
+                        num_context_ids: 1,
+                        fixed_columns: config.constants.clone(),
+                        advice_alloc_strategy: self.advice_alloc_strategy,
+                    },
+                );
+                let ctx = &mut aux;
+
+                let a = (0..5).map(|_| Witness(Value::known(Fr::random(OsRng)))).collect_vec();
+                let b = (0..5).map(|_| Witness(Value::known(Fr::random(OsRng)))).collect_vec();
+
+                // uses about 2.5 columns worth of rows, so that the vertical strategy leaves the last column mostly empty
+                for _ in 0..(5 << K) / 32 {
+                    config.inner_product(ctx, a.clone(), b.clone());
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+fn bench(c: &mut Criterion) {
+    let params = ParamsKZG::<Bn256>::setup(K, OsRng);
+
+    let mut group = c.benchmark_group("advice-alloc");
+    group.sample_size(10);
+    for advice_alloc_strategy in [AdviceAllocStrategy::Vertical, AdviceAllocStrategy::Balanced] {
+        let circuit = MyCircuit::<Fr> { advice_alloc_strategy, _marker: PhantomData };
+        MockProver::run(K, &circuit, vec![]).unwrap().assert_satisfied();
+
+        let vk = keygen_vk(&params, &circuit).expect("vk should not fail");
+        let pk = keygen_pk(&params, vk, &circuit).expect("pk should not fail");
+
+        group.bench_with_input(
+            BenchmarkId::new(format!("{advice_alloc_strategy:?}"), K),
+            &(&params, &pk, &circuit),
+            |b, &(params, pk, circuit)| {
+                b.iter(|| {
+                    let rng = OsRng;
+                    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+                    create_proof::<
+                        KZGCommitmentScheme<Bn256>,
+                        ProverSHPLONK<'_, Bn256>,
+                        Challenge255<G1Affine>,
+                        _,
+                        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<_>>,
+                        _,
+                    >(
+                        params, pk, &[circuit.clone()], &[&[]], rng, &mut transcript
+                    )
+                    .expect("prover should not fail");
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_profiler(PProfProfiler::new(10, Output::Flamegraph(None)));
+    targets = bench
+}
+criterion_main!(benches);
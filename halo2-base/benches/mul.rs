@@ -65,11 +65,7 @@ impl Circuit<Fr> for MyCircuit<Fr> {
 
                 let mut aux = Context::new(
                     region,
-                    ContextParams {
-                        max_rows: config.max_rows,
-                        num_context_ids: 1,
-                        fixed_columns: config.constants.clone(),
-                    },
+                    ContextParams::new(config.max_rows, 1, config.constants.clone()),
                 );
                 let ctx = &mut aux;
 
@@ -0,0 +1,272 @@
+//! Opt-in debugging of failing constraints by named scopes.
+//!
+//! After [`Context::enable_debug_scopes`](crate::Context::enable_debug_scopes), the `Context` records for every advice cell it assigns
+//! the current scope path, e.g. `"ecdsa/u1_mul/ec_add_unequal"`, built with [`Context::push_scope`](crate::Context::push_scope) and
+//! [`Context::pop_scope`](crate::Context::pop_scope), together with the value of the cell.
+//! At the end of synthesis, the recorded cells are retrieved with [`Context::take_debug_info`](crate::Context::take_debug_info)
+//! to translate the failures of `MockProver::verify` into scope names and cell values.
+//!
+//! Rows are the offsets within the region of the `Context`, so this assumes that region starts at row 0,
+//! as is the case for the circuits in this crate.
+//!
+//! Independently, after [`Context::enable_shadow_checks`](crate::Context::enable_shadow_checks) the `Context` checks the witness
+//! against the constraints as it assigns it, with a [`ShadowChecker`], and panics on the first violation. This is much faster than `MockProver`.
+use crate::{
+    halo2_proofs::{
+        dev::{metadata, FailureLocation, VerifyFailure},
+        plonk::{Any, FirstPhase, SecondPhase, ThirdPhase},
+    },
+    utils::ScalarField,
+};
+use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+
+/// An advice cell assigned by a `Context` in debug mode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugCell {
+    /// Index of the advice column
+    pub column: usize,
+    pub row: usize,
+    /// Scope path at the time the cell was assigned
+    pub scope: String,
+    /// Debug string of the value of the cell, if known
+    pub value: String,
+}
+
+/// The scope stack of a `Context` in debug mode and the cells recorded so far
+#[derive(Clone, Debug)]
+pub struct DebugScopes {
+    // interned scope paths; `paths[0]` is the root scope ""
+    paths: Vec<String>,
+    path_ids: FxHashMap<String, usize>,
+    // ids of the scope paths on the stack, starting from the root
+    stack: Vec<usize>,
+    // (column, row) -> (scope id, value)
+    cells: FxHashMap<(usize, usize), (usize, String)>,
+    // scope ids of the cells pushed to `Context::cells_to_lookup`, in order
+    lookup_scopes: Vec<usize>,
+}
+
+impl Default for DebugScopes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugScopes {
+    pub fn new() -> Self {
+        Self {
+            paths: vec![String::new()],
+            path_ids: FxHashMap::from_iter([(String::new(), 0)]),
+            stack: vec![0],
+            cells: FxHashMap::default(),
+            lookup_scopes: vec![],
+        }
+    }
+
+    /// The current scope path, "" at the root
+    pub fn current_scope(&self) -> &str {
+        &self.paths[self.current_scope_id()]
+    }
+
+    fn current_scope_id(&self) -> usize {
+        *self.stack.last().unwrap()
+    }
+
+    pub fn push_scope(&mut self, name: &str) {
+        let parent = self.current_scope();
+        let path = if parent.is_empty() { name.to_string() } else { format!("{parent}/{name}") };
+        let id = match self.path_ids.get(&path) {
+            Some(id) => *id,
+            None => {
+                self.paths.push(path.clone());
+                self.path_ids.insert(path, self.paths.len() - 1);
+                self.paths.len() - 1
+            }
+        };
+        self.stack.push(id);
+    }
+
+    pub fn pop_scope(&mut self) {
+        assert!(self.stack.len() > 1, "pop_scope called without a matching push_scope");
+        self.stack.pop();
+    }
+
+    /// Records the cell at `(column, row)` as assigned in the current scope
+    pub fn record_cell(&mut self, column: usize, row: usize, value: String) {
+        self.cells.insert((column, row), (self.current_scope_id(), value));
+    }
+
+    /// Records that a cell is added to the cells to look up, in the current scope
+    pub fn record_lookup(&mut self) {
+        self.lookup_scopes.push(self.current_scope_id());
+    }
+
+    /// Records the copy of the `idx`-th cell to look up at `(column, row)`, with the scope the cell was added in
+    pub fn record_lookup_copy(&mut self, idx: usize, column: usize, row: usize, value: String) {
+        let scope = self.lookup_scopes.get(idx).copied().unwrap_or_else(|| self.current_scope_id());
+        self.cells.insert((column, row), (scope, value));
+    }
+
+    /// Call this after all cells to look up have been copied
+    pub fn clear_lookups(&mut self) {
+        self.lookup_scopes.clear();
+    }
+
+    pub fn into_info(self) -> DebugInfo {
+        let cells = self
+            .cells
+            .into_iter()
+            .map(|((column, row), (scope, value))| {
+                ((row, column), DebugCell { column, row, scope: self.paths[scope].clone(), value })
+            })
+            .collect();
+        DebugInfo { cells }
+    }
+}
+
+/// The cells recorded by a `Context` in debug mode, used to explain `MockProver` failures
+#[derive(Clone, Debug, Default)]
+pub struct DebugInfo {
+    // (row, column) -> cell, so cells are sorted by row
+    cells: BTreeMap<(usize, usize), DebugCell>,
+}
+
+impl DebugInfo {
+    /// Returns the recorded cells in rows `rows`, sorted by row and then column
+    pub fn cells_in_rows(&self, rows: std::ops::Range<usize>) -> impl Iterator<Item = &DebugCell> {
+        self.cells.range((rows.start, 0)..(rows.end, 0)).map(|(_, cell)| cell)
+    }
+
+    /// Returns the recorded cells involved in `failure`.
+    ///
+    /// For a constraint failure these are the cells queried by the constraint, i.e., whose column and rotation from the row of
+    /// the failure appear in its `cell_values`, among the 4 rows queried by the gates of this crate.
+    /// `MockProver` does not expose the column of other failures, so for those these are the cells of all advice columns in the row of the failure.
+    pub fn involved_cells(&self, failure: &VerifyFailure) -> Vec<&DebugCell> {
+        let row = match failure {
+            VerifyFailure::ConstraintNotSatisfied { location, cell_values, .. } => {
+                let row = location_row(location);
+                return self
+                    .cells_in_rows(row..row + 4)
+                    .filter(|cell| is_queried(cell_values, cell.column, (cell.row - row) as i32))
+                    .collect();
+            }
+            VerifyFailure::Lookup { location, .. }
+            | VerifyFailure::Permutation { location, .. } => location_row(location),
+            VerifyFailure::CellNotAssigned { offset, .. } => *offset as usize,
+            _ => return vec![],
+        };
+        self.cells_in_rows(row..row + 1).collect()
+    }
+
+    /// Returns a description of `failure` followed by the scope and value of each of its involved cells
+    pub fn explain(&self, failure: &VerifyFailure) -> String {
+        let mut explanation = format!("{failure}");
+        for cell in self.involved_cells(failure) {
+            explanation += &format!(
+                "\n    column {} row {}: \"{}\" = {}",
+                cell.column, cell.row, cell.scope, cell.value
+            );
+        }
+        explanation
+    }
+
+    /// Explains each of `failures`, see [`DebugInfo::explain`]
+    pub fn explain_all(&self, failures: &[VerifyFailure]) -> String {
+        failures.iter().map(|failure| self.explain(failure)).collect::<Vec<_>>().join("\n")
+    }
+}
+
+// Whether the advice cell in column `column` at rotation `rotation` is among the `cell_values` of a constraint failure.
+// An advice column index belongs to a single phase, so it is enough to compare it in every phase.
+fn is_queried(
+    cell_values: &[(metadata::VirtualCell, String)],
+    column: usize,
+    rotation: i32,
+) -> bool {
+    [Any::advice_in(FirstPhase), Any::advice_in(SecondPhase), Any::advice_in(ThirdPhase)]
+        .into_iter()
+        .any(|column_type| {
+            let column: metadata::Column = (column_type, column).into();
+            let cell: metadata::VirtualCell = (column, rotation).into();
+            cell_values.iter().any(|(queried, _)| *queried == cell)
+        })
+}
+
+fn location_row(location: &FailureLocation) -> usize {
+    match location {
+        FailureLocation::InRegion { offset, .. } => *offset,
+        FailureLocation::OutsideRegion { row } => *row,
+    }
+}
+
+/// Checks the basic gates of the witness as it is assigned.
+///
+/// A basic gate enabled at `row` of an advice column constrains `a + q_left * b + q_right * c + q_mul * b * c = d`,
+/// where `a, b, c, d` are the cells at rows `row, ..., row + 3`, and `[q_mul, q_left, q_right]` is `[1, 0, 0]` for the vertical gate.
+/// The gate is checked as soon as all 4 of its cells are assigned. Gates involving cells with unknown values are not checked.
+#[derive(Clone, Debug, Default)]
+pub struct ShadowChecker<F: ScalarField> {
+    // `values[column][row]` is the value of the cell, or `None` if it is unknown or not assigned
+    values: Vec<Vec<Option<F>>>,
+    // (column, last row of the gate) -> (first row of the gate, [q_mul, q_left, q_right]) for gates whose cells are not all assigned yet
+    pending_gates: FxHashMap<(usize, usize), Vec<(usize, [F; 3])>>,
+}
+
+impl<F: ScalarField> ShadowChecker<F> {
+    pub fn new() -> Self {
+        Self { values: vec![], pending_gates: FxHashMap::default() }
+    }
+
+    /// Records the value of the cell at `(column, row)` and checks the gates that end at this cell
+    pub fn record_cell(
+        &mut self,
+        column: usize,
+        row: usize,
+        value: Option<F>,
+    ) -> Result<(), String> {
+        if column >= self.values.len() {
+            self.values.resize(column + 1, vec![]);
+        }
+        let values = &mut self.values[column];
+        if row >= values.len() {
+            values.resize(row + 1, None);
+        }
+        values[row] = value;
+        match self.pending_gates.remove(&(column, row)) {
+            Some(gates) => {
+                gates.into_iter().try_for_each(|(row, q)| self.check_gate(column, row, q))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the basic gate enabled at `(column, row)`, or defers the check until its last cell is assigned
+    pub fn enable_gate(&mut self, column: usize, row: usize, q: [F; 3]) -> Result<(), String> {
+        let assigned_rows = self.values.get(column).map_or(0, |values| values.len());
+        if row + 3 < assigned_rows {
+            self.check_gate(column, row, q)
+        } else {
+            self.pending_gates.entry((column, row + 3)).or_default().push((row, q));
+            Ok(())
+        }
+    }
+
+    fn check_gate(
+        &self,
+        column: usize,
+        row: usize,
+        [q_mul, q_left, q_right]: [F; 3],
+    ) -> Result<(), String> {
+        let cells = &self.values[column][row..row + 4];
+        if let [Some(a), Some(b), Some(c), Some(d)] = *cells {
+            if a + q_left * b + q_right * c + q_mul * b * c != d {
+                return Err(format!(
+                    "basic gate at column {column} row {row}: {a:?} + {q_left:?} * {b:?} + {q_right:?} * {c:?} + {q_mul:?} * {b:?} * {c:?} != {d:?}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! Lookup arguments into tables built from advice cells.
+//!
+//! The lookup table of [`RangeConfig`](super::range::RangeConfig) is a fixed `TableColumn`. Here the table is a list of
+//! `(key, value)` pairs of advice cells, so it can depend on the witness. This gives read-only memory: after loading an array
+//! into a [`DynamicLookupTable`], each read at a witness index costs a constant number of cells instead of the cost of
+//! [`select_from_idx`](super::GateInstructions::select_from_idx), which is linear in the length of the array.
+use crate::{
+    gates::GateInstructions,
+    halo2_proofs::{
+        circuit::Value,
+        plonk::{Advice, Column, ConstraintSystem, Selector},
+        poly::Rotation,
+    },
+    utils::{value_to_option, ScalarField},
+    AssignedValue, Context,
+};
+use rustc_hash::FxHashMap;
+
+/// Configuration for lookups of `(key, value)` pairs into a table of `(key, value)` pairs, all in the first phase.
+///
+/// During synthesis the table and the pairs to look up are collected in a [`DynamicLookupTable`] and then copied to the
+/// dedicated columns of this config by [`DynamicLookupConfig::finalize`].
+///
+/// Each row of `table` and each row of a column pair in `lookup_advice` has its own selector, and the lookup argument is
+/// `(q_lookup, q_lookup * key, q_lookup * value)` into `(q_table, q_table * key, q_table * value)`.
+/// Rows with disabled selectors are then `(0, 0, 0)` on both sides, while enabled rows can only match enabled table rows.
+#[derive(Clone, Debug)]
+pub struct DynamicLookupConfig {
+    /// `[key, value]` columns of the table
+    pub table: [Column<Advice>; 2],
+    pub q_table: Selector,
+    /// `[key, value]` columns of the pairs to look up
+    pub lookup_advice: Vec<[Column<Advice>; 2]>,
+    /// `q_lookup[i]` enables the lookup for the rows of `lookup_advice[i]`
+    pub q_lookup: Vec<Selector>,
+}
+
+impl DynamicLookupConfig {
+    /// Creates a table with one pair of advice columns and `num_lookup_advice` pairs of advice columns for the lookups.
+    ///
+    /// The table holds at most `max_rows` entries, and each pair of lookup columns at most `max_rows` lookups, where `max_rows`
+    /// is that of the `Context` passed to [`DynamicLookupConfig::finalize`].
+    pub fn configure<F: ScalarField>(
+        meta: &mut ConstraintSystem<F>,
+        num_lookup_advice: usize,
+    ) -> Self {
+        let mut advice_pair = || {
+            [(); 2].map(|_| {
+                let a = meta.advice_column();
+                meta.enable_equality(a);
+                a
+            })
+        };
+        let table = advice_pair();
+        let lookup_advice: Vec<_> = (0..num_lookup_advice).map(|_| advice_pair()).collect();
+        let q_table = meta.complex_selector();
+        let q_lookup: Vec<_> = (0..num_lookup_advice).map(|_| meta.complex_selector()).collect();
+
+        for (columns, q) in lookup_advice.iter().zip(q_lookup.iter()) {
+            meta.lookup_any("dynamic lookup", |meta| {
+                let q_table = meta.query_selector(q_table);
+                let q = meta.query_selector(*q);
+                let [key, value] = columns.map(|c| meta.query_advice(c, Rotation::cur()));
+                let [table_key, table_value] = table.map(|c| meta.query_advice(c, Rotation::cur()));
+                vec![
+                    (q.clone(), q_table.clone()),
+                    (q.clone() * key, q_table.clone() * table_key),
+                    (q * value, q_table * table_value),
+                ]
+            });
+        }
+
+        Self { table, q_table, lookup_advice, q_lookup }
+    }
+
+    /// Call this at the end of the first phase to copy the entries and lookups of `table` to the columns of this config
+    /// and enable their selectors.
+    ///
+    /// Returns the total number of lookups.
+    ///
+    /// assumes ctx.region is not in shape mode
+    pub fn finalize<'v, F: ScalarField>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        table: &DynamicLookupTable<'v, F>,
+    ) -> usize {
+        assert_eq!(ctx.current_phase(), 0, "dynamic lookups are only supported in the first phase");
+        assert!(
+            table.entries.len() <= ctx.max_rows,
+            "dynamic lookup table has more than {} entries",
+            ctx.max_rows
+        );
+        for (offset, entry) in table.entries.iter().enumerate() {
+            for (acell, column) in entry.iter().zip(self.table) {
+                acell.copy_advice(ctx.region(), column, offset);
+            }
+            self.q_table.enable(ctx.region(), offset).expect("enable selector should not fail");
+        }
+
+        let mut lookups = table.lookups.iter();
+        for (columns, q) in self.lookup_advice.iter().zip(self.q_lookup.iter()) {
+            for (offset, lookup) in lookups.by_ref().take(ctx.max_rows).enumerate() {
+                for (acell, column) in lookup.iter().zip(columns) {
+                    acell.copy_advice(ctx.region(), *column, offset);
+                }
+                q.enable(ctx.region(), offset).expect("enable selector should not fail");
+            }
+        }
+        if lookups.next().is_some() {
+            panic!("NOT ENOUGH ADVICE COLUMNS WITH DYNAMIC LOOKUP ENABLED");
+        }
+        table.lookups.len()
+    }
+}
+
+/// The entries of a dynamic lookup table and the `(key, value)` pairs constrained to be among them.
+///
+/// Nothing is constrained until the table is passed to [`DynamicLookupConfig::finalize`].
+#[derive(Clone, Debug, Default)]
+pub struct DynamicLookupTable<'v, F: ScalarField> {
+    entries: Vec<[AssignedValue<'v, F>; 2]>,
+    lookups: Vec<[AssignedValue<'v, F>; 2]>,
+    // index in `entries` of the first entry with each known key, so `read` does not scan the table
+    #[cfg(feature = "halo2-axiom")]
+    key_index: FxHashMap<F, usize>,
+    // PSE's halo2curves does not derive Hash
+    #[cfg(feature = "halo2-pse")]
+    key_index: FxHashMap<Vec<u8>, usize>,
+}
+
+impl<'v, F: ScalarField> DynamicLookupTable<'v, F> {
+    pub fn new() -> Self {
+        Self { entries: vec![], lookups: vec![], key_index: FxHashMap::default() }
+    }
+
+    #[cfg(feature = "halo2-axiom")]
+    fn entry_index(&self, key: &F) -> Option<usize> {
+        self.key_index.get(key).copied()
+    }
+    #[cfg(feature = "halo2-pse")]
+    fn entry_index(&self, key: &F) -> Option<usize> {
+        self.key_index.get(key.to_repr().as_ref()).copied()
+    }
+
+    /// Loads `values` as an array, i.e., the table entries `(i, values[i])`.
+    ///
+    /// Assigns a constant cell for each index.
+    pub fn from_array(
+        ctx: &mut Context<'_, F>,
+        gate: &impl GateInstructions<F>,
+        values: impl IntoIterator<Item = AssignedValue<'v, F>>,
+    ) -> Self {
+        let mut table = Self::new();
+        for (i, value) in values.into_iter().enumerate() {
+            let key = gate.load_constant(ctx, gate.get_field_element(i as u64));
+            table.add_entry(key, value);
+        }
+        table
+    }
+
+    pub fn entries(&self) -> &[[AssignedValue<'v, F>; 2]] {
+        &self.entries
+    }
+
+    pub fn num_lookups(&self) -> usize {
+        self.lookups.len()
+    }
+
+    pub fn add_entry(&mut self, key: AssignedValue<'v, F>, value: AssignedValue<'v, F>) {
+        if let Some(k) = value_to_option(key.value()) {
+            #[cfg(feature = "halo2-axiom")]
+            let k = *k;
+            #[cfg(feature = "halo2-pse")]
+            let k = k.to_repr().as_ref().to_vec();
+            self.key_index.entry(k).or_insert(self.entries.len());
+        }
+        self.entries.push([key, value]);
+    }
+
+    /// Constrains `(key, value)` to be an entry of the table
+    pub fn lookup(&mut self, key: AssignedValue<'v, F>, value: AssignedValue<'v, F>) {
+        self.lookups.push([key, value]);
+    }
+
+    /// Returns a new cell with the value of the first entry of the table with key `key`, and constrains `(key, value)` to be an entry of the table.
+    ///
+    /// If the keys of the table are distinct, this is the value at `key`.
+    /// If no entry has key `key`, the returned cell has value zero and the lookup will fail.
+    pub fn read(
+        &mut self,
+        ctx: &mut Context<'_, F>,
+        gate: &impl GateInstructions<F>,
+        key: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        let value = match value_to_option(key.value()) {
+            Some(key) => self
+                .entry_index(key)
+                .map(|i| self.entries[i][1].value().copied())
+                .unwrap_or_else(|| Value::known(F::zero())),
+            None => Value::unknown(),
+        };
+        let value = gate.load_witness(ctx, value);
+        self.lookup(key.clone(), value.clone());
+        value
+    }
+}
@@ -11,7 +11,8 @@ use crate::halo2_proofs::{
     poly::Rotation,
 };
 use crate::utils::ScalarField;
-use itertools::Itertools;
+use crate::AdviceAllocStrategy;
+use itertools::{Either, Itertools};
 use std::{
     iter::{self, once},
     marker::PhantomData,
@@ -20,6 +21,24 @@ use std::{
 /// The maximum number of phases halo2 currently supports
 pub const MAX_PHASE: usize = 3;
 
+/// Returns `gate_offsets` together with whether any offset is negative, i.e., whether the gate overlaps with the previously assigned region.
+///
+/// Only `AdviceAllocStrategy::Balanced` needs to know about overlaps, so we avoid collecting `gate_offsets` otherwise.
+fn gate_offsets_with_overlap<F: ScalarField>(
+    strategy: AdviceAllocStrategy,
+    gate_offsets: impl IntoIterator<Item = (isize, Option<[F; 3]>)>,
+) -> (impl Iterator<Item = (isize, Option<[F; 3]>)>, bool) {
+    let gate_offsets = gate_offsets.into_iter();
+    match strategy {
+        AdviceAllocStrategy::Vertical => (Either::Left(gate_offsets), false),
+        AdviceAllocStrategy::Balanced => {
+            let gate_offsets = gate_offsets.collect_vec();
+            let overlap = gate_offsets.iter().any(|(i, _)| *i < 0);
+            (Either::Right(gate_offsets.into_iter()), overlap)
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum GateStrategy {
     Vertical,
@@ -355,6 +374,9 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
     ) -> Vec<AssignedValue<'b, F>> {
         // We enforce the pattern that you should assign everything in current phase at once and then move onto next phase
         debug_assert_eq!(phase, ctx.current_phase());
+        if let Some(virtual_ctx) = ctx.virtual_ctx_mut() {
+            return virtual_ctx.assign_region(self.context_id, inputs, gate_offsets);
+        }
 
         let inputs = inputs.into_iter();
         let (len, hi) = inputs.size_hint();
@@ -362,15 +384,10 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
         // we index into `advice_alloc` twice so this assert should save a bound check
         assert!(self.context_id < ctx.advice_alloc.len(), "context id out of bounds");
 
-        let (gate_index, row_offset) = {
-            let alloc = ctx.advice_alloc.get_mut(self.context_id).unwrap();
-
-            if alloc.1 + len >= ctx.max_rows {
-                alloc.1 = 0;
-                alloc.0 += 1;
-            }
-            *alloc
-        };
+        let (gate_offsets, overlap) =
+            gate_offsets_with_overlap(ctx.advice_alloc_strategy, gate_offsets);
+        let (gate_index, row_offset) =
+            ctx.advice_alloc_start(self.context_id, len, self.num_advice[phase], overlap);
 
         let basic_gate = self.basic_gates[phase]
             .get(gate_index)
@@ -394,15 +411,24 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
         for (i, q_coeff) in gate_offsets.into_iter() {
             basic_gate
                 .q_enable
-                .enable(&mut ctx.region, (row_offset as isize + i) as usize)
+                .enable(ctx.region(), (row_offset as isize + i) as usize)
                 .expect("enable selector should not fail");
+            let q_coeff = match self.strategy {
+                GateStrategy::Vertical => None,
+                GateStrategy::PlonkPlus => q_coeff,
+            };
+            ctx.shadow_enable_gate(
+                column,
+                (row_offset as isize + i) as usize,
+                q_coeff.unwrap_or([F::one(), F::zero(), F::zero()]),
+            );
 
             if self.strategy == GateStrategy::PlonkPlus {
                 let q_coeff = q_coeff.unwrap_or([F::one(), F::zero(), F::zero()]);
                 for (j, q_coeff) in q_coeff.into_iter().enumerate() {
                     #[cfg(feature = "halo2-axiom")]
                     {
-                        ctx.region.assign_fixed(
+                        ctx.region().assign_fixed(
                             basic_gate.q_enable_plus[0],
                             ((row_offset as isize) + i) as usize + j,
                             Assigned::Trivial(q_coeff),
@@ -410,7 +436,7 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
                     }
                     #[cfg(feature = "halo2-pse")]
                     {
-                        ctx.region
+                        ctx.region()
                             .assign_fixed(
                                 || "",
                                 basic_gate.q_enable_plus[0],
@@ -442,6 +468,10 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
     ) -> AssignedValue<'b, F> {
         // We enforce the pattern that you should assign everything in current phase at once and then move onto next phase
         debug_assert_eq!(phase, ctx.current_phase());
+        if let Some(virtual_ctx) = ctx.virtual_ctx_mut() {
+            let mut assignments = virtual_ctx.assign_region(self.context_id, inputs, gate_offsets);
+            return assignments.pop().expect("inputs should not be empty");
+        }
 
         let inputs = inputs.into_iter();
         let (len, hi) = inputs.size_hint();
@@ -450,15 +480,10 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
         // we index into `advice_alloc` twice so this assert should save a bound check
         assert!(self.context_id < ctx.advice_alloc.len(), "context id out of bounds");
 
-        let (gate_index, row_offset) = {
-            let alloc = ctx.advice_alloc.get_mut(self.context_id).unwrap();
-
-            if alloc.1 + len >= ctx.max_rows {
-                alloc.1 = 0;
-                alloc.0 += 1;
-            }
-            *alloc
-        };
+        let (gate_offsets, overlap) =
+            gate_offsets_with_overlap(ctx.advice_alloc_strategy, gate_offsets);
+        let (gate_index, row_offset) =
+            ctx.advice_alloc_start(self.context_id, len, self.num_advice[phase], overlap);
 
         let basic_gate = self.basic_gates[phase]
             .get(gate_index)
@@ -480,15 +505,24 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
         for (i, q_coeff) in gate_offsets.into_iter() {
             basic_gate
                 .q_enable
-                .enable(&mut ctx.region, (row_offset as isize + i) as usize)
+                .enable(ctx.region(), (row_offset as isize + i) as usize)
                 .expect("selector enable should not fail");
+            let q_coeff = match self.strategy {
+                GateStrategy::Vertical => None,
+                GateStrategy::PlonkPlus => q_coeff,
+            };
+            ctx.shadow_enable_gate(
+                column,
+                (row_offset as isize + i) as usize,
+                q_coeff.unwrap_or([F::one(), F::zero(), F::zero()]),
+            );
 
             if self.strategy == GateStrategy::PlonkPlus {
                 let q_coeff = q_coeff.unwrap_or([F::one(), F::zero(), F::zero()]);
                 for (j, q_coeff) in q_coeff.into_iter().enumerate() {
                     #[cfg(feature = "halo2-axiom")]
                     {
-                        ctx.region.assign_fixed(
+                        ctx.region().assign_fixed(
                             basic_gate.q_enable_plus[0],
                             ((row_offset as isize) + i) as usize + j,
                             Assigned::Trivial(q_coeff),
@@ -496,7 +530,7 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
                     }
                     #[cfg(feature = "halo2-pse")]
                     {
-                        ctx.region
+                        ctx.region()
                             .assign_fixed(
                                 || "",
                                 basic_gate.q_enable_plus[0],
@@ -588,18 +622,48 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
                 let mut b = b.into_iter().peekable();
                 let (len, hi) = b.size_hint();
                 debug_assert_eq!(Some(len), hi);
+                if ctx.is_virtual() {
+                    // a virtual context records whole regions, so here we do use `assign_region`
+                    // | start | a_0 | b_0 | acc_0 | a_1 | b_1 | acc_1 | ...
+                    let right_one = matches!(b.peek(), Some(&Constant(x)) if x == F::one());
+                    let start = if right_one {
+                        b.next();
+                        a.next().unwrap()
+                    } else {
+                        Constant(F::zero())
+                    };
+                    let mut acc = start.value().copied();
+                    let mut cells = vec![start];
+                    let mut gate_offsets = Vec::with_capacity(len);
+                    for (a, b) in a.zip(b) {
+                        acc = acc + a.value().zip(b.value()).map(|(a, b)| *a * b);
+                        gate_offsets.push((cells.len() as isize - 1, None));
+                        cells.extend([a, b, Witness(acc)]);
+                    }
+                    let num_products = gate_offsets.len();
+                    let assignments = self.assign_region(ctx, cells, gate_offsets);
+                    a_assigned.clear();
+                    assert!(a_assigned.capacity() >= len);
+                    if right_one {
+                        a_assigned.push(assignments[0].clone());
+                    }
+                    a_assigned.extend((0..num_products).map(|i| assignments[3 * i + 1].clone()));
+                    return if num_products == 0 {
+                        a_assigned[0].clone()
+                    } else {
+                        assignments.last().unwrap().clone()
+                    };
+                }
                 // we do not use `assign_region` and implement directly to avoid `collect`ing the vector of assignments
                 let phase = ctx.current_phase();
                 assert!(self.context_id < ctx.advice_alloc.len(), "context id out of bounds");
 
-                let (gate_index, mut row_offset) = {
-                    let alloc = ctx.advice_alloc.get_mut(self.context_id).unwrap();
-                    if alloc.1 + 3 * len + 1 >= ctx.max_rows {
-                        alloc.1 = 0;
-                        alloc.0 += 1;
-                    }
-                    *alloc
-                };
+                let (gate_index, mut row_offset) = ctx.advice_alloc_start(
+                    self.context_id,
+                    3 * len + 1,
+                    self.num_advice[phase],
+                    false,
+                );
                 let basic_gate = self.basic_gates[phase]
                     .get(gate_index)
                     .unwrap_or_else(|| panic!("NOT ENOUGH ADVICE COLUMNS IN PHASE {phase}"));
@@ -634,8 +698,13 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
 
                 for (a, b) in a.zip(b) {
                     q_enable
-                        .enable(&mut ctx.region, row_offset - 1)
+                        .enable(ctx.region(), row_offset - 1)
                         .expect("enable selector should not fail");
+                    ctx.shadow_enable_gate(
+                        column,
+                        row_offset - 1,
+                        [F::one(), F::zero(), F::zero()],
+                    );
 
                     acc = acc + a.value().zip(b.value()).map(|(a, b)| *a * b);
                     let [a, _, c] = [(a, 0), (b, 1), (Witness(acc), 2)].map(|(qcell, idx)| {
@@ -750,7 +819,7 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
                     ],
                     vec![(0, Some([F::zero(), F::one(), -F::one()])), (3, None)],
                 );
-                ctx.region.constrain_equal(assignments[2].cell(), assignments[5].cell());
+                ctx.constrain_equal(&assignments[2], &assignments[5]);
                 assignments.pop().unwrap()
             }
         }
@@ -815,7 +884,7 @@ impl<F: ScalarField> GateInstructions<F> for FlexGateConfig<F> {
             self.pow_of_two[..range_bits].iter().map(|c| Constant(*c)),
             &mut bit_cells,
         );
-        ctx.region.constrain_equal(a.cell(), acc.cell());
+        ctx.constrain_equal(a, &acc);
 
         for bit_cell in &bit_cells {
             self.assign_region(
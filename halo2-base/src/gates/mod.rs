@@ -1,4 +1,7 @@
-use self::{flex_gate::GateStrategy, range::RangeStrategy};
+use self::{
+    flex_gate::GateStrategy,
+    range::{BitwiseOp, RangeStrategy},
+};
 use super::{
     utils::ScalarField,
     AssignedValue, Context,
@@ -6,7 +9,7 @@ use super::{
 };
 use crate::{
     halo2_proofs::{circuit::Value, plonk::Assigned},
-    utils::{biguint_to_fe, bit_length, fe_to_biguint, PrimeField},
+    utils::{biguint_to_fe, bit_length, fe_to_biguint, value_to_option, PrimeField},
 };
 use core::iter;
 use num_bigint::BigUint;
@@ -14,8 +17,13 @@ use num_integer::Integer;
 use num_traits::{One, Zero};
 use std::ops::Shl;
 
+pub mod dynamic_lookup;
 pub mod flex_gate;
+pub mod permutation;
+pub mod primitive;
 pub mod range;
+pub mod rlc;
+pub mod signed;
 
 pub trait GateInstructions<F: ScalarField> {
     fn strategy(&self) -> GateStrategy;
@@ -78,10 +86,10 @@ pub trait GateInstructions<F: ScalarField> {
         let assignments =
             self.assign_region(ctx, inputs, gate_offsets.into_iter().map(|i| (i as isize, None)));
         for (offset1, offset2) in equality_offsets.into_iter() {
-            ctx.region.constrain_equal(assignments[offset1].cell(), assignments[offset2].cell());
+            ctx.constrain_equal(&assignments[offset1], &assignments[offset2]);
         }
         for (assigned, eq_offset) in external_equality.into_iter() {
-            ctx.region.constrain_equal(assigned.cell(), assignments[eq_offset].cell());
+            ctx.constrain_equal(assigned, &assignments[eq_offset]);
         }
         assignments
     }
@@ -198,7 +206,7 @@ pub trait GateInstructions<F: ScalarField> {
         let out_val = a.value().zip(b.value()).map(|(a, b)| (F::one() - a) * b);
         let assignments =
             self.assign_region(ctx, vec![Witness(out_val), a, b.clone(), b], vec![(0, None)]);
-        ctx.region.constrain_equal(assignments[2].cell(), assignments[3].cell());
+        ctx.constrain_equal(&assignments[2], &assignments[3]);
         assignments.into_iter().next().unwrap()
     }
 
@@ -227,7 +235,7 @@ pub trait GateInstructions<F: ScalarField> {
 
     fn assert_equal(&self, ctx: &mut Context<'_, F>, a: QuantumCell<F>, b: QuantumCell<F>) {
         if let (Existing(a), Existing(b)) = (&a, &b) {
-            ctx.region.constrain_equal(a.cell(), b.cell());
+            ctx.constrain_equal(a, b);
         } else {
             self.assign_region_smart(
                 ctx,
@@ -240,11 +248,11 @@ pub trait GateInstructions<F: ScalarField> {
     }
 
     fn assert_is_const(&self, ctx: &mut Context<'_, F>, a: &AssignedValue<F>, constant: F) {
-        let c_cell = ctx.assign_fixed(constant);
-        #[cfg(feature = "halo2-axiom")]
-        ctx.region.constrain_equal(a.cell(), &c_cell);
-        #[cfg(feature = "halo2-pse")]
-        ctx.region.constrain_equal(a.cell(), c_cell).unwrap();
+        ctx.shadow_check(
+            || value_to_option(a.value()).map_or(true, |a| *a == constant),
+            || format!("constant {constant:?} equal to {:?}", a.value()),
+        );
+        ctx.constrain_constant(a, constant);
     }
 
     /// Returns `(assignments, output)` where `output` is the inner product of `<a, b>`
@@ -612,6 +620,47 @@ pub trait GateInstructions<F: ScalarField> {
         range_bits: usize,
     ) -> Vec<AssignedValue<'v, F>>;
 
+    /// Returns the inverses of all of `a`, using Montgomery's trick so that only a single inverse is witnessed.
+    ///
+    /// Uses about `3 * a.len()` multiplications. Constrains all of `a` to be nonzero.
+    fn batch_invert<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        a: &[AssignedValue<'v, F>],
+    ) -> Vec<AssignedValue<'v, F>> {
+        if a.is_empty() {
+            return vec![];
+        }
+        // prefix[i] = a[0] * ... * a[i]
+        let mut prefix = Vec::with_capacity(a.len());
+        prefix.push(a[0].clone());
+        for a_i in a.iter().skip(1) {
+            let prod = self.mul(ctx, Existing(prefix.last().unwrap()), Existing(a_i));
+            prefix.push(prod);
+        }
+        // | 0 | prod | inv | 1 |
+        // constrains prod * inv = 1, so prod and hence all of `a` are nonzero
+        let prod = prefix.pop().unwrap();
+        let inv_val = prod.value().map(|prod| prod.invert().unwrap_or(F::zero()));
+        let mut inv = self
+            .assign_region(
+                ctx,
+                [Constant(F::zero()), Existing(&prod), Witness(inv_val), Constant(F::one())],
+                [(0, None)],
+            )
+            .swap_remove(2);
+
+        // `inv` is the inverse of a[0] * ... * a[i] at the start of iteration `i`
+        let mut inverses = Vec::with_capacity(a.len());
+        for (a_i, prefix) in a.iter().skip(1).zip(prefix.iter()).rev() {
+            inverses.push(self.mul(ctx, Existing(&inv), Existing(prefix)));
+            inv = self.mul(ctx, Existing(&inv), Existing(a_i));
+        }
+        inverses.push(inv);
+        inverses.reverse();
+        inverses
+    }
+
     /// given pairs `coords[i] = (x_i, y_i)`, let `f` be the unique degree `len(coords)` polynomial such that `f(x_i) = y_i` for all `i`.
     ///
     /// input: coords, x
@@ -625,36 +674,85 @@ pub trait GateInstructions<F: ScalarField> {
         coords: &[(AssignedValue<'v, F>, AssignedValue<'v, F>)],
         x: &AssignedValue<'v, F>,
     ) -> (AssignedValue<'v, F>, AssignedValue<'v, F>) {
-        let mut z = self.sub(ctx, Existing(x), Existing(&coords[0].0));
-        for coord in coords.iter().skip(1) {
-            let sub = self.sub(ctx, Existing(x), Existing(&coord.0));
-            z = self.mul(ctx, Existing(&z), Existing(&sub));
+        let diffs = coords
+            .iter()
+            .map(|(x_i, _)| self.sub(ctx, Existing(x), Existing(x_i)))
+            .collect::<Vec<_>>();
+        let mut z = diffs[0].clone();
+        for diff in diffs.iter().skip(1) {
+            z = self.mul(ctx, Existing(&z), Existing(diff));
         }
-        let mut eval = None;
-        for i in 0..coords.len() {
+        let mut denoms = Vec::with_capacity(coords.len());
+        for (i, diff) in diffs.into_iter().enumerate() {
             // compute (x - x_i) * Prod_{j != i} (x_i - x_j)
-            let mut denom = self.sub(ctx, Existing(x), Existing(&coords[i].0));
-            for j in 0..coords.len() {
+            let mut denom = diff;
+            for (j, (x_j, _)) in coords.iter().enumerate() {
                 if i == j {
                     continue;
                 }
-                let sub = self.sub(ctx, Existing(&coords[i].0), Existing(&coords[j].0));
+                let sub = self.sub(ctx, Existing(&coords[i].0), Existing(x_j));
                 denom = self.mul(ctx, Existing(&denom), Existing(&sub));
             }
-            // TODO: batch inversion
-            let is_zero = self.is_zero(ctx, &denom);
-            self.assert_is_const(ctx, &is_zero, F::zero());
-
-            // y_i / denom
-            let quot = self.div_unsafe(ctx, Existing(&coords[i].1), Existing(&denom));
-            eval = if let Some(eval) = eval {
-                let eval = self.add(ctx, Existing(&eval), Existing(&quot));
-                Some(eval)
-            } else {
-                Some(quot)
-            };
+            denoms.push(denom);
+        }
+        // also constrains all denominators are nonzero
+        let inv_denoms = self.batch_invert(ctx, &denoms);
+
+        // sum_i y_i / denom_i
+        let eval = self.inner_product(
+            ctx,
+            coords.iter().map(|(_, y_i)| Existing(y_i)),
+            inv_denoms.iter().map(Existing),
+        );
+        let out = self.mul(ctx, Existing(&eval), Existing(&z));
+        (out, z)
+    }
+
+    /// Same as [`GateInstructions::lagrange_and_eval`] for the fixed points `domain[i]`, i.e., `coords[i] = (domain[i], ys[i])`,
+    /// using the barycentric formula
+    /// `f(x) = Prod_i (x - x_i) * sum_i w_i * y_i / (x - x_i)` where the weights `w_i = 1 / Prod_{j != i} (x_i - x_j)` are constants.
+    ///
+    /// This uses `O(len(domain))` cells instead of `O(len(domain)^2)`.
+    ///
+    /// output: (f(x), Prod_i (x - x_i))
+    ///
+    /// constrains x is not in `domain`; the points of `domain` must be distinct
+    fn lagrange_and_eval_barycentric<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        domain: &[F],
+        ys: &[AssignedValue<'v, F>],
+        x: &AssignedValue<'v, F>,
+    ) -> (AssignedValue<'v, F>, AssignedValue<'v, F>) {
+        assert_eq!(domain.len(), ys.len());
+        assert!(!domain.is_empty());
+        let weights = domain.iter().enumerate().map(|(i, x_i)| {
+            domain
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| i != *j)
+                .fold(F::one(), |prod, (_, x_j)| prod * (*x_i - x_j))
+                .invert()
+                .unwrap()
+        });
+
+        let diffs =
+            domain.iter().map(|x_i| self.sub(ctx, Existing(x), Constant(*x_i))).collect::<Vec<_>>();
+        let mut z = diffs[0].clone();
+        for diff in diffs.iter().skip(1) {
+            z = self.mul(ctx, Existing(&z), Existing(diff));
         }
-        let out = self.mul(ctx, Existing(&eval.unwrap()), Existing(&z));
+        // also constrains x - x_i is nonzero
+        let inv_diffs = self.batch_invert(ctx, &diffs);
+
+        let eval = self.sum_products_with_coeff_and_var(
+            ctx,
+            weights
+                .zip(ys.iter().zip(inv_diffs.iter()))
+                .map(|(w_i, (y_i, inv_diff))| (w_i, Existing(y_i), Existing(inv_diff))),
+            Constant(F::zero()),
+        );
+        let out = self.mul(ctx, Existing(&eval), Existing(&z));
         (out, z)
     }
 }
@@ -858,6 +956,119 @@ pub trait RangeInstructions<F: ScalarField> {
         self.check_less_than(ctx, Existing(&assigned[4]), b, b_num_bits);
         (assigned[3].clone(), assigned[4].clone())
     }
+
+    /// Returns `a[i] op b[i]` for each `i`, constraining all of `a` and `b` to be bytes.
+    ///
+    /// The default implementation decomposes each byte into bits with [`GateInstructions::num_to_bits`].
+    /// [`RangeConfig`](range::RangeConfig) overrides it with a single lookup per byte into its byte lookup table.
+    fn bitwise_bytes<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        op: BitwiseOp,
+        a: &[AssignedValue<'a, F>],
+        b: &[AssignedValue<'a, F>],
+    ) -> Vec<AssignedValue<'a, F>> {
+        assert_eq!(a.len(), b.len());
+        let gate = self.gate();
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| {
+                let a_bits = gate.num_to_bits(ctx, a, 8);
+                let b_bits = gate.num_to_bits(ctx, b, 8);
+                let mut out_bits = Vec::with_capacity(8);
+                for (x, y) in a_bits.iter().zip(b_bits.iter()) {
+                    let out = match op {
+                        BitwiseOp::And => gate.and(ctx, Existing(x), Existing(y)),
+                        BitwiseOp::Or => gate.or(ctx, Existing(x), Existing(y)),
+                        // x xor y = x * (1 - 2y) + y
+                        BitwiseOp::Xor => {
+                            let not_2y = gate.mul_add(
+                                ctx,
+                                Constant(-F::from(2)),
+                                Existing(y),
+                                Constant(F::one()),
+                            );
+                            gate.mul_add(ctx, Existing(x), Existing(&not_2y), Existing(y))
+                        }
+                    };
+                    out_bits.push(out);
+                }
+                gate.inner_product(
+                    ctx,
+                    out_bits.iter().map(Existing),
+                    (0..8).map(|i| Constant(gate.pow_of_two()[i])),
+                )
+            })
+            .collect()
+    }
+
+    fn bitwise_xor_bytes<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &[AssignedValue<'a, F>],
+        b: &[AssignedValue<'a, F>],
+    ) -> Vec<AssignedValue<'a, F>> {
+        self.bitwise_bytes(ctx, BitwiseOp::Xor, a, b)
+    }
+
+    /// Constrains each of `bytes` to be a byte and returns the little-endian number `sum_i bytes[i] * 2^{8i}`
+    ///
+    /// The default implementation range checks each byte with [`range_check`](Self::range_check);
+    /// [`RangeConfig`](range::RangeConfig) uses its byte lookup table instead.
+    fn bytes_to_num<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        bytes: &[AssignedValue<'a, F>],
+    ) -> AssignedValue<'a, F> {
+        assert!(bytes.len() * 8 <= F::CAPACITY as usize);
+        for byte in bytes {
+            self.range_check(ctx, byte, 8);
+        }
+        let gate = self.gate();
+        gate.inner_product(
+            ctx,
+            bytes.iter().map(Existing),
+            (0..bytes.len()).map(|i| Constant(gate.pow_of_two()[8 * i])),
+        )
+    }
+
+    /// Returns the `num_bytes` little-endian bytes of `a`, each constrained to be a byte.
+    ///
+    /// The constraints fail if `a` does not fit in `num_bytes` bytes. The default implementation range checks each byte with
+    /// [`range_check`](Self::range_check); [`RangeConfig`](range::RangeConfig) uses its byte lookup table instead.
+    fn num_to_bytes<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        num_bytes: usize,
+    ) -> Vec<AssignedValue<'a, F>> {
+        // so the decomposition is unique
+        assert!(num_bytes * 8 <= F::CAPACITY as usize);
+        let bytes = a
+            .value()
+            .map(|a| {
+                a.to_repr().as_ref()[..num_bytes]
+                    .iter()
+                    .map(|byte| F::from(*byte as u64))
+                    .collect::<Vec<_>>()
+            })
+            .transpose_vec(num_bytes);
+
+        let gate = self.gate();
+        let mut byte_cells = Vec::with_capacity(num_bytes);
+        let acc = gate.inner_product_left(
+            ctx,
+            bytes.into_iter().map(Witness),
+            (0..num_bytes).map(|i| Constant(gate.pow_of_two()[8 * i])),
+            &mut byte_cells,
+        );
+        ctx.constrain_equal(a, &acc);
+
+        for byte in &byte_cells {
+            self.range_check(ctx, byte, 8);
+        }
+        byte_cells
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,195 @@
+//! Sorting and multiset equality of arrays of assigned values.
+//!
+//! Sorting an array is proven in two parts: in the first phase the sorted array is witnessed and each pair of neighbors is
+//! constrained to be in order; in a later phase the sorted array is constrained to be a permutation of the original array,
+//! by comparing the grand products `prod_i (gamma - a[i])` at a verifier challenge `gamma`. The challenge must be drawn after both arrays
+//! are committed to, i.e., the arrays are assigned in an earlier phase than the one the products are computed in (see
+//! [`Context::next_phase`](crate::Context::next_phase)). Then by the Schwartz-Zippel lemma two arrays of length `n` which are not
+//! permutations of each other pass with probability at most `n / |F|`.
+use super::{rlc::AssignedChallenge, GateInstructions, RangeInstructions};
+use crate::{
+    halo2_proofs::circuit::Value,
+    utils::{fe_to_biguint, value_to_option, PrimeField},
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use std::marker::PhantomData;
+
+/// Chip for sorting arrays and checking that two arrays are permutations of each other
+#[derive(Clone, Debug)]
+pub struct PermutationChip<'r, F: PrimeField, R: RangeInstructions<F>> {
+    pub range: &'r R,
+    _marker: PhantomData<F>,
+}
+
+impl<'r, F: PrimeField, R: RangeInstructions<F>> PermutationChip<'r, F, R> {
+    pub fn construct(range: &'r R) -> Self {
+        Self { range, _marker: PhantomData }
+    }
+
+    pub fn gate(&self) -> &R::Gate {
+        self.range.gate()
+    }
+
+    /// Constrains `a[i] <= a[i + 1]` for all `i`.
+    ///
+    /// Assumes all of `a` have at most `num_bits` bits.
+    pub fn assert_sorted<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &[AssignedValue<'a, F>],
+        num_bits: usize,
+    ) {
+        for pair in a.windows(2) {
+            // a[i + 1] - a[i] wraps around the modulus unless a[i] <= a[i + 1]
+            let diff = self.gate().sub(ctx, Existing(&pair[1]), Existing(&pair[0]));
+            self.range.range_check(ctx, &diff, num_bits);
+        }
+    }
+
+    /// Returns the witnessed array `a` sorted in increasing order, constrained to be sorted but not yet to be a permutation of `a`.
+    ///
+    /// Call [`PermutationChip::check_permutation`] on `a` and the output in a later phase to complete the proof.
+    /// Assumes all of `a` have at most `num_bits` bits.
+    pub fn sort<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &[AssignedValue<'a, F>],
+        num_bits: usize,
+    ) -> Vec<AssignedValue<'a, F>> {
+        let values =
+            a.iter().map(|x| value_to_option(x.value()).copied()).collect::<Option<Vec<_>>>();
+        let sorted: Vec<_> = match values {
+            Some(mut values) => {
+                values.sort_by_cached_key(fe_to_biguint);
+                values.into_iter().map(Value::known).collect()
+            }
+            None => vec![Value::unknown(); a.len()],
+        };
+        let sorted = self.gate().assign_witnesses(ctx, sorted);
+        self.assert_sorted(ctx, &sorted, num_bits);
+        sorted
+    }
+
+    /// Returns the witnessed records `(key, value)` stably sorted by key, constrained to be sorted but not yet to be a permutation of `records`.
+    ///
+    /// Call [`PermutationChip::check_keyed_permutation`] on `records` and the output in a later phase to complete the proof.
+    /// The keys and values of the output are range checked to `key_bits` and `value_bits` bits, see [`PermutationChip::assert_sorted_by_key`].
+    pub fn sort_by_key<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        records: &[(AssignedValue<'a, F>, AssignedValue<'a, F>)],
+        key_bits: usize,
+        value_bits: usize,
+    ) -> Vec<(AssignedValue<'a, F>, AssignedValue<'a, F>)> {
+        let values = records
+            .iter()
+            .map(|(key, value)| {
+                value_to_option(key.value().zip(value.value())).map(|(k, v)| (*k, *v))
+            })
+            .collect::<Option<Vec<_>>>();
+        let sorted: Vec<_> = match values {
+            Some(mut values) => {
+                values.sort_by_cached_key(|(key, _)| fe_to_biguint(key));
+                values.into_iter().map(|(k, v)| (Value::known(k), Value::known(v))).collect()
+            }
+            None => vec![(Value::unknown(), Value::unknown()); records.len()],
+        };
+        let sorted = sorted
+            .into_iter()
+            .map(|(key, value)| {
+                let mut cells = self.gate().assign_witnesses(ctx, [key, value]);
+                let value = cells.pop().unwrap();
+                (cells.pop().unwrap(), value)
+            })
+            .collect::<Vec<_>>();
+        self.assert_sorted_by_key(ctx, &sorted, key_bits, value_bits);
+        sorted
+    }
+
+    /// Constrains the records `(key, value)` to be sorted by key, with all keys of at most `key_bits` bits and all values of at most
+    /// `value_bits` bits.
+    ///
+    /// The range checks make the packing of records in [`PermutationChip::check_keyed_permutation`] injective, so records witnessed
+    /// by the prover must pass through this before they are compared.
+    pub fn assert_sorted_by_key<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        records: &[(AssignedValue<'a, F>, AssignedValue<'a, F>)],
+        key_bits: usize,
+        value_bits: usize,
+    ) {
+        assert!(
+            key_bits + value_bits <= F::CAPACITY as usize,
+            "records of {key_bits} bit keys and {value_bits} bit values do not fit in a field element"
+        );
+        for (key, value) in records {
+            self.range.range_check(ctx, key, key_bits);
+            self.range.range_check(ctx, value, value_bits);
+        }
+        let keys = records.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+        self.assert_sorted(ctx, &keys, key_bits);
+    }
+
+    /// Returns `prod_i (gamma - a[i])`
+    pub fn grand_product<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &[AssignedValue<'a, F>],
+        gamma: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let mut prod = self.gate().load_constant(ctx, F::one());
+        for a_i in a {
+            let diff = self.gate().sub(ctx, Existing(gamma), Existing(a_i));
+            prod = self.gate().mul(ctx, Existing(&prod), Existing(&diff));
+        }
+        prod
+    }
+
+    /// Constrains `b` to be a permutation of `a`, i.e., equal as multisets.
+    ///
+    /// `gamma` is the challenge loaded with [`RlcConfig::load_gamma`](super::rlc::RlcConfig::load_gamma), which is drawn after all of
+    /// `a` and `b` are assigned.
+    pub fn check_permutation<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &[AssignedValue<'a, F>],
+        b: &[AssignedValue<'a, F>],
+        gamma: &AssignedChallenge<'a, F>,
+    ) {
+        assert_eq!(a.len(), b.len(), "arrays of different lengths are not permutations");
+        assert!(ctx.current_phase() > 0, "the challenge is only available after the first phase");
+        let prod_a = self.grand_product(ctx, a, gamma.assigned());
+        let prod_b = self.grand_product(ctx, b, gamma.assigned());
+        ctx.constrain_equal(&prod_a, &prod_b);
+    }
+
+    /// Constrains the records `b` to be a permutation of the records `a`.
+    ///
+    /// Each record `(key, value)` is packed into the single field element `key * 2^value_bits + value`, which is injective only if all
+    /// values have at most `value_bits` bits and all keys have at most `F::CAPACITY - value_bits` bits. Otherwise e.g. the record
+    /// `(key - 1, value + 2^value_bits)` packs to the same element as `(key, value)`. The records of `a` are assumed to be in range,
+    /// and the records of `b` must be constrained to be in range, as [`PermutationChip::sort_by_key`] does with the same `value_bits`.
+    ///
+    /// `gamma` is the challenge loaded with [`RlcConfig::load_gamma`](super::rlc::RlcConfig::load_gamma), see [`PermutationChip::check_permutation`].
+    pub fn check_keyed_permutation<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &[(AssignedValue<'a, F>, AssignedValue<'a, F>)],
+        b: &[(AssignedValue<'a, F>, AssignedValue<'a, F>)],
+        value_bits: usize,
+        gamma: &AssignedChallenge<'a, F>,
+    ) {
+        let shift = self.gate().pow_of_two()[value_bits];
+        let pack = |records: &[(AssignedValue<'a, F>, AssignedValue<'a, F>)]| {
+            records
+                .iter()
+                .map(|(key, value)| {
+                    self.gate().mul_add(ctx, Existing(key), Constant(shift), Existing(value))
+                })
+                .collect::<Vec<_>>()
+        };
+        let [a, b] = [a, b].map(pack);
+        self.check_permutation(ctx, &a, &b, gamma);
+    }
+}
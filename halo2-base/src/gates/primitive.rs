@@ -0,0 +1,314 @@
+//! Unsigned integers of a fixed bit width, such as `u8`, `u32`, `u64`, with the overflow behavior of Rust's integer types.
+//!
+//! Every [`AssignedPrimitive`] created by [`PrimitiveChip`] is constrained to be less than `2^T::BITS`, so operations do not need to
+//! track bit bounds: `wrapping_*` operations reduce modulo `2^T::BITS`, while `checked_*` operations constrain that there is no overflow.
+use super::{range::BitwiseOp, GateInstructions, RangeInstructions};
+use crate::{
+    halo2_proofs::circuit::Value,
+    utils::{PrimeField, Primitive},
+    AssignedPrimitive, AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use num_bigint::BigUint;
+use num_traits::One;
+use std::marker::PhantomData;
+
+/// Chip for [`AssignedPrimitive`] operations. The bitwise operations `and`, `or`, `xor` require the byte lookup table of
+/// [`RangeConfig`](super::range::RangeConfig) to be configured with the corresponding [`BitwiseOp`].
+#[derive(Clone, Debug)]
+pub struct PrimitiveChip<'r, F: PrimeField, R: RangeInstructions<F>> {
+    pub range: &'r R,
+    _marker: PhantomData<F>,
+}
+
+impl<'r, F: PrimeField, R: RangeInstructions<F>> PrimitiveChip<'r, F, R> {
+    pub fn construct(range: &'r R) -> Self {
+        Self { range, _marker: PhantomData }
+    }
+
+    pub fn gate(&self) -> &R::Gate {
+        self.range.gate()
+    }
+
+    /// Assigns `a` and range checks it to `T::BITS` bits
+    pub fn load_witness<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: Value<T>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let assigned = self.gate().load_witness(ctx, a.map(|a| F::from(Into::<u64>::into(a))));
+        self.from_assigned(ctx, assigned)
+    }
+
+    pub fn load_constant<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: T,
+    ) -> AssignedPrimitive<'a, T, F> {
+        AssignedPrimitive::new_unchecked(
+            self.gate().load_constant(ctx, F::from(Into::<u64>::into(a))),
+        )
+    }
+
+    /// Range checks `a` to `T::BITS` bits
+    pub fn from_assigned<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: AssignedValue<'a, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        self.range.range_check(ctx, &a, T::BITS);
+        AssignedPrimitive::new_unchecked(a)
+    }
+
+    /// Returns `(a / 2^T::BITS, a mod 2^T::BITS)` for `a` of at most `a_num_bits` bits
+    fn reduce<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        a_num_bits: usize,
+    ) -> (AssignedValue<'a, F>, AssignedPrimitive<'a, T, F>) {
+        let (hi, lo) = self.range.div_mod(ctx, Existing(a), BigUint::one() << T::BITS, a_num_bits);
+        (hi, AssignedPrimitive::new_unchecked(lo))
+    }
+
+    /// Returns `(a + b) mod 2^T::BITS` and whether the addition overflowed, as a bit
+    pub fn overflowing_add<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> (AssignedPrimitive<'a, T, F>, AssignedValue<'a, F>) {
+        let sum = self.gate().add(ctx, Existing(&a.assigned), Existing(&b.assigned));
+        // the carry is less than 2 because a + b < 2^(T::BITS + 1) - 1
+        let (carry, out) = self.reduce(ctx, &sum, T::BITS + 1);
+        (out, carry)
+    }
+
+    pub fn wrapping_add<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        self.overflowing_add(ctx, a, b).0
+    }
+
+    /// Returns `a + b` and constrains that it does not overflow
+    pub fn checked_add<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let sum = self.gate().add(ctx, Existing(&a.assigned), Existing(&b.assigned));
+        self.from_assigned(ctx, sum)
+    }
+
+    /// Returns `(a - b) mod 2^T::BITS` and whether the subtraction underflowed, as a bit
+    pub fn overflowing_sub<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> (AssignedPrimitive<'a, T, F>, AssignedValue<'a, F>) {
+        // a - b + 2^T::BITS is in [1, 2^(T::BITS + 1))
+        let shifted = self.gate().add(
+            ctx,
+            Existing(&a.assigned),
+            Constant(self.gate().pow_of_two()[T::BITS]),
+        );
+        let diff = self.gate().sub(ctx, Existing(&shifted), Existing(&b.assigned));
+        let (no_borrow, out) = self.reduce(ctx, &diff, T::BITS + 1);
+        let borrow = self.gate().not(ctx, Existing(&no_borrow));
+        (out, borrow)
+    }
+
+    pub fn wrapping_sub<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        self.overflowing_sub(ctx, a, b).0
+    }
+
+    /// Returns `a - b` and constrains that it does not underflow
+    pub fn checked_sub<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let diff = self.gate().sub(ctx, Existing(&a.assigned), Existing(&b.assigned));
+        self.from_assigned(ctx, diff)
+    }
+
+    pub fn wrapping_mul<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let prod = self.gate().mul(ctx, Existing(&a.assigned), Existing(&b.assigned));
+        self.reduce(ctx, &prod, 2 * T::BITS).1
+    }
+
+    /// Returns `a * b` and constrains that it does not overflow
+    pub fn checked_mul<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let prod = self.gate().mul(ctx, Existing(&a.assigned), Existing(&b.assigned));
+        self.from_assigned(ctx, prod)
+    }
+
+    /// Returns `(a << shift) mod 2^T::BITS`. Requires `shift < T::BITS`.
+    pub fn shl<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        shift: usize,
+    ) -> AssignedPrimitive<'a, T, F> {
+        assert!(shift < T::BITS);
+        let shifted =
+            self.gate().mul(ctx, Existing(&a.assigned), Constant(self.gate().pow_of_two()[shift]));
+        self.reduce(ctx, &shifted, T::BITS + shift).1
+    }
+
+    /// Returns `a >> shift`. Requires `shift < T::BITS`.
+    pub fn shr<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        shift: usize,
+    ) -> AssignedPrimitive<'a, T, F> {
+        assert!(shift < T::BITS);
+        let (hi, _) =
+            self.range.div_mod(ctx, Existing(&a.assigned), BigUint::one() << shift, T::BITS);
+        AssignedPrimitive::new_unchecked(hi)
+    }
+
+    /// Returns `a` with its bits rotated left by `shift`, like `T::rotate_left`
+    pub fn rotate_left<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        shift: usize,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let shift = shift % T::BITS;
+        if shift == 0 {
+            return a.clone();
+        }
+        // a = hi * 2^(T::BITS - shift) + lo with hi < 2^shift, so the rotation is lo * 2^shift + hi
+        let (hi, lo) = self.range.div_mod(
+            ctx,
+            Existing(&a.assigned),
+            BigUint::one() << (T::BITS - shift),
+            T::BITS,
+        );
+        let out = self.gate().mul_add(
+            ctx,
+            Existing(&lo),
+            Constant(self.gate().pow_of_two()[shift]),
+            Existing(&hi),
+        );
+        AssignedPrimitive::new_unchecked(out)
+    }
+
+    /// Returns `a` with its bits rotated right by `shift`, like `T::rotate_right`
+    pub fn rotate_right<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        shift: usize,
+    ) -> AssignedPrimitive<'a, T, F> {
+        self.rotate_left(ctx, a, T::BITS - shift % T::BITS)
+    }
+
+    /// Returns `!a`, i.e., `2^T::BITS - 1 - a`
+    pub fn not<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let max = self.gate().pow_of_two()[T::BITS] - F::one();
+        let out = self.gate().sub(ctx, Constant(max), Existing(&a.assigned));
+        AssignedPrimitive::new_unchecked(out)
+    }
+
+    /// Returns `a op b` bytewise, using the byte lookup table
+    pub fn bitwise<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        op: BitwiseOp,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let num_bytes = T::BITS / 8;
+        let a_bytes = self.range.num_to_bytes(ctx, &a.assigned, num_bytes);
+        let b_bytes = self.range.num_to_bytes(ctx, &b.assigned, num_bytes);
+        let out_bytes = self.range.bitwise_bytes(ctx, op, &a_bytes, &b_bytes);
+        AssignedPrimitive::new_unchecked(self.range.bytes_to_num(ctx, &out_bytes))
+    }
+
+    pub fn and<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        self.bitwise(ctx, BitwiseOp::And, a, b)
+    }
+
+    pub fn or<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        self.bitwise(ctx, BitwiseOp::Or, a, b)
+    }
+
+    pub fn xor<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        self.bitwise(ctx, BitwiseOp::Xor, a, b)
+    }
+
+    /// Returns whether `a < b`
+    pub fn is_less_than<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedValue<'a, F> {
+        self.range.is_less_than(ctx, Existing(&a.assigned), Existing(&b.assigned), T::BITS)
+    }
+
+    pub fn is_equal<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+    ) -> AssignedValue<'a, F> {
+        self.gate().is_equal(ctx, Existing(&a.assigned), Existing(&b.assigned))
+    }
+
+    /// Returns `a` if `sel` is 1 and `b` if `sel` is 0. Assumes `sel` is a bit.
+    pub fn select<'a, T: Primitive>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedPrimitive<'a, T, F>,
+        b: &AssignedPrimitive<'a, T, F>,
+        sel: &AssignedValue<'a, F>,
+    ) -> AssignedPrimitive<'a, T, F> {
+        let out =
+            self.gate().select(ctx, Existing(&a.assigned), Existing(&b.assigned), Existing(sel));
+        AssignedPrimitive::new_unchecked(out)
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
         flex_gate::{FlexGateConfig, GateStrategy, MAX_PHASE},
         GateInstructions,
     },
-    utils::{decompose_fe_to_u64_limbs, value_to_option, ScalarField},
+    utils::{decompose_fe_to_u64_limbs, fe_to_biguint, value_to_option, ScalarField},
     AssignedValue,
     QuantumCell::{self, Constant, Existing, Witness},
 };
@@ -11,7 +11,8 @@ use crate::{
     halo2_proofs::{
         circuit::{Layouter, Value},
         plonk::{
-            Advice, Column, ConstraintSystem, Error, SecondPhase, Selector, TableColumn, ThirdPhase,
+            Advice, Column, ConstraintSystem, Error, Fixed, SecondPhase, Selector, TableColumn,
+            ThirdPhase,
         },
         poly::Rotation,
     },
@@ -29,6 +30,156 @@ pub enum RangeStrategy {
     // CustomHorizontal, // vertical basic gate and dedicated horizontal custom gate
 }
 
+/// Bitwise operation on bytes, computed with the byte lookup table of [`ByteLookupConfig`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitwiseOp {
+    Xor,
+    And,
+    Or,
+}
+
+impl BitwiseOp {
+    /// The tag of the rows `(tag, a, b, a op b)` of this operation in the byte lookup table
+    pub fn tag(&self) -> u64 {
+        match self {
+            BitwiseOp::Xor => 1,
+            BitwiseOp::And => 2,
+            BitwiseOp::Or => 3,
+        }
+    }
+
+    pub fn apply(&self, a: u8, b: u8) -> u8 {
+        match self {
+            BitwiseOp::Xor => a ^ b,
+            BitwiseOp::And => a & b,
+            BitwiseOp::Or => a | b,
+        }
+    }
+}
+
+/// Fixed lookup table for byte decomposition and bitwise operations on bytes, all in the first phase.
+///
+/// The table has rows `(tag, a, b, out)`:
+/// * `(0, x, 0, 0)` for each byte `x`, to check that `x` is a byte
+/// * `(op.tag(), a, b, op(a, b))` for each pair of bytes `a, b` and each `op` in `ops`
+///
+/// so it has `2^8 + 2^16 * ops.len()` rows.
+///
+/// The looked up cells are copied to `lookup_advice` at the end of the phase, with the tag of each row in the fixed column `lookup_tag`.
+/// The unused rows of these columns are all zero, which is a row of the table, so no selector is needed.
+#[derive(Clone, Debug)]
+pub struct ByteLookupConfig {
+    /// `[tag, a, b, out]`
+    pub table: [TableColumn; 4],
+    pub ops: Vec<BitwiseOp>,
+    /// `[a, b, out]`
+    pub lookup_advice: Vec<[Column<Advice>; 3]>,
+    pub lookup_tag: Vec<Column<Fixed>>,
+}
+
+impl ByteLookupConfig {
+    pub const BYTE_TAG: u64 = 0;
+
+    pub fn configure<F: ScalarField>(
+        meta: &mut ConstraintSystem<F>,
+        ops: &[BitwiseOp],
+        num_lookup_advice: usize,
+        // params.k()
+        circuit_degree: usize,
+    ) -> Self {
+        assert!(
+            (1usize << 8) + (1usize << 16) * ops.len() < 1usize << circuit_degree,
+            "byte lookup table does not fit in 2^{circuit_degree} rows"
+        );
+        let table = [(); 4].map(|_| meta.lookup_table_column());
+        let lookup_advice: Vec<_> = (0..num_lookup_advice)
+            .map(|_| {
+                [(); 3].map(|_| {
+                    let a = meta.advice_column();
+                    meta.enable_equality(a);
+                    a
+                })
+            })
+            .collect();
+        let lookup_tag: Vec<_> = (0..num_lookup_advice).map(|_| meta.fixed_column()).collect();
+
+        for (advice, tag) in lookup_advice.iter().zip(lookup_tag.iter()) {
+            meta.lookup("byte lookup", |meta| {
+                let tag = meta.query_fixed(*tag, Rotation::cur());
+                let [a, b, out] = advice.map(|c| meta.query_advice(c, Rotation::cur()));
+                vec![(tag, table[0]), (a, table[1]), (b, table[2]), (out, table[3])]
+            });
+        }
+
+        Self { table, ops: ops.to_vec(), lookup_advice, lookup_tag }
+    }
+
+    pub fn load_lookup_table<F: ScalarField>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "byte lookup",
+            |mut table| {
+                let rows = (0..=u8::MAX).map(|x| [Self::BYTE_TAG, x as u64, 0, 0]).chain(
+                    self.ops.iter().flat_map(|op| {
+                        (0..=u8::MAX).flat_map(move |a| {
+                            (0..=u8::MAX)
+                                .map(move |b| [op.tag(), a as u64, b as u64, op.apply(a, b) as u64])
+                        })
+                    }),
+                );
+                for (offset, row) in rows.enumerate() {
+                    for (column, val) in self.table.iter().zip(row) {
+                        table.assign_cell(
+                            || "byte lookup table",
+                            *column,
+                            offset,
+                            || Value::known(F::from(val)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Call this at the end of the first phase to copy the cells in `ctx.cells_to_byte_lookup` to `lookup_advice`
+    ///
+    /// returns total number of rows looked up
+    pub fn finalize<F: ScalarField>(&self, ctx: &mut Context<'_, F>) -> usize {
+        if ctx.cells_to_byte_lookup.is_empty() {
+            return 0;
+        }
+        assert_eq!(ctx.current_phase(), 0, "byte lookups are only supported in the first phase");
+        let rows = std::mem::take(&mut ctx.cells_to_byte_lookup);
+        let total_rows = rows.len();
+        let mut rows = rows.iter();
+        for (advice, tag_column) in self.lookup_advice.iter().zip(self.lookup_tag.iter()) {
+            for (offset, (tag, cells)) in rows.by_ref().take(ctx.max_rows).enumerate() {
+                for (acell, column) in cells.iter().zip(advice) {
+                    acell.copy_advice(ctx.region(), *column, offset);
+                }
+                #[cfg(feature = "halo2-axiom")]
+                ctx.region().assign_fixed(
+                    *tag_column,
+                    offset,
+                    crate::halo2_proofs::plonk::Assigned::Trivial(F::from(*tag)),
+                );
+                #[cfg(feature = "halo2-pse")]
+                ctx.region()
+                    .assign_fixed(|| "", *tag_column, offset, || Value::known(F::from(*tag)))
+                    .expect("assign fixed should not fail");
+            }
+        }
+        if rows.next().is_some() {
+            panic!("NOT ENOUGH ADVICE COLUMNS WITH BYTE LOOKUP ENABLED");
+        }
+        total_rows
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RangeConfig<F: ScalarField> {
     // `lookup_advice` are special advice columns only used for lookups
@@ -48,6 +199,8 @@ pub struct RangeConfig<F: ScalarField> {
     // `q_range[k][i]` stores the selector for a custom range gate of length `k`
     // pub q_range: HashMap<usize, Vec<Selector>>,
     pub gate: FlexGateConfig<F>,
+    // optional lookup table for bytes and bitwise operations on bytes
+    pub byte_lookup: Option<ByteLookupConfig>,
     strategy: RangeStrategy,
     pub context_id: usize,
 }
@@ -63,6 +216,37 @@ impl<F: ScalarField> RangeConfig<F> {
         context_id: usize,
         // params.k()
         circuit_degree: usize,
+    ) -> Self {
+        Self::configure_with_byte_lookup(
+            meta,
+            range_strategy,
+            num_advice,
+            num_lookup_advice,
+            num_fixed,
+            lookup_bits,
+            context_id,
+            circuit_degree,
+            &[],
+            0,
+        )
+    }
+
+    /// Same as [`RangeConfig::configure`], additionally with a byte lookup table for the bitwise operations `byte_ops`
+    /// and `num_byte_lookup_advice` triples of advice columns for it, see [`ByteLookupConfig`].
+    ///
+    /// If `num_byte_lookup_advice` is 0, there is no byte lookup table.
+    pub fn configure_with_byte_lookup(
+        meta: &mut ConstraintSystem<F>,
+        range_strategy: RangeStrategy,
+        num_advice: &[usize],
+        num_lookup_advice: &[usize],
+        num_fixed: usize,
+        lookup_bits: usize,
+        context_id: usize,
+        // params.k()
+        circuit_degree: usize,
+        byte_ops: &[BitwiseOp],
+        num_byte_lookup_advice: usize,
     ) -> Self {
         assert!(lookup_bits <= 28);
         let lookup = meta.lookup_table_column();
@@ -118,6 +302,9 @@ impl<F: ScalarField> RangeConfig<F> {
             lookup_bits,
             limb_bases,
             gate,
+            byte_lookup: (num_byte_lookup_advice != 0).then(|| {
+                ByteLookupConfig::configure(meta, byte_ops, num_byte_lookup_advice, circuit_degree)
+            }),
             strategy: range_strategy,
             context_id,
         };
@@ -161,25 +348,65 @@ impl<F: ScalarField> RangeConfig<F> {
                 Ok(())
             },
         )?;
+        if let Some(byte_lookup) = &self.byte_lookup {
+            byte_lookup.load_lookup_table(layouter)?;
+        }
         Ok(())
     }
 
     /// Call this at the end of a phase to assign cells to special columns for lookup arguments
     ///
-    /// returns total number of lookup cells assigned
+    /// returns total number of lookup cells assigned, plus the number of rows looked up in the byte lookup table
     pub fn finalize(&self, ctx: &mut Context<'_, F>) -> usize {
-        ctx.copy_and_lookup_cells(self.lookup_advice[ctx.current_phase].clone())
+        if let Some(Some(q)) = self.q_lookup.get(ctx.current_phase()) {
+            // only cells of virtual contexts are still queued here, since there was no row to enable the lookup at before
+            for acell in std::mem::take(&mut ctx.cells_to_lookup) {
+                q.enable(ctx.region(), acell.row()).expect("enable selector should not fail");
+            }
+        }
+        let mut total = ctx.copy_and_lookup_cells(self.lookup_advice[ctx.current_phase].clone());
+        if let Some(byte_lookup) = &self.byte_lookup {
+            total += byte_lookup.finalize(ctx);
+        }
+        total
+    }
+
+    fn byte_lookup(&self) -> &ByteLookupConfig {
+        self.byte_lookup.as_ref().expect("byte lookup table should be configured")
+    }
+
+    /// If shadow checks are enabled, checks that the value of `a` has at most `bits` bits, as the lookup of `a` in `table` requires
+    fn shadow_check_bits(
+        &self,
+        ctx: &Context<'_, F>,
+        a: &AssignedValue<F>,
+        bits: usize,
+        table: &str,
+    ) {
+        ctx.shadow_check(
+            || value_to_option(a.value()).map_or(true, |a| fe_to_biguint(a).bits() <= bits as u64),
+            || format!("{table} lookup of {:?} at row {}", a.value(), a.row()),
+        );
+    }
+
+    /// Constrains `a` to be a byte using the byte lookup table
+    fn enable_byte_lookup<'a>(&self, ctx: &mut Context<'a, F>, a: AssignedValue<'a, F>) {
+        self.byte_lookup();
+        self.shadow_check_bits(ctx, &a, 8, "byte");
+        ctx.cells_to_byte_lookup.push((ByteLookupConfig::BYTE_TAG, vec![a]));
     }
 
     /// assuming this is called when ctx.region is not in shape mode
     /// `offset` is the offset of the cell in `ctx.region`
     /// `offset` is only used if there is a single advice column
     fn enable_lookup<'a>(&self, ctx: &mut Context<'a, F>, acell: AssignedValue<'a, F>) {
+        self.shadow_check_bits(ctx, &acell, self.lookup_bits, "range");
         let phase = ctx.current_phase();
-        if let Some(q) = &self.q_lookup[phase] {
-            q.enable(&mut ctx.region, acell.row()).expect("enable selector should not fail");
-        } else {
-            ctx.cells_to_lookup.push(acell);
+        match &self.q_lookup[phase] {
+            Some(q) if !ctx.is_virtual() => {
+                q.enable(ctx.region(), acell.row()).expect("enable selector should not fail");
+            }
+            _ => ctx.push_cell_to_lookup(acell),
         }
     }
 
@@ -220,7 +447,7 @@ impl<F: ScalarField> RangeConfig<F> {
                 ),
             };
             // the inner product above must equal `a`
-            ctx.region.constrain_equal(a.cell(), acc.cell());
+            ctx.constrain_equal(a, &acc);
         };
         assert_eq!(limbs_assigned.len(), k);
 
@@ -434,4 +661,79 @@ impl<F: ScalarField> RangeInstructions<F> for RangeConfig<F> {
         let res = self.gate().is_zero(ctx, limbs.borrow().get(k).unwrap());
         res
     }
+    fn bitwise_bytes<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        op: BitwiseOp,
+        a: &[AssignedValue<'a, F>],
+        b: &[AssignedValue<'a, F>],
+    ) -> Vec<AssignedValue<'a, F>> {
+        assert_eq!(a.len(), b.len());
+        assert!(
+            self.byte_lookup().ops.contains(&op),
+            "byte lookup table should be configured with {op:?}"
+        );
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| {
+                self.shadow_check_bits(ctx, a, 8, "bitwise");
+                self.shadow_check_bits(ctx, b, 8, "bitwise");
+                let out_val = a.value().zip(b.value()).map(|(a, b)| {
+                    F::from(op.apply(a.get_lower_32() as u8, b.get_lower_32() as u8) as u64)
+                });
+                let out = self.gate.load_witness(ctx, out_val);
+                ctx.cells_to_byte_lookup.push((op.tag(), vec![a.clone(), b.clone(), out.clone()]));
+                out
+            })
+            .collect()
+    }
+
+    fn bytes_to_num<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        bytes: &[AssignedValue<'a, F>],
+    ) -> AssignedValue<'a, F> {
+        assert!(bytes.len() * 8 <= F::CAPACITY as usize);
+        for byte in bytes {
+            self.enable_byte_lookup(ctx, byte.clone());
+        }
+        self.gate.inner_product(
+            ctx,
+            bytes.iter().map(Existing),
+            (0..bytes.len()).map(|i| Constant(self.gate.pow_of_two[8 * i])),
+        )
+    }
+
+    fn num_to_bytes<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        num_bytes: usize,
+    ) -> Vec<AssignedValue<'a, F>> {
+        // so the decomposition is unique
+        assert!(num_bytes * 8 <= F::CAPACITY as usize);
+        let bytes = a
+            .value()
+            .map(|a| {
+                a.to_repr().as_ref()[..num_bytes]
+                    .iter()
+                    .map(|byte| F::from(*byte as u64))
+                    .collect::<Vec<_>>()
+            })
+            .transpose_vec(num_bytes);
+
+        let mut byte_cells = Vec::with_capacity(num_bytes);
+        let acc = self.gate.inner_product_left(
+            ctx,
+            bytes.into_iter().map(Witness),
+            (0..num_bytes).map(|i| Constant(self.gate.pow_of_two[8 * i])),
+            &mut byte_cells,
+        );
+        ctx.constrain_equal(a, &acc);
+
+        for byte in &byte_cells {
+            self.enable_byte_lookup(ctx, byte.clone());
+        }
+        byte_cells
+    }
 }
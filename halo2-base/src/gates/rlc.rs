@@ -0,0 +1,209 @@
+//! Random linear combinations of arrays of assigned values.
+//!
+//! The RLC of `a[0], ..., a[n - 1]` at `gamma` is `a[0] * gamma^(n - 1) + ... + a[n - 2] * gamma + a[n - 1]`. When `gamma` is a
+//! verifier challenge drawn after all entries are committed to, two different arrays of length at most `n` have the same RLC with
+//! probability at most `n / |F|`, so an array can be compared with a single cell. The RLC of a concatenation is
+//! `rlc(a ++ b) = rlc(a) * gamma^len(b) + rlc(b)`, which makes it cheap to constrain arrays of variable length to be concatenations
+//! of each other.
+//!
+//! The arrays are assigned in the first phase and the RLCs are computed in the second phase, after the challenge is drawn (see
+//! [`Context::next_phase`] and [`Context::get_challenge`]).
+use super::GateInstructions;
+use crate::{
+    halo2_proofs::{
+        plonk::{Advice, Challenge, Column, ConstraintSystem, FirstPhase, SecondPhase, Selector},
+        poly::Rotation,
+    },
+    utils::ScalarField,
+    AssignedValue, Context,
+    QuantumCell::{Existing, Witness},
+};
+
+/// Configuration for loading the verifier challenge `gamma`, drawn after the first phase, into an assigned cell.
+///
+/// The challenge is a value in the verifier's transcript and not a cell, so it is copied to the `gamma` column of this config and
+/// constrained by the gate `q_gamma * (gamma - challenge) = 0`. From then on it can be used in any second phase gate.
+#[derive(Clone, Debug)]
+pub struct RlcConfig {
+    pub challenge: Challenge,
+    /// second phase advice column holding the challenge
+    pub gamma: Column<Advice>,
+    pub q_gamma: Selector,
+}
+
+impl RlcConfig {
+    pub fn configure<F: ScalarField>(meta: &mut ConstraintSystem<F>) -> Self {
+        let challenge = meta.challenge_usable_after(FirstPhase);
+        let gamma = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(gamma);
+        let q_gamma = meta.selector();
+
+        meta.create_gate("gamma = challenge", |meta| {
+            let q = meta.query_selector(q_gamma);
+            let gamma = meta.query_advice(gamma, Rotation::cur());
+            let challenge = meta.query_challenge(challenge);
+            vec![q * (gamma - challenge)]
+        });
+
+        Self { challenge, gamma, q_gamma }
+    }
+
+    /// Returns a cell constrained to equal the challenge, which must be registered with [`Context::set_challenge`] for phase 0.
+    ///
+    /// Call this in the second phase. The first call in a region assigns the cell to the first row of the `gamma` column and
+    /// caches it on `ctx`; later calls return the same cell.
+    pub fn load_gamma<'a, F: ScalarField>(
+        &self,
+        ctx: &mut Context<'a, F>,
+    ) -> AssignedChallenge<'a, F> {
+        assert_eq!(ctx.current_phase(), 1, "the challenge can only be loaded in the second phase");
+        if let Some(gamma) = &ctx.gamma_cell {
+            return AssignedChallenge(gamma.clone());
+        }
+        let value = ctx.get_challenge(0);
+        let gamma = ctx.assign_cell(
+            Witness(value),
+            self.gamma,
+            #[cfg(feature = "display")]
+            0,
+            0,
+            #[cfg(feature = "halo2-pse")]
+            1,
+        );
+        self.q_gamma.enable(ctx.region(), 0).expect("enable selector should not fail");
+        ctx.gamma_cell = Some(gamma.clone());
+        AssignedChallenge(gamma)
+    }
+}
+
+/// A cell constrained to equal the verifier challenge, returned by [`RlcConfig::load_gamma`].
+///
+/// It can only be created by `load_gamma`, so chips that rely on the challenge for soundness take this instead of an [`AssignedValue`].
+#[derive(Clone, Debug)]
+pub struct AssignedChallenge<'a, F: ScalarField>(AssignedValue<'a, F>);
+
+impl<'a, F: ScalarField> AssignedChallenge<'a, F> {
+    pub fn assigned(&self) -> &AssignedValue<'a, F> {
+        &self.0
+    }
+}
+
+/// The RLC of the first `len` entries of an array with at most `max_len` entries.
+#[derive(Clone, Debug)]
+pub struct RlcVar<'a, F: ScalarField> {
+    pub rlc: AssignedValue<'a, F>,
+    pub len: AssignedValue<'a, F>,
+    pub max_len: usize,
+}
+
+/// Chip for computing RLCs of arrays at the challenge `gamma` loaded by [`RlcConfig::load_gamma`]
+#[derive(Clone, Debug)]
+pub struct RlcChip<'a, 'g, F: ScalarField, G: GateInstructions<F>> {
+    pub gate: &'g G,
+    gamma: AssignedChallenge<'a, F>,
+}
+
+impl<'a, 'g, F: ScalarField, G: GateInstructions<F>> RlcChip<'a, 'g, F, G> {
+    pub fn construct(gate: &'g G, gamma: AssignedChallenge<'a, F>) -> Self {
+        Self { gate, gamma }
+    }
+
+    pub fn gamma(&self) -> &AssignedChallenge<'a, F> {
+        &self.gamma
+    }
+
+    /// Returns the RLC of all of `inputs`.
+    pub fn compute_rlc_fixed_len(
+        &self,
+        ctx: &mut Context<'a, F>,
+        inputs: &[AssignedValue<'a, F>],
+    ) -> AssignedValue<'a, F> {
+        let gamma = self.gamma.assigned();
+        let mut rlc = self.gate.load_constant(ctx, F::zero());
+        for input in inputs {
+            rlc = self.gate.mul_add(ctx, Existing(&rlc), Existing(gamma), Existing(input));
+        }
+        rlc
+    }
+
+    /// Returns the RLC of the first `len` entries of `inputs`, where `len` is a witness.
+    ///
+    /// Constrains `len <= inputs.len()`.
+    pub fn compute_rlc(
+        &self,
+        ctx: &mut Context<'a, F>,
+        inputs: &[AssignedValue<'a, F>],
+        len: AssignedValue<'a, F>,
+    ) -> RlcVar<'a, F> {
+        // running[i] is the RLC of the first i entries
+        let mut running = Vec::with_capacity(inputs.len() + 1);
+        let gamma = self.gamma.assigned();
+        running.push(self.gate.load_constant(ctx, F::zero()));
+        for input in inputs {
+            let rlc = running.last().unwrap();
+            let rlc = self.gate.mul_add(ctx, Existing(rlc), Existing(gamma), Existing(input));
+            running.push(rlc);
+        }
+        let indicator = self.indicator(ctx, &len, inputs.len() + 1);
+        let rlc = self.gate.select_by_indicator(ctx, running.iter().map(Existing), &indicator);
+        RlcVar { rlc, len, max_len: inputs.len() }
+    }
+
+    /// Returns `gamma^len`, constraining `len <= max_len`.
+    pub fn gamma_pow(
+        &self,
+        ctx: &mut Context<'a, F>,
+        len: &AssignedValue<'a, F>,
+        max_len: usize,
+    ) -> AssignedValue<'a, F> {
+        let mut pows = Vec::with_capacity(max_len + 1);
+        pows.push(self.gate.load_constant(ctx, F::one()));
+        for _ in 0..max_len {
+            let pow = pows.last().unwrap();
+            let pow = self.gate.mul(ctx, Existing(pow), Existing(self.gamma.assigned()));
+            pows.push(pow);
+        }
+        let indicator = self.indicator(ctx, len, max_len + 1);
+        self.gate.select_by_indicator(ctx, pows.iter().map(Existing), &indicator)
+    }
+
+    /// Returns the RLC of the concatenation of the arrays with RLCs `a` and `b`.
+    pub fn concat(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &RlcVar<'a, F>,
+        b: &RlcVar<'a, F>,
+    ) -> RlcVar<'a, F> {
+        let gamma_pow = self.gamma_pow(ctx, &b.len, b.max_len);
+        let rlc = self.gate.mul_add(ctx, Existing(&a.rlc), Existing(&gamma_pow), Existing(&b.rlc));
+        let len = self.gate.add(ctx, Existing(&a.len), Existing(&b.len));
+        RlcVar { rlc, len, max_len: a.max_len + b.max_len }
+    }
+
+    /// Constrains the array with RLC `out` to be the concatenation of the arrays with RLCs `parts`, in order.
+    pub fn constrain_concat(
+        &self,
+        ctx: &mut Context<'a, F>,
+        parts: &[RlcVar<'a, F>],
+        out: &RlcVar<'a, F>,
+    ) {
+        let (first, rest) = parts.split_first().expect("there should be at least one part");
+        let concat = rest.iter().fold(first.clone(), |acc, part| self.concat(ctx, &acc, part));
+        ctx.constrain_equal(&concat.rlc, &out.rlc);
+        ctx.constrain_equal(&concat.len, &out.len);
+    }
+
+    // returns the indicator vector of `idx` of length `len`, constraining `idx < len`
+    fn indicator(
+        &self,
+        ctx: &mut Context<'a, F>,
+        idx: &AssignedValue<'a, F>,
+        len: usize,
+    ) -> Vec<AssignedValue<'a, F>> {
+        let indicator = self.gate.idx_to_indicator(ctx, Existing(idx), len);
+        // `idx_to_indicator` returns all zeros if `idx >= len`
+        let sum = self.gate.sum(ctx, indicator.iter().map(Existing));
+        self.gate.assert_is_const(ctx, &sum, F::one());
+        indicator
+    }
+}
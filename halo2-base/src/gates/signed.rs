@@ -0,0 +1,366 @@
+//! Signed integers and fixed-point numbers in the native field.
+//!
+//! A signed integer `x` with `-2^(n-1) <= x < 2^(n-1)` is represented by the field element `x`, i.e., a negative `x` is `p - |x|`
+//! for the modulus `p`. Additions and multiplications are then native field operations, as long as the results stay far below `p / 2`.
+//! Comparisons are reduced to [`RangeInstructions::is_less_than`], which only needs `|a - b| < 2^n`.
+//!
+//! A fixed-point number `x` with `frac_bits` fractional bits is the signed integer `round(x * 2^frac_bits)`.
+use super::{GateInstructions, RangeInstructions};
+use crate::{
+    halo2_proofs::circuit::Value,
+    utils::{biguint_to_fe, PrimeField},
+    AssignedValue, Context,
+    QuantumCell::{self, Constant, Existing},
+};
+use num_bigint::BigUint;
+use num_traits::One;
+use std::marker::PhantomData;
+
+/// Returns the field element representing the signed integer `x`
+pub fn i64_to_fe<F: PrimeField>(x: i64) -> F {
+    if x < 0 {
+        -F::from(x.unsigned_abs())
+    } else {
+        F::from(x as u64)
+    }
+}
+
+/// Chip for signed integers of at most `num_bits` bits in two's complement, i.e., in `[-2^(num_bits - 1), 2^(num_bits - 1))`.
+///
+/// Unless stated otherwise, methods assume their inputs are in this range, e.g. created by [`SignedChip::load_witness`] or constrained
+/// by [`SignedChip::range_check`].
+#[derive(Clone, Debug)]
+pub struct SignedChip<'r, F: PrimeField, R: RangeInstructions<F>> {
+    pub range: &'r R,
+    pub num_bits: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<'r, F: PrimeField, R: RangeInstructions<F>> SignedChip<'r, F, R> {
+    pub fn construct(range: &'r R, num_bits: usize) -> Self {
+        assert!(num_bits > 1);
+        // products of two signed integers must not wrap around the modulus
+        assert!(2 * num_bits < F::CAPACITY as usize, "signed integers have too many bits");
+        Self { range, num_bits, _marker: PhantomData }
+    }
+
+    pub fn gate(&self) -> &R::Gate {
+        self.range.gate()
+    }
+
+    /// `2^(num_bits - 1)`, the absolute value of the smallest signed integer
+    fn offset(&self) -> F {
+        self.gate().pow_of_two()[self.num_bits - 1]
+    }
+
+    /// Constrains `a` to be in `[-2^(num_bits - 1), 2^(num_bits - 1))`
+    pub fn range_check<'a>(&self, ctx: &mut Context<'a, F>, a: &AssignedValue<'a, F>) {
+        let shifted = self.gate().add(ctx, Existing(a), Constant(self.offset()));
+        self.range.range_check(ctx, &shifted, self.num_bits);
+    }
+
+    /// Assigns `a` and constrains it to be in range
+    pub fn load_witness<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: Value<i64>,
+    ) -> AssignedValue<'a, F> {
+        let a = self.gate().load_witness(ctx, a.map(i64_to_fe));
+        self.range_check(ctx, &a);
+        a
+    }
+
+    pub fn load_constant<'a>(&self, ctx: &mut Context<'a, F>, a: i64) -> AssignedValue<'a, F> {
+        self.gate().load_constant(ctx, i64_to_fe(a))
+    }
+
+    /// Returns `a + b`, which may have `num_bits + 1` bits. Use [`SignedChip::range_check`] to constrain it to `num_bits` bits.
+    pub fn add<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.gate().add(ctx, Existing(a), Existing(b))
+    }
+
+    /// Returns `a - b`, which may have `num_bits + 1` bits. Use [`SignedChip::range_check`] to constrain it to `num_bits` bits.
+    pub fn sub<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.gate().sub(ctx, Existing(a), Existing(b))
+    }
+
+    /// Returns `a * b`, which may have `2 * num_bits - 1` bits. Use [`SignedChip::range_check`] to constrain it to `num_bits` bits.
+    pub fn mul<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.gate().mul(ctx, Existing(a), Existing(b))
+    }
+
+    /// Returns `(q, r)` with `a = b * q + r` and `0 <= r < b`, i.e., `q = floor(a / b)` rounding towards negative infinity.
+    ///
+    /// `a` may have up to `a_num_bits` bits, e.g. `2 * num_bits - 1` for a product. Requires `b > 0`.
+    pub fn div_floor<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: u64,
+        a_num_bits: usize,
+    ) -> (AssignedValue<'a, F>, AssignedValue<'a, F>) {
+        assert!(b > 0);
+        assert!(a_num_bits < F::CAPACITY as usize);
+        // a + k * b is nonnegative and floor((a + k * b) / b) = floor(a / b) + k
+        let half = BigUint::one() << (a_num_bits - 1);
+        let k = (&half + b - 1u64) / b;
+        let shift = &k * b;
+        let shifted_num_bits = (&half + &shift).bits() as usize;
+        let [k, shift] = [k, shift].map(|v| biguint_to_fe::<F>(&v));
+
+        let shifted = self.gate().add(ctx, Existing(a), Constant(shift));
+        let (q, r) = self.range.div_mod(ctx, Existing(&shifted), b, shifted_num_bits);
+        let q = self.gate().sub(ctx, Existing(&q), Constant(k));
+        (q, r)
+    }
+
+    /// Returns whether `a < b`. Assumes `|a - b| < 2^num_bits`, which holds for `a, b` in range.
+    pub fn is_less_than<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: QuantumCell<'_, 'a, F>,
+        b: QuantumCell<'_, 'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.range.is_less_than(ctx, a, b, self.num_bits)
+    }
+
+    /// Returns whether `a < 0`
+    pub fn is_neg<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.is_less_than(ctx, Existing(a), Constant(F::zero()))
+    }
+
+    /// Returns `|a|`, which is `2^(num_bits - 1)` for the smallest signed integer
+    pub fn abs<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let is_neg = self.is_neg(ctx, a);
+        let neg_a = self.gate().neg(ctx, Existing(a));
+        self.gate().select(ctx, Existing(&neg_a), Existing(a), Existing(&is_neg))
+    }
+
+    pub fn max<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let a_lt_b = self.is_less_than(ctx, Existing(a), Existing(b));
+        self.gate().select(ctx, Existing(b), Existing(a), Existing(&a_lt_b))
+    }
+
+    pub fn min<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let a_lt_b = self.is_less_than(ctx, Existing(a), Existing(b));
+        self.gate().select(ctx, Existing(a), Existing(b), Existing(&a_lt_b))
+    }
+
+    /// Returns `max(a, 0)`
+    pub fn relu<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let is_neg = self.is_neg(ctx, a);
+        self.gate().select(ctx, Constant(F::zero()), Existing(a), Existing(&is_neg))
+    }
+
+    /// Returns `a` clamped to `[lo, hi]`, i.e., `min(max(a, lo), hi)`. Requires `lo <= hi` to be in range.
+    pub fn clamp<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        lo: i64,
+        hi: i64,
+    ) -> AssignedValue<'a, F> {
+        assert!(lo <= hi);
+        let [lo, hi] = [lo, hi].map(i64_to_fe::<F>);
+        let below = self.is_less_than(ctx, Existing(a), Constant(lo));
+        let a = self.gate().select(ctx, Constant(lo), Existing(a), Existing(&below));
+        let above = self.is_less_than(ctx, Constant(hi), Existing(&a));
+        self.gate().select(ctx, Constant(hi), Existing(&a), Existing(&above))
+    }
+}
+
+/// Chip for fixed-point numbers with `frac_bits` fractional bits, represented by signed integers of `num_bits` bits.
+///
+/// All arithmetic constrains its result to be in range, so overflows fail instead of wrapping around.
+#[derive(Clone, Debug)]
+pub struct FixedPointChip<'r, F: PrimeField, R: RangeInstructions<F>> {
+    pub signed: SignedChip<'r, F, R>,
+    pub frac_bits: usize,
+}
+
+impl<'r, F: PrimeField, R: RangeInstructions<F>> FixedPointChip<'r, F, R> {
+    pub fn construct(range: &'r R, num_bits: usize, frac_bits: usize) -> Self {
+        assert!(frac_bits < num_bits);
+        // rescaling divides by the constant `2^frac_bits`
+        assert!(frac_bits < 64);
+        Self { signed: SignedChip::construct(range, num_bits), frac_bits }
+    }
+
+    pub fn gate(&self) -> &R::Gate {
+        self.signed.gate()
+    }
+
+    /// Returns the signed integer representing `x`, rounded to the nearest multiple of `2^-frac_bits`
+    pub fn quantize(&self, x: f64) -> i64 {
+        (x * (1u64 << self.frac_bits) as f64).round() as i64
+    }
+
+    /// Returns the number represented by the signed integer `x`
+    pub fn dequantize(&self, x: i64) -> f64 {
+        x as f64 / (1u64 << self.frac_bits) as f64
+    }
+
+    /// Assigns the quantization of `x` and constrains it to be in range
+    pub fn load_witness<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        x: Value<f64>,
+    ) -> AssignedValue<'a, F> {
+        self.signed.load_witness(ctx, x.map(|x| self.quantize(x)))
+    }
+
+    pub fn load_constant<'a>(&self, ctx: &mut Context<'a, F>, x: f64) -> AssignedValue<'a, F> {
+        self.signed.load_constant(ctx, self.quantize(x))
+    }
+
+    pub fn add<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let out = self.signed.add(ctx, a, b);
+        self.signed.range_check(ctx, &out);
+        out
+    }
+
+    pub fn sub<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let out = self.signed.sub(ctx, a, b);
+        self.signed.range_check(ctx, &out);
+        out
+    }
+
+    /// Returns `a * b`, rescaled to `frac_bits` fractional bits by rounding towards negative infinity
+    pub fn mul<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let prod = self.signed.mul(ctx, a, b);
+        let (out, _) =
+            self.signed.div_floor(ctx, &prod, 1u64 << self.frac_bits, 2 * self.signed.num_bits);
+        self.signed.range_check(ctx, &out);
+        out
+    }
+
+    /// Returns `a / b`, rounded towards zero.
+    ///
+    /// Assumes that `b != 0`.
+    pub fn div<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        let num_bits = self.signed.num_bits;
+        let gate = self.gate();
+        let [a_neg, b_neg] = [a, b].map(|x| self.signed.is_neg(ctx, x));
+        let [a_abs, b_abs] = [(a, &a_neg), (b, &b_neg)].map(|(x, x_neg)| {
+            let neg_x = gate.neg(ctx, Existing(x));
+            gate.select(ctx, Existing(&neg_x), Existing(x), Existing(x_neg))
+        });
+        // |a| * 2^frac_bits / |b|
+        let num = gate.mul(ctx, Existing(&a_abs), Constant(gate.pow_of_two()[self.frac_bits]));
+        let (q, _) = self.signed.range.div_mod_var(
+            ctx,
+            Existing(&num),
+            Existing(&b_abs),
+            num_bits + self.frac_bits,
+            num_bits,
+        );
+        let same_sign = gate.is_equal(ctx, Existing(&a_neg), Existing(&b_neg));
+        let neg_q = gate.neg(ctx, Existing(&q));
+        let out = gate.select(ctx, Existing(&q), Existing(&neg_q), Existing(&same_sign));
+        self.signed.range_check(ctx, &out);
+        out
+    }
+
+    pub fn is_less_than<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.signed.is_less_than(ctx, Existing(a), Existing(b))
+    }
+
+    pub fn max<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.signed.max(ctx, a, b)
+    }
+
+    pub fn min<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        b: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.signed.min(ctx, a, b)
+    }
+
+    pub fn relu<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+    ) -> AssignedValue<'a, F> {
+        self.signed.relu(ctx, a)
+    }
+
+    /// Returns `a` clamped to the quantizations of `[lo, hi]`
+    pub fn clamp<'a>(
+        &self,
+        ctx: &mut Context<'a, F>,
+        a: &AssignedValue<'a, F>,
+        lo: f64,
+        hi: f64,
+    ) -> AssignedValue<'a, F> {
+        self.signed.clamp(ctx, a, self.quantize(lo), self.quantize(hi))
+    }
+}
@@ -1,19 +1,32 @@
 use super::{
+    dynamic_lookup::{DynamicLookupConfig, DynamicLookupTable},
     flex_gate::{FlexGateConfig, GateStrategy},
-    range, GateInstructions, RangeInstructions,
+    permutation::PermutationChip,
+    primitive::PrimitiveChip,
+    range::{self, BitwiseOp},
+    rlc::{RlcChip, RlcConfig},
+    signed::{i64_to_fe, FixedPointChip},
+    GateInstructions, RangeInstructions,
 };
 use crate::halo2_proofs::{circuit::*, dev::MockProver, halo2curves::bn256::Fr, plonk::*};
 use crate::{
-    Context, ContextParams,
+    debug::DebugInfo,
+    utils::{biguint_to_fe, Primitive, ScalarField},
+    virtual_context::assign_virtual_contexts,
+    AdviceAllocStrategy, AssignedValue, Context, ContextParams,
     QuantumCell::{Constant, Existing, Witness},
     SKIP_FIRST_PASS,
 };
+use num_bigint::BigUint;
+use rayon::prelude::*;
+use std::{cell::RefCell, marker::PhantomData};
 
 #[derive(Default)]
 struct MyCircuit<F> {
     a: Value<F>,
     b: Value<F>,
     c: Value<F>,
+    advice_alloc_strategy: AdviceAllocStrategy,
 }
 
 const NUM_ADVICE: usize = 2;
@@ -58,6 +71,7 @@ impl Circuit<Fr> for MyCircuit<Fr> {
                         max_rows: config.max_rows,
                         num_context_ids: 1,
                         fixed_columns: config.constants.clone(),
+                        advice_alloc_strategy: self.advice_alloc_strategy,
                     },
                 );
                 let ctx = &mut aux;
@@ -121,6 +135,7 @@ fn test_gates() {
         a: Value::known(Fr::from(10u64)),
         b: Value::known(Fr::from(12u64)),
         c: Value::known(Fr::from(120u64)),
+        advice_alloc_strategy: AdviceAllocStrategy::Vertical,
     };
 
     let prover = MockProver::run(k, &circuit, vec![]).unwrap();
@@ -128,6 +143,78 @@ fn test_gates() {
     // assert_eq!(prover.verify(), Ok(()));
 }
 
+#[test]
+fn test_gates_balanced() {
+    let k = 6;
+    let circuit = MyCircuit::<Fr> {
+        a: Value::known(Fr::from(10u64)),
+        b: Value::known(Fr::from(12u64)),
+        c: Value::known(Fr::from(120u64)),
+        advice_alloc_strategy: AdviceAllocStrategy::Balanced,
+    };
+
+    let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+    prover.assert_satisfied();
+}
+
+// assigns `num_cells` witnesses into contexts with `max_rows` rows using `AdviceAllocStrategy::Balanced`
+struct FullColumnsCircuit {
+    max_rows: usize,
+    num_cells: usize,
+}
+
+impl Circuit<Fr> for FullColumnsCircuit {
+    type Config = FlexGateConfig<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self { max_rows: self.max_rows, num_cells: self.num_cells }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        FlexGateConfig::configure(meta, GateStrategy::Vertical, &[NUM_ADVICE], 1, 0, 6)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let mut first_pass = SKIP_FIRST_PASS;
+
+        layouter.assign_region(
+            || "full columns",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut ctx = Context::new(
+                    region,
+                    ContextParams {
+                        max_rows: self.max_rows,
+                        num_context_ids: 1,
+                        fixed_columns: config.constants.clone(),
+                        advice_alloc_strategy: AdviceAllocStrategy::Balanced,
+                    },
+                );
+                for i in 0..self.num_cells {
+                    config.load_witness(&mut ctx, Value::known(Fr::from(i as u64)));
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+#[should_panic(expected = "context 0 ran out of advice cells: all 2 advice columns")]
+fn test_balanced_out_of_rows() {
+    let circuit = FullColumnsCircuit { max_rows: 8, num_cells: 2 * 8 + 1 };
+    MockProver::run(6, &circuit, vec![]).unwrap();
+}
+
 #[cfg(feature = "dev-graph")]
 #[test]
 fn plot_gates() {
@@ -148,6 +235,7 @@ struct RangeTestCircuit<F> {
     lt_bits: usize,
     a: Value<F>,
     b: Value<F>,
+    advice_alloc_strategy: AdviceAllocStrategy,
 }
 
 impl Circuit<Fr> for RangeTestCircuit<Fr> {
@@ -160,6 +248,7 @@ impl Circuit<Fr> for RangeTestCircuit<Fr> {
             lt_bits: self.lt_bits,
             a: Value::unknown(),
             b: Value::unknown(),
+            advice_alloc_strategy: self.advice_alloc_strategy,
         }
     }
 
@@ -224,6 +313,7 @@ impl Circuit<Fr> for RangeTestCircuit<Fr> {
                         max_rows: config.gate.max_rows,
                         num_context_ids: 1,
                         fixed_columns: config.gate.constants.clone(),
+                        advice_alloc_strategy: self.advice_alloc_strategy,
                     },
                 );
                 let ctx = &mut aux;
@@ -281,6 +371,7 @@ fn test_range() {
         lt_bits: 8,
         a: Value::known(Fr::from(100u64)),
         b: Value::known(Fr::from(101u64)),
+        advice_alloc_strategy: AdviceAllocStrategy::Vertical,
     };
 
     let prover = MockProver::run(k, &circuit, vec![]).unwrap();
@@ -288,6 +379,21 @@ fn test_range() {
     //assert_eq!(prover.verify(), Ok(()));
 }
 
+#[test]
+fn test_range_balanced() {
+    let k = 11;
+    let circuit = RangeTestCircuit::<Fr> {
+        range_bits: 8,
+        lt_bits: 8,
+        a: Value::known(Fr::from(100u64)),
+        b: Value::known(Fr::from(101u64)),
+        advice_alloc_strategy: AdviceAllocStrategy::Balanced,
+    };
+
+    let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+    prover.assert_satisfied();
+}
+
 #[cfg(feature = "dev-graph")]
 #[test]
 fn plot_range() {
@@ -302,11 +408,726 @@ fn plot_range() {
         lt_bits: 8,
         a: Value::unknown(),
         b: Value::unknown(),
+        advice_alloc_strategy: AdviceAllocStrategy::Vertical,
     };
 
     halo2_proofs::dev::CircuitLayout::default().render(7, &circuit, &root).unwrap();
 }
 
+/// The configuration of a [`TestCircuit`]. `Circuit::configure` takes no parameters, so each configuration is a type.
+trait TestConfig {
+    type Config: Clone;
+    /// the circuit has `2^DEGREE` rows
+    const DEGREE: u32;
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config;
+
+    fn gate(config: &Self::Config) -> &FlexGateConfig<Fr>;
+
+    fn load_lookup_table(
+        _config: &Self::Config,
+        _layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// The challenge usable after the first phase, registered with the context for phase 0
+    fn challenge(_config: &Self::Config) -> Option<Challenge> {
+        None
+    }
+
+    /// Called at the end of the region, after the test
+    fn finalize(_config: &Self::Config, _ctx: &mut Context<'_, Fr>) {}
+}
+
+/// `FlexGateConfig` with `NUM_ADVICE` advice columns
+struct FlexGate<const K: u32>;
+
+impl<const K: u32> TestConfig for FlexGate<K> {
+    type Config = FlexGateConfig<Fr>;
+    const DEGREE: u32 = K;
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        FlexGateConfig::configure(meta, GateStrategy::Vertical, &[NUM_ADVICE], 1, 0, K as usize)
+    }
+
+    fn gate(config: &Self::Config) -> &FlexGateConfig<Fr> {
+        config
+    }
+}
+
+/// `FlexGateConfig` with a second phase, and `RlcConfig`
+struct FlexGateRlc<const K: u32>;
+
+impl<const K: u32> TestConfig for FlexGateRlc<K> {
+    type Config = (FlexGateConfig<Fr>, RlcConfig);
+    const DEGREE: u32 = K;
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let gate = FlexGateConfig::configure(
+            meta,
+            GateStrategy::Vertical,
+            &[NUM_ADVICE, 1],
+            1,
+            0,
+            K as usize,
+        );
+        let rlc = RlcConfig::configure(meta);
+        (gate, rlc)
+    }
+
+    fn gate((gate, _): &Self::Config) -> &FlexGateConfig<Fr> {
+        gate
+    }
+
+    fn challenge((_, rlc): &Self::Config) -> Option<Challenge> {
+        Some(rlc.challenge)
+    }
+}
+
+/// `FlexGateConfig` and `DynamicLookupConfig`. The lookups need the table, so the test finalizes them.
+struct FlexGateDynamicLookup<const K: u32>;
+
+impl<const K: u32> TestConfig for FlexGateDynamicLookup<K> {
+    type Config = (FlexGateConfig<Fr>, DynamicLookupConfig);
+    const DEGREE: u32 = K;
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let gate = FlexGateConfig::configure(
+            meta,
+            GateStrategy::Vertical,
+            &[NUM_ADVICE],
+            1,
+            0,
+            K as usize,
+        );
+        let dynamic_lookup = DynamicLookupConfig::configure(meta, 1);
+        (gate, dynamic_lookup)
+    }
+
+    fn gate((gate, _): &Self::Config) -> &FlexGateConfig<Fr> {
+        gate
+    }
+}
+
+/// `RangeConfig` with `ADVICE` advice columns and lookups of `LOOKUP_BITS` bits
+struct Range<const ADVICE: usize, const LOOKUP_BITS: usize, const K: u32>;
+
+impl<const ADVICE: usize, const LOOKUP_BITS: usize, const K: u32> TestConfig
+    for Range<ADVICE, LOOKUP_BITS, K>
+{
+    type Config = range::RangeConfig<Fr>;
+    const DEGREE: u32 = K;
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        range::RangeConfig::configure(
+            meta,
+            range::RangeStrategy::Vertical,
+            &[ADVICE],
+            &[1],
+            1,
+            LOOKUP_BITS,
+            0,
+            K as usize,
+        )
+    }
+
+    fn gate(config: &Self::Config) -> &FlexGateConfig<Fr> {
+        &config.gate
+    }
+
+    fn load_lookup_table(
+        config: &Self::Config,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        config.load_lookup_table(layouter)
+    }
+
+    fn finalize(config: &Self::Config, ctx: &mut Context<'_, Fr>) {
+        config.finalize(ctx);
+    }
+}
+
+/// `RangeConfig` with `NUM_ADVICE` advice columns, lookups of `LOOKUP_BITS` bits, and the byte lookup table of all bitwise
+/// operations
+struct ByteRange<const LOOKUP_BITS: usize, const K: u32>;
+
+impl<const LOOKUP_BITS: usize, const K: u32> TestConfig for ByteRange<LOOKUP_BITS, K> {
+    type Config = range::RangeConfig<Fr>;
+    const DEGREE: u32 = K;
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        range::RangeConfig::configure_with_byte_lookup(
+            meta,
+            range::RangeStrategy::Vertical,
+            &[NUM_ADVICE],
+            &[1],
+            1,
+            LOOKUP_BITS,
+            0,
+            K as usize,
+            &[BitwiseOp::Xor, BitwiseOp::And, BitwiseOp::Or],
+            1,
+        )
+    }
+
+    fn gate(config: &Self::Config) -> &FlexGateConfig<Fr> {
+        &config.gate
+    }
+
+    fn load_lookup_table(
+        config: &Self::Config,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        config.load_lookup_table(layouter)
+    }
+
+    fn finalize(config: &Self::Config, ctx: &mut Context<'_, Fr>) {
+        config.finalize(ctx);
+    }
+}
+
+/// `RangeConfig` with a second phase and lookups of `LOOKUP_BITS` bits, and `RlcConfig`. The range checks are copied to the
+/// lookup column in the first phase, so the test finalizes them before moving to the second phase.
+struct RangeRlc<const LOOKUP_BITS: usize, const K: u32>;
+
+impl<const LOOKUP_BITS: usize, const K: u32> TestConfig for RangeRlc<LOOKUP_BITS, K> {
+    type Config = (range::RangeConfig<Fr>, RlcConfig);
+    const DEGREE: u32 = K;
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let range = range::RangeConfig::configure(
+            meta,
+            range::RangeStrategy::Vertical,
+            &[NUM_ADVICE, 1],
+            &[1],
+            1,
+            LOOKUP_BITS,
+            0,
+            K as usize,
+        );
+        let rlc = RlcConfig::configure(meta);
+        (range, rlc)
+    }
+
+    fn gate((range, _): &Self::Config) -> &FlexGateConfig<Fr> {
+        &range.gate
+    }
+
+    fn load_lookup_table(
+        (range, _): &Self::Config,
+        layouter: &mut impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        range.load_lookup_table(layouter)
+    }
+
+    fn challenge((_, rlc): &Self::Config) -> Option<Challenge> {
+        Some(rlc.challenge)
+    }
+}
+
+// A circuit with the configuration `C` that runs `f` in a single region. The witnesses are the values captured by `f`.
+struct TestCircuit<C, Func> {
+    f: Func,
+    _marker: PhantomData<C>,
+}
+
+impl<C, Func> Circuit<Fr> for TestCircuit<C, Func>
+where
+    C: TestConfig,
+    Func: Fn(&C::Config, &mut Context<'_, Fr>) + Clone,
+{
+    type Config = C::Config;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // `MockProver` does not call this
+        Self { f: self.f.clone(), _marker: PhantomData }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        C::load_lookup_table(&config, &mut layouter)?;
+
+        let challenge = C::challenge(&config).map(|c| (c, layouter.get_challenge(c)));
+        let mut first_pass = SKIP_FIRST_PASS;
+
+        layouter.assign_region(
+            || "test",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let gate = C::gate(&config);
+                let mut aux = Context::new(
+                    region,
+                    ContextParams::new(gate.max_rows, 1, gate.constants.clone()),
+                );
+                let ctx = &mut aux;
+                if let Some((challenge, value)) = challenge {
+                    ctx.set_challenge(0, challenge, value);
+                }
+
+                (self.f)(&config, ctx);
+
+                C::finalize(&config, ctx);
+                Ok(())
+            },
+        )
+    }
+}
+
+// Runs `f` in a `TestCircuit` with the configuration `C` on the mock prover
+fn mock_circuit<C: TestConfig>(
+    f: impl Fn(&C::Config, &mut Context<'_, Fr>) + Clone,
+) -> MockProver<Fr> {
+    let circuit = TestCircuit::<C, _> { f, _marker: PhantomData };
+    MockProver::run(C::DEGREE, &circuit, vec![]).unwrap()
+}
+
+// Checks `bitwise_bytes` of `a` and `b` for each operation, and that `num` round trips through `num_bytes` bytes
+fn mock_byte_lookup(a: &[u64], b: &[u64], num: u64, num_bytes: usize) -> MockProver<Fr> {
+    mock_circuit::<ByteRange<3, 18>>(|config, ctx| {
+        let [a, b] = [a, b].map(|values| {
+            config.gate.assign_witnesses(ctx, values.iter().map(|x| Value::known(Fr::from(*x))))
+        });
+        for op in [BitwiseOp::Xor, BitwiseOp::And, BitwiseOp::Or] {
+            let out = config.bitwise_bytes(ctx, op, &a, &b);
+            for ((a, b), out) in a.iter().zip(b.iter()).zip(out.iter()) {
+                a.value().zip(b.value()).zip(out.value()).assert_if_known(|((a, b), out)| {
+                    let [a, b] = [a, b].map(|x| x.get_lower_32() as u8);
+                    out.get_lower_32() == op.apply(a, b) as u32
+                });
+            }
+        }
+
+        let num = config.gate.load_witness(ctx, Value::known(Fr::from(num)));
+        let bytes = config.num_to_bytes(ctx, &num, num_bytes);
+        let num_again = config.bytes_to_num(ctx, &bytes);
+        ctx.constrain_equal(&num, &num_again);
+    })
+}
+
+#[test]
+fn test_byte_lookup() {
+    mock_byte_lookup(&[0, 255, 0x5a, 17], &[0, 0x0f, 255, 200], 0x0123456789abcdef, 8)
+        .assert_satisfied();
+}
+
+#[test]
+fn test_byte_lookup_fails() {
+    // not a byte
+    assert!(mock_byte_lookup(&[256], &[1], 1, 1).verify().is_err());
+    // does not fit in `num_bytes` bytes
+    assert!(mock_byte_lookup(&[], &[], 70000, 2).verify().is_err());
+}
+
+const FIXED_POINT_BITS: usize = 32;
+const FRAC_BITS: usize = 8;
+
+// Checks the operations of `FixedPointChip` on `a` and `b` against the same operations on their quantizations
+fn mock_fixed_point(a: f64, b: f64) -> MockProver<Fr> {
+    mock_circuit::<Range<4, 10, 12>>(|config, ctx| {
+        let chip = FixedPointChip::construct(config, FIXED_POINT_BITS, FRAC_BITS);
+        let [qa, qb] = [a, b].map(|x| chip.quantize(x));
+        let [a, b] = [a, b].map(|x| chip.load_witness(ctx, Value::known(x)));
+        let scale = 1i128 << FRAC_BITS;
+        let check = |ctx: &mut Context<'_, Fr>, x: &AssignedValue<Fr>, expected: i128| {
+            config.gate.assert_is_const(ctx, x, i64_to_fe(expected as i64));
+        };
+
+        let sum = chip.add(ctx, &a, &b);
+        check(ctx, &sum, (qa + qb) as i128);
+        let diff = chip.sub(ctx, &a, &b);
+        check(ctx, &diff, (qa - qb) as i128);
+        let prod = chip.mul(ctx, &a, &b);
+        check(ctx, &prod, (qa as i128 * qb as i128).div_euclid(scale));
+        let quot = chip.div(ctx, &a, &b);
+        check(ctx, &quot, qa as i128 * scale / qb as i128);
+
+        let a_lt_b = chip.is_less_than(ctx, &a, &b);
+        check(ctx, &a_lt_b, (qa < qb) as i128);
+        let max = chip.max(ctx, &a, &b);
+        check(ctx, &max, qa.max(qb) as i128);
+        let min = chip.min(ctx, &a, &b);
+        check(ctx, &min, qa.min(qb) as i128);
+        for (x, qx) in [(&a, qa), (&b, qb)] {
+            let relu = chip.relu(ctx, x);
+            check(ctx, &relu, qx.max(0) as i128);
+            let clamped = chip.clamp(ctx, x, -1.0, 2.0);
+            check(ctx, &clamped, qx.clamp(-scale as i64, 2 * scale as i64) as i128);
+            let abs = chip.signed.abs(ctx, x);
+            check(ctx, &abs, qx.abs() as i128);
+            let (q, r) = chip.signed.div_floor(ctx, x, 3, FIXED_POINT_BITS);
+            check(ctx, &q, qx.div_euclid(3) as i128);
+            check(ctx, &r, qx.rem_euclid(3) as i128);
+        }
+    })
+}
+
+#[test]
+fn test_fixed_point() {
+    for (a, b) in [(3.5, -1.25), (-7.0, 2.0), (0.0, 0.5), (-100.75, -3.0)] {
+        mock_fixed_point(a, b).assert_satisfied();
+    }
+}
+
+#[test]
+fn test_fixed_point_overflow() {
+    // the product 2^40 does not fit in 32 bits
+    assert!(mock_fixed_point(1048576.0, 1048576.0).verify().is_err());
+}
+
+// Checks the operations of `PrimitiveChip` on `a` and `b`, shifting and rotating by `shift`, against the same operations on
+// `u128`s
+fn mock_primitive<T: Primitive>(a: T, b: T, shift: usize) -> MockProver<Fr> {
+    mock_circuit::<ByteRange<8, 18>>(|config, ctx| {
+        let chip = PrimitiveChip::construct(config);
+        let [qa, qb] = [a, b].map(|x| Into::<u64>::into(x) as u128);
+        let a = chip.load_witness(ctx, Value::known(a));
+        let b = chip.load_witness(ctx, Value::known(b));
+        let bits = T::BITS;
+        let modulus = 1u128 << bits;
+        let check = |ctx: &mut Context<'_, Fr>, x: &AssignedValue<Fr>, expected: u128| {
+            config.gate.assert_is_const(ctx, x, biguint_to_fe(&BigUint::from(expected)));
+        };
+
+        let (sum, carry) = chip.overflowing_add(ctx, &a, &b);
+        check(ctx, &sum.assigned, (qa + qb) % modulus);
+        check(ctx, &carry, (qa + qb >= modulus) as u128);
+        let (diff, borrow) = chip.overflowing_sub(ctx, &a, &b);
+        check(ctx, &diff.assigned, (qa + modulus - qb) % modulus);
+        check(ctx, &borrow, (qa < qb) as u128);
+        let prod = chip.wrapping_mul(ctx, &a, &b);
+        check(ctx, &prod.assigned, (qa * qb) % modulus);
+        // these fail if the operations overflow
+        let sum = chip.checked_add(ctx, &a, &b);
+        check(ctx, &sum.assigned, qa + qb);
+        let prod = chip.checked_mul(ctx, &a, &b);
+        check(ctx, &prod.assigned, qa * qb);
+
+        let shl = chip.shl(ctx, &a, shift);
+        check(ctx, &shl.assigned, (qa << shift) % modulus);
+        let shr = chip.shr(ctx, &a, shift);
+        check(ctx, &shr.assigned, qa >> shift);
+        let rotl = chip.rotate_left(ctx, &a, shift);
+        check(ctx, &rotl.assigned, ((qa << shift) | (qa >> (bits - shift))) % modulus);
+        let rotr = chip.rotate_right(ctx, &a, shift);
+        check(ctx, &rotr.assigned, ((qa >> shift) | (qa << (bits - shift))) % modulus);
+
+        let not = chip.not(ctx, &a);
+        check(ctx, &not.assigned, modulus - 1 - qa);
+        let and = chip.and(ctx, &a, &b);
+        check(ctx, &and.assigned, qa & qb);
+        let or = chip.or(ctx, &a, &b);
+        check(ctx, &or.assigned, qa | qb);
+        let xor = chip.xor(ctx, &a, &b);
+        check(ctx, &xor.assigned, qa ^ qb);
+
+        let lt = chip.is_less_than(ctx, &a, &b);
+        check(ctx, &lt, (qa < qb) as u128);
+        let eq = chip.is_equal(ctx, &a, &b);
+        check(ctx, &eq, (qa == qb) as u128);
+    })
+}
+
+#[test]
+fn test_primitive() {
+    mock_primitive::<u8>(13, 17, 3).assert_satisfied();
+    mock_primitive::<u32>(0x1234_5678, 9, 7).assert_satisfied();
+    mock_primitive::<u64>(0xdead_beef, 0x1_0001, 63).assert_satisfied();
+}
+
+#[test]
+fn test_primitive_overflow() {
+    // `checked_add` overflows
+    assert!(mock_primitive::<u32>(u32::MAX, 1, 1).verify().is_err());
+}
+
+const SORT_BITS: usize = 16;
+
+// Sorts `a` and checks that `b` is a permutation of `a`. With `tamper_records`, the records `(a[i], i)` sorted by key are
+// witnessed with the smallest record `(key, value)` replaced by `(key - 1, value + 2^SORT_BITS)`, which packs to the same field
+// element.
+fn mock_permutation(a: &[u64], b: &[u64], tamper_records: bool) -> MockProver<Fr> {
+    mock_circuit::<RangeRlc<8, 11>>(|(config, rlc), ctx| {
+        let chip = PermutationChip::construct(config);
+
+        let [a_cells, b_cells] = [a, b].map(|values| {
+            config.gate.assign_witnesses(ctx, values.iter().map(|v| Value::known(Fr::from(*v))))
+        });
+        for x in a_cells.iter().chain(b_cells.iter()) {
+            config.range_check(ctx, x, SORT_BITS);
+        }
+        let sorted = chip.sort(ctx, &a_cells, SORT_BITS);
+        // records `(a[i], i)`
+        let records = a_cells
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.clone(), config.gate.load_constant(ctx, Fr::from(i as u64))))
+            .collect::<Vec<_>>();
+        let sorted_records = if tamper_records {
+            let mut sorted = a.iter().copied().zip(0u64..).collect::<Vec<_>>();
+            sorted.sort_by_key(|(key, _)| *key);
+            sorted[0] = (sorted[0].0 - 1, sorted[0].1 + (1 << SORT_BITS));
+            let sorted = sorted
+                .into_iter()
+                .map(|(key, value)| {
+                    let mut cells = config
+                        .gate
+                        .assign_witnesses(ctx, [key, value].map(|x| Value::known(Fr::from(x))));
+                    let value = cells.pop().unwrap();
+                    (cells.pop().unwrap(), value)
+                })
+                .collect::<Vec<_>>();
+            chip.assert_sorted_by_key(ctx, &sorted, SORT_BITS, SORT_BITS);
+            sorted
+        } else {
+            chip.sort_by_key(ctx, &records, SORT_BITS, SORT_BITS)
+        };
+        config.finalize(ctx);
+
+        ctx.next_phase();
+        let gamma = rlc.load_gamma(ctx);
+        chip.check_permutation(ctx, &a_cells, &sorted, &gamma);
+        chip.check_keyed_permutation(ctx, &records, &sorted_records, SORT_BITS, &gamma);
+        chip.check_permutation(ctx, &a_cells, &b_cells, &gamma);
+
+        let mut expected = a.to_vec();
+        expected.sort();
+        for (x, expected) in sorted.iter().zip(expected) {
+            x.value().assert_if_known(|x| **x == Fr::from(expected));
+        }
+    })
+}
+
+#[test]
+fn test_permutation() {
+    mock_permutation(&[5, 3, 60000, 3, 0, 17, 1024, 5], &[3, 5, 5, 17, 1024, 0, 60000, 3], false)
+        .assert_satisfied();
+}
+
+#[test]
+fn test_permutation_fails() {
+    // `b` has the same sum but is not a permutation of `a`
+    assert!(mock_permutation(&[1, 2, 3, 4], &[1, 1, 4, 4], false).verify().is_err());
+}
+
+#[test]
+fn test_keyed_permutation_overflowing_value_fails() {
+    // the tampered records are sorted and pack to the same elements, but a value overflows `SORT_BITS`
+    assert!(mock_permutation(&[5, 3, 17, 9], &[3, 5, 9, 17], true).verify().is_err());
+}
+
+// Constrains `c` to be the concatenation of `a` and `b`, where `a` and `b` are padded with zeros to `max_len` entries and `c`
+// to `2 * max_len` entries
+fn mock_rlc_concat(a: &[u8], b: &[u8], c: &[u8], max_len: usize) -> MockProver<Fr> {
+    mock_circuit::<FlexGateRlc<10>>(|(config, rlc), ctx| {
+        let [(a_cells, a_len), (b_cells, b_len), (c_cells, c_len)] =
+            [(a, max_len), (b, max_len), (c, 2 * max_len)].map(|(bytes, max_len)| {
+                let padded = bytes
+                    .iter()
+                    .map(|x| Fr::from(*x as u64))
+                    .chain(std::iter::repeat(Fr::zero()))
+                    .take(max_len);
+                let assigned = config.assign_witnesses(ctx, padded.map(Value::known));
+                let len = config.load_witness(ctx, Value::known(Fr::from(bytes.len() as u64)));
+                (assigned, len)
+            });
+
+        ctx.next_phase();
+        let chip = RlcChip::construct(config, rlc.load_gamma(ctx));
+        // loading the challenge again returns the same cell
+        let gamma = rlc.load_gamma(ctx);
+        assert_eq!(gamma.assigned().row(), chip.gamma().assigned().row());
+        let a_rlc = chip.compute_rlc(ctx, &a_cells, a_len);
+        let b_rlc = chip.compute_rlc(ctx, &b_cells, b_len);
+        let c_rlc = chip.compute_rlc(ctx, &c_cells, c_len);
+        chip.constrain_concat(ctx, &[a_rlc.clone(), b_rlc], &c_rlc);
+
+        // agrees with the RLC of the unpadded array
+        let a_rlc_fixed = chip.compute_rlc_fixed_len(ctx, &a_cells[..a.len()]);
+        ctx.constrain_equal(&a_rlc.rlc, &a_rlc_fixed);
+        a_rlc.rlc.value().zip(gamma.assigned().value()).assert_if_known(|(rlc, gamma)| {
+            **rlc == a.iter().fold(Fr::zero(), |acc, x| acc * *gamma + Fr::from(*x as u64))
+        });
+    })
+}
+
+#[test]
+fn test_rlc_concat() {
+    mock_rlc_concat(&[1, 2, 3], &[0, 4, 5, 6, 0], &[1, 2, 3, 0, 4, 5, 6, 0], 6).assert_satisfied();
+    mock_rlc_concat(&[], &[7; 6], &[7; 6], 6).assert_satisfied();
+}
+
+#[test]
+fn test_rlc_concat_fails() {
+    // `c` drops the trailing zero of `b`, which the RLC alone does not see but the length does
+    assert!(mock_rlc_concat(&[1, 2, 3], &[4, 5, 0], &[1, 2, 3, 4, 5], 4).verify().is_err());
+    assert!(mock_rlc_concat(&[1, 2], &[3, 4], &[1, 2, 4, 3], 4).verify().is_err());
+}
+
+// Adds `a` and `b` in the scope "outer", and claims `a * b = c` in the scope "outer/claimed_mul". Also returns the cells
+// recorded in debug mode.
+fn mock_debug_scopes(
+    a: u64,
+    b: u64,
+    c: u64,
+    shadow_checks: bool,
+) -> (MockProver<Fr>, Option<DebugInfo>) {
+    let debug_info = RefCell::new(None);
+    let prover = mock_circuit::<FlexGate<6>>(|config, ctx| {
+        ctx.enable_debug_scopes();
+        if shadow_checks {
+            ctx.enable_shadow_checks();
+        }
+
+        ctx.push_scope("outer");
+        let cells = config.assign_witnesses(ctx, [a, b].map(|x| Value::known(Fr::from(x))));
+        config.add(ctx, Existing(&cells[0]), Existing(&cells[1]));
+        ctx.push_scope("claimed_mul");
+        config.assign_region(
+            ctx,
+            [
+                Constant(Fr::zero()),
+                Existing(&cells[0]),
+                Existing(&cells[1]),
+                Witness(Value::known(Fr::from(c))),
+            ],
+            [(0, None)],
+        );
+        ctx.pop_scope();
+        ctx.pop_scope();
+
+        debug_info.replace(ctx.take_debug_info());
+    });
+    (prover, debug_info.take())
+}
+
+#[test]
+fn test_debug_scopes() {
+    let (prover, debug_info) = mock_debug_scopes(3, 5, 16, false);
+    let debug_info = debug_info.expect("debug scopes should be recorded");
+    let failures = prover.verify().unwrap_err();
+    assert_eq!(failures.len(), 1);
+    let cells = debug_info.involved_cells(&failures[0]);
+    assert_eq!(cells.len(), 4);
+    assert!(cells.iter().all(|cell| cell.scope == "outer/claimed_mul"));
+    assert!(cells.iter().all(|cell| cell.column == cells[0].column));
+    assert!(debug_info.explain_all(&failures).contains("\"outer/claimed_mul\""));
+
+    mock_debug_scopes(3, 5, 15, false).0.assert_satisfied();
+}
+
+#[test]
+fn test_shadow_checks() {
+    mock_debug_scopes(3, 5, 15, true).0.assert_satisfied();
+}
+
+#[test]
+#[should_panic(expected = "shadow check failed in scope \"outer/claimed_mul\": basic gate")]
+fn test_shadow_checks_fail() {
+    mock_debug_scopes(3, 5, 16, true);
+}
+
+// Witnesses each sub-computation `(a * b + i) * i + a - b` for `i` in `0..4` in its own virtual context, in parallel, and
+// range checks it to `range_bits` bits
+fn mock_virtual_contexts(a: u64, b: u64, range_bits: usize) -> MockProver<Fr> {
+    mock_circuit::<Range<NUM_ADVICE, 3, 8>>(|config, ctx| {
+        let params = ContextParams::new(config.gate.max_rows, 1, config.gate.constants.clone());
+        let [a, b] = [a, b].map(Fr::from);
+        let external = config.gate.assign_witnesses(ctx, [a, b].map(Value::known));
+
+        let (virtual_ctxs, outs): (Vec<_>, Vec<_>) = (0..4u64)
+            .into_par_iter()
+            .map(|i| {
+                let mut vctx = Context::new_virtual(params.clone());
+                let a = vctx.load_external(&external[0]);
+                let b = vctx.load_external(&external[1]);
+                let i = Fr::from(i);
+                let gate = &config.gate;
+                let ab = gate.mul_add(&mut vctx, Existing(&a), Existing(&b), Constant(i));
+                let out = gate.inner_product(
+                    &mut vctx,
+                    [Existing(&ab), Existing(&a)],
+                    [Constant(i), Constant(Fr::one())],
+                );
+                let out = gate.sub(&mut vctx, Existing(&out), Existing(&b));
+                config.range_check(&mut vctx, &out, range_bits);
+                let is_zero = gate.is_zero(&mut vctx, &out);
+                gate.assert_is_const(&mut vctx, &is_zero, Fr::zero());
+                (vctx.into_virtual(), out)
+            })
+            .unzip();
+
+        let assigned = assign_virtual_contexts(&config.gate, ctx, virtual_ctxs);
+        for (i, (assigned, out)) in assigned.iter().zip(outs.iter()).enumerate() {
+            let i = Fr::from(i as u64);
+            let expected = (a * b + i) * i + a - b;
+            let out = assigned.get(out);
+            out.value().assert_if_known(|v| **v == expected);
+            // cells of the virtual context can be used in the real context once assigned
+            config.gate.assert_equal(ctx, Existing(out), Witness(Value::known(expected)));
+        }
+    })
+}
+
+#[test]
+fn test_virtual_contexts() {
+    mock_virtual_contexts(12, 10, 9).assert_satisfied();
+    // the range checks recorded in the virtual contexts are enforced: `(a * b + 3) * 3 + a - b = 371` has more than 8 bits
+    assert!(mock_virtual_contexts(12, 10, 8).verify().is_err());
+}
+
+// Loads `array` into a dynamic lookup table, reads it at each of `indices`, and looks up the extra `(key, value)` pair
+// `lookup`
+fn mock_dynamic_lookup(
+    array: &[u64],
+    indices: &[u64],
+    lookup: Option<(u64, u64)>,
+) -> MockProver<Fr> {
+    mock_circuit::<FlexGateDynamicLookup<8>>(|(gate, dynamic_lookup), ctx| {
+        let array = gate.assign_witnesses(ctx, array.iter().map(|x| Value::known(Fr::from(*x))));
+        let mut table = DynamicLookupTable::from_array(ctx, gate, array.iter().cloned());
+        for idx in indices {
+            let idx = gate.load_witness(ctx, Value::known(Fr::from(*idx)));
+            let value = table.read(ctx, gate, &idx);
+            let expected = gate.select_from_idx(ctx, array.iter().map(Existing), Existing(&idx));
+            ctx.constrain_equal(&value, &expected);
+        }
+        if let Some((key, value)) = lookup {
+            let [key, value] =
+                [key, value].map(|a| gate.load_witness(ctx, Value::known(Fr::from(a))));
+            table.lookup(key, value);
+        }
+
+        dynamic_lookup.finalize(ctx, &table);
+    })
+}
+
+#[test]
+fn test_dynamic_lookup() {
+    let array = (0..20u64).map(|i| i * i + 7).collect::<Vec<_>>();
+    mock_dynamic_lookup(&array, &[0, 19, 3, 3, 11], Some((5, 32))).assert_satisfied();
+}
+
+#[test]
+fn test_dynamic_lookup_not_in_table() {
+    let array = (0..20u64).map(|i| i * i + 7).collect::<Vec<_>>();
+    for lookup in [(5u64, 33u64), (20, 0), (0, 0)] {
+        assert!(mock_dynamic_lookup(&array, &[], Some(lookup)).verify().is_err());
+    }
+}
+
 mod lagrange {
     use crate::halo2_proofs::{
         arithmetic::Field,
@@ -368,11 +1189,7 @@ mod lagrange {
 
                     let mut aux = Context::new(
                         region,
-                        ContextParams {
-                            max_rows: config.max_rows,
-                            num_context_ids: 1,
-                            fixed_columns: config.constants.clone(),
-                        },
+                        ContextParams::new(config.max_rows, 1, config.constants.clone()),
                     );
                     let ctx = &mut aux;
 
@@ -460,4 +1277,103 @@ mod lagrange {
 
         Ok(())
     }
+
+    #[derive(Default)]
+    struct BarycentricCircuit<F> {
+        ys: Vec<Value<F>>,
+        a: Value<F>,
+    }
+
+    impl Circuit<Fr> for BarycentricCircuit<Fr> {
+        type Config = FlexGateConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { ys: self.ys.iter().map(|_| Value::unknown()).collect(), a: Value::unknown() }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FlexGateConfig::configure(meta, GateStrategy::PlonkPlus, &[NUM_ADVICE], 1, 0, 14)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut first_pass = SKIP_FIRST_PASS;
+
+            layouter.assign_region(
+                || "gate",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let mut aux = Context::new(
+                        region,
+                        ContextParams::new(config.max_rows, 1, config.constants.clone()),
+                    );
+                    let ctx = &mut aux;
+
+                    let domain = (0..self.ys.len() as u64).map(Fr::from).collect::<Vec<_>>();
+                    let x =
+                        domain.iter().map(|x| config.load_constant(ctx, *x)).collect::<Vec<_>>();
+                    let ys = config.assign_witnesses(ctx, self.ys.clone());
+                    let a = config.assign_witnesses(ctx, vec![self.a]).pop().unwrap();
+
+                    let (eval, z) = config.lagrange_and_eval(
+                        ctx,
+                        &x.into_iter().zip(ys.iter().cloned()).collect::<Vec<_>>(),
+                        &a,
+                    );
+                    let (bary_eval, bary_z) =
+                        config.lagrange_and_eval_barycentric(ctx, &domain, &ys, &a);
+                    ctx.constrain_equal(&eval, &bary_eval);
+                    ctx.constrain_equal(&z, &bary_z);
+
+                    let inverses = config.batch_invert(ctx, &ys);
+                    for (y, inv) in ys.iter().zip(inverses.iter()) {
+                        let prod = config.mul(ctx, Existing(y), Existing(inv));
+                        config.assert_is_const(ctx, &prod, Fr::one());
+                    }
+
+                    #[cfg(feature = "display")]
+                    {
+                        println!("total advice cells: {}", ctx.total_advice);
+                    }
+
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_lagrange_barycentric() {
+        let k = 14;
+        let mut rng = OsRng;
+        let circuit = BarycentricCircuit::<Fr> {
+            ys: (0..50).map(|_| Value::known(Fr::random(&mut rng))).collect(),
+            a: Value::known(Fr::random(&mut rng)),
+        };
+
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_lagrange_barycentric_in_domain() {
+        let k = 14;
+        let mut rng = OsRng;
+        let circuit = BarycentricCircuit::<Fr> {
+            ys: (0..50).map(|_| Value::known(Fr::random(&mut rng))).collect(),
+            // x in the domain
+            a: Value::known(Fr::from(7u64)),
+        };
+
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
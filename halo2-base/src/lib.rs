@@ -32,17 +32,21 @@ pub use halo2_proofs_axiom as halo2_proofs;
 
 use halo2_proofs::{
     circuit::{AssignedCell, Cell, Region, Value},
-    plonk::{Advice, Assigned, Column, Fixed},
+    plonk::{Advice, Assigned, Challenge, Column, Fixed},
 };
 use rustc_hash::FxHashMap;
 #[cfg(feature = "halo2-pse")]
 use std::marker::PhantomData;
 use std::{cell::RefCell, rc::Rc};
-use utils::ScalarField;
+use utils::{Primitive, ScalarField};
+use virtual_context::{VirtualCell, VirtualContext};
 
+pub mod debug;
 pub mod gates;
+pub mod poly;
 // pub mod hashes;
 pub mod utils;
+pub mod virtual_context;
 
 #[cfg(feature = "halo2-axiom")]
 pub const SKIP_FIRST_PASS: bool = false;
@@ -72,62 +76,143 @@ impl<F: ScalarField> QuantumCell<'_, '_, F> {
     }
 }
 
+/// An assigned cell together with its value.
+///
+/// The cell used to be the public field `cell` (and `value`, `row_offset` with `halo2-pse`). A cell of a virtual context has no
+/// location in the region, so these are now read with [`AssignedValue::cell`], [`AssignedValue::value`], [`AssignedValue::row`],
+/// and with `halo2-axiom` [`AssignedValue::assigned_cell`], which behave as the fields did for cells of a `Context` with a region.
 #[derive(Clone, Debug)]
 pub struct AssignedValue<'a, F: ScalarField> {
-    #[cfg(feature = "halo2-axiom")]
-    pub cell: AssignedCell<&'a Assigned<F>, F>,
-
-    #[cfg(feature = "halo2-pse")]
-    pub cell: Cell,
-    #[cfg(feature = "halo2-pse")]
-    pub value: Value<F>,
-    #[cfg(feature = "halo2-pse")]
-    pub row_offset: usize,
-    #[cfg(feature = "halo2-pse")]
-    pub _marker: PhantomData<&'a F>,
+    cell: ValueCell<'a, F>,
 
     #[cfg(feature = "display")]
     pub context_id: usize,
 }
 
+// Where the value of an `AssignedValue` lives
+#[derive(Clone, Debug)]
+enum ValueCell<'a, F: ScalarField> {
+    #[cfg(feature = "halo2-axiom")]
+    Region(AssignedCell<&'a Assigned<F>, F>),
+    #[cfg(feature = "halo2-pse")]
+    Region {
+        cell: Cell,
+        value: Value<F>,
+        row_offset: usize,
+        _marker: PhantomData<&'a F>,
+    },
+    // cell `cell` of the virtual context with id `id`, which is only assigned to the region by `assign_virtual_contexts`
+    Virtual {
+        id: usize,
+        cell: VirtualCell,
+        value: Value<F>,
+    },
+}
+
 impl<'a, F: ScalarField> AssignedValue<'a, F> {
+    #[cfg(feature = "halo2-axiom")]
+    pub fn new(
+        cell: AssignedCell<&'a Assigned<F>, F>,
+        #[cfg(feature = "display")] context_id: usize,
+    ) -> Self {
+        Self {
+            cell: ValueCell::Region(cell),
+            #[cfg(feature = "display")]
+            context_id,
+        }
+    }
+
+    #[cfg(feature = "halo2-pse")]
+    pub fn new(
+        cell: Cell,
+        value: Value<F>,
+        row_offset: usize,
+        #[cfg(feature = "display")] context_id: usize,
+    ) -> Self {
+        Self {
+            cell: ValueCell::Region { cell, value, row_offset, _marker: PhantomData },
+            #[cfg(feature = "display")]
+            context_id,
+        }
+    }
+
+    pub(crate) fn new_virtual(
+        id: usize,
+        cell: VirtualCell,
+        value: Value<F>,
+        #[cfg(feature = "display")] context_id: usize,
+    ) -> Self {
+        Self {
+            cell: ValueCell::Virtual { id, cell, value },
+            #[cfg(feature = "display")]
+            context_id,
+        }
+    }
+
     #[cfg(feature = "display")]
     pub fn context_id(&self) -> usize {
         self.context_id
     }
 
-    pub fn row(&self) -> usize {
-        #[cfg(feature = "halo2-axiom")]
-        {
-            self.cell.row_offset()
+    /// Whether this cell was assigned in a virtual context, see [`virtual_context`]
+    pub fn is_virtual(&self) -> bool {
+        matches!(self.cell, ValueCell::Virtual { .. })
+    }
+
+    /// Returns the id of the virtual context this cell was assigned in, and its location there
+    pub(crate) fn virtual_cell(&self) -> Option<(usize, VirtualCell)> {
+        match self.cell {
+            ValueCell::Virtual { id, cell, .. } => Some((id, cell)),
+            _ => None,
         }
+    }
 
-        #[cfg(feature = "halo2-pse")]
-        {
-            self.row_offset
+    /// Panics for a cell of a virtual context, which has no row until it is assigned with [`assign_virtual_contexts`](virtual_context::assign_virtual_contexts)
+    pub fn row(&self) -> usize {
+        match &self.cell {
+            #[cfg(feature = "halo2-axiom")]
+            ValueCell::Region(cell) => cell.row_offset(),
+            #[cfg(feature = "halo2-pse")]
+            ValueCell::Region { row_offset, .. } => *row_offset,
+            ValueCell::Virtual { .. } => panic!("{VIRTUAL_CELL_UNASSIGNED}"),
         }
     }
 
+    /// Panics for a cell of a virtual context, which has no location until it is assigned with [`assign_virtual_contexts`](virtual_context::assign_virtual_contexts)
     #[cfg(feature = "halo2-axiom")]
     pub fn cell(&self) -> &Cell {
-        self.cell.cell()
+        self.assigned_cell().cell()
     }
+    /// Panics for a cell of a virtual context, which has no location until it is assigned with [`assign_virtual_contexts`](virtual_context::assign_virtual_contexts)
     #[cfg(feature = "halo2-pse")]
     pub fn cell(&self) -> Cell {
-        self.cell
+        match &self.cell {
+            ValueCell::Region { cell, .. } => *cell,
+            ValueCell::Virtual { .. } => panic!("{VIRTUAL_CELL_UNASSIGNED}"),
+        }
+    }
+
+    /// Returns the cell in the region with its value, i.e., the former public field `cell`.
+    ///
+    /// Panics for a cell of a virtual context, which has no location until it is assigned with [`assign_virtual_contexts`](virtual_context::assign_virtual_contexts)
+    #[cfg(feature = "halo2-axiom")]
+    pub fn assigned_cell(&self) -> &AssignedCell<&'a Assigned<F>, F> {
+        match &self.cell {
+            ValueCell::Region(cell) => cell,
+            ValueCell::Virtual { .. } => panic!("{VIRTUAL_CELL_UNASSIGNED}"),
+        }
     }
 
     pub fn value(&self) -> Value<&F> {
-        #[cfg(feature = "halo2-axiom")]
-        {
-            self.cell.value().map(|a| match *a {
+        match &self.cell {
+            #[cfg(feature = "halo2-axiom")]
+            ValueCell::Region(cell) => cell.value().map(|a| match *a {
                 Assigned::Trivial(a) => a,
                 _ => unreachable!(),
-            })
-        }
-        #[cfg(feature = "halo2-pse")]
-        {
-            self.value.as_ref()
+            }),
+            #[cfg(feature = "halo2-pse")]
+            ValueCell::Region { value, .. } => value.as_ref(),
+            ValueCell::Virtual { value, .. } => value.as_ref(),
         }
     }
 
@@ -139,7 +224,7 @@ impl<'a, F: ScalarField> AssignedValue<'a, F> {
         offset: usize,
     ) -> AssignedCell<&'v Assigned<F>, F> {
         let assigned_cell = region
-            .assign_advice(column, offset, self.cell.value().map(|v| **v))
+            .assign_advice(column, offset, self.assigned_cell().value().map(|v| **v))
             .unwrap_or_else(|err| panic!("{err:?}"));
         region.constrain_equal(assigned_cell.cell(), self.cell());
 
@@ -154,7 +239,7 @@ impl<'a, F: ScalarField> AssignedValue<'a, F> {
         offset: usize,
     ) -> Cell {
         let cell = region
-            .assign_advice(|| "", column, offset, || self.value)
+            .assign_advice(|| "", column, offset, || self.value().copied())
             .expect("assign copy advice should not fail")
             .cell();
         region.constrain_equal(cell, self.cell()).expect("constrain equal should not fail");
@@ -163,21 +248,32 @@ impl<'a, F: ScalarField> AssignedValue<'a, F> {
     }
 }
 
+const VIRTUAL_CELL_UNASSIGNED: &str =
+    "a cell of a virtual context is not in the region until it is assigned with `assign_virtual_contexts`";
+
 // The reason we have a `Context` is that we will need to mutably borrow `advice_rows` (etc.) to update row count
 // The `Circuit` trait takes in `Config` as an input that is NOT mutable, so we must pass around &mut Context everywhere for function calls
 // We follow halo2wrong's convention of having `Context` also include the `Region` to be passed around, instead of a `Layouter`, so that everything happens within a single `layouter.assign_region` call. This allows us to circumvent the Halo2 layouter and use our own "pseudo-layouter", which is more specialized (and hence faster) for our specific gates
 #[derive(Debug)]
 pub struct Context<'a, F: ScalarField> {
-    pub region: Region<'a, F>, // I don't see a reason to use Box<Region<'a, F>> since we will pass mutable reference of `Context` anyways
+    // I don't see a reason to use Box<Region<'a, F>> since we will pass mutable reference of `Context` anyways
+    // `None` for a virtual context, see `Context::new_virtual`; read with `Context::region`, which replaces the former public field
+    region: Option<Region<'a, F>>,
+    // records the regions of a virtual context instead of assigning them
+    virtual_ctx: Option<VirtualContext<'a, F>>,
 
     pub max_rows: usize,
 
-    // Assigning advice in a "horizontal" first fashion requires getting the column with min rows used each time `assign_region` is called, which takes a toll on witness generation speed, so instead we will just assigned a column all the way down until it reaches `max_rows` and then increment the column index
+    // Assigning advice in a "horizontal" first fashion requires getting the column with min rows used each time `assign_region` is called, which takes a toll on witness generation speed, so by default we will just assigned a column all the way down until it reaches `max_rows` and then increment the column index
+    // The "horizontal" fashion is available as `AdviceAllocStrategy::Balanced`
+    pub advice_alloc_strategy: AdviceAllocStrategy,
     //
     /// `advice_alloc[context_id] = (index, offset)` where `index` contains the current column index corresponding to `context_id`, and `offset` contains the current row offset within column `index`
     ///
     /// This assumes the phase is `ctx.current_phase()` to enforce the design pattern that advice should be assigned one phase at a time.
     pub advice_alloc: Vec<(usize, usize)>, // [Vec<(usize, usize)>; MAX_PHASE],
+    // only used with `AdviceAllocStrategy::Balanced`: `advice_rows[context_id][index]` is the number of rows used in column `index` in the current phase
+    advice_rows: Vec<Vec<usize>>,
 
     #[cfg(feature = "display")]
     pub total_advice: usize,
@@ -203,14 +299,29 @@ pub struct Context<'a, F: ScalarField> {
 
     // `cells_to_lookup` is a vector keeping track of all cells that we want to enable lookup for. When there is more than 1 advice column we will copy_advice all of these cells to the single lookup enabled column and do lookups there
     pub cells_to_lookup: Vec<AssignedValue<'a, F>>,
+    // `cells_to_byte_lookup` keeps track of the rows `(tag, cells)` to look up in the byte lookup table of `RangeConfig`, see `gates::range::ByteLookupConfig`
+    pub cells_to_byte_lookup: Vec<(u64, Vec<AssignedValue<'a, F>>)>,
+    // `cells_to_table_lookup[table_id]` keeps track of the rows `(tag, cells)` to look up in lookup tables configured outside of this crate, which copy them to their own lookup advice columns at the end of the phase in the same way as `gates::range::ByteLookupConfig`
+    pub cells_to_table_lookup: FxHashMap<usize, Vec<(u64, Vec<AssignedValue<'a, F>>)>>,
 
     current_phase: usize,
+    // `challenges[phase]` is the verifier challenge usable after phase `phase` together with its value read from the layouter, see `Context::set_challenge`
+    challenges: [Option<(Challenge, Value<F>)>; MAX_PHASE],
+    // the cell assigned by `RlcConfig::load_gamma` in this region, returned by later calls instead of reassigning it
+    pub(crate) gamma_cell: Option<AssignedValue<'a, F>>,
+
+    // named scopes and the cells assigned in them, only recorded after `enable_debug_scopes`
+    debug_scopes: Option<debug::DebugScopes>,
+    // checks the witness against the constraints as it is assigned, only after `enable_shadow_checks`
+    shadow_checker: Option<debug::ShadowChecker<F>>,
 
     #[cfg(feature = "display")]
     pub op_count: FxHashMap<String, usize>,
     #[cfg(feature = "display")]
     pub advice_alloc_cache: [Vec<(usize, usize)>; MAX_PHASE],
     #[cfg(feature = "display")]
+    advice_rows_cache: [Vec<Vec<usize>>; MAX_PHASE],
+    #[cfg(feature = "display")]
     pub total_lookup_cells: [usize; MAX_PHASE],
     #[cfg(feature = "display")]
     pub total_fixed: usize,
@@ -231,23 +342,71 @@ impl<'a, F: ScalarField> std::fmt::Display for Context<'a, F> {
     }
 }
 
+/// How a `Context` chooses the advice column to assign a new region of cells to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AdviceAllocStrategy {
+    /// Fill one advice column all the way down to `max_rows` before moving on to the next column.
+    ///
+    /// This is the fastest for witness generation, but the rows used are not balanced across columns.
+    #[default]
+    Vertical,
+    /// Assign each new region to the advice column with the fewest rows used so far, so rows are balanced across all advice columns.
+    ///
+    /// A region that overlaps with the previous region (i.e., has a negative gate offset) stays in the column of the previous region.
+    Balanced,
+}
+
 // a single struct to package any configuration parameters we will need for constructing a new `Context`
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ContextParams {
     pub max_rows: usize,
     /// `num_advice[context_id][phase]` contains the number of advice columns that context `context_id` keeps track of in phase `phase`
     pub num_context_ids: usize,
     pub fixed_columns: Vec<Column<Fixed>>,
+    pub advice_alloc_strategy: AdviceAllocStrategy,
+}
+
+impl ContextParams {
+    /// Parameters with the default [`AdviceAllocStrategy::Vertical`]
+    pub fn new(max_rows: usize, num_context_ids: usize, fixed_columns: Vec<Column<Fixed>>) -> Self {
+        Self {
+            max_rows,
+            num_context_ids,
+            fixed_columns,
+            advice_alloc_strategy: AdviceAllocStrategy::Vertical,
+        }
+    }
 }
 
 impl<'a, F: ScalarField> Context<'a, F> {
     pub fn new(region: Region<'a, F>, params: ContextParams) -> Self {
+        Self::with_region(Some(region), params)
+    }
+
+    /// Creates a virtual context: a `Context` without a `Region` that records the regions, copy constraints, constants, and lookups
+    /// of the chips run on it instead of assigning them, see [`virtual_context`].
+    ///
+    /// Virtual contexts can be created and used in separate threads. Convert it with [`Context::into_virtual`] when done, and
+    /// assign it to the region with [`assign_virtual_contexts`](virtual_context::assign_virtual_contexts).
+    ///
+    /// A virtual context stays in the first phase and only supports chips that assign their cells through
+    /// [`GateInstructions`](gates::GateInstructions) and the lookups of `Context`; anything that needs the region directly panics.
+    pub fn new_virtual(params: ContextParams) -> Self {
+        let mut ctx = Self::with_region(None, params);
+        ctx.virtual_ctx = Some(VirtualContext::new(ctx.current_phase));
+        ctx
+    }
+
+    fn with_region(region: Option<Region<'a, F>>, params: ContextParams) -> Self {
         let advice_alloc = vec![(0, 0); params.num_context_ids];
 
         Self {
             region,
+            virtual_ctx: None,
             max_rows: params.max_rows,
+            advice_alloc_strategy: params.advice_alloc_strategy,
             advice_alloc,
+            advice_rows: vec![vec![]; params.num_context_ids],
             #[cfg(feature = "display")]
             total_advice: 0,
             preallocated_vec_to_assign: Rc::new(RefCell::new(Vec::with_capacity(256))),
@@ -257,18 +416,77 @@ impl<'a, F: ScalarField> Context<'a, F> {
             assigned_constants: FxHashMap::default(),
             zero_cell: None,
             cells_to_lookup: Vec::new(),
+            cells_to_byte_lookup: Vec::new(),
+            cells_to_table_lookup: FxHashMap::default(),
             current_phase: 0,
+            challenges: [None; MAX_PHASE],
+            gamma_cell: None,
+            debug_scopes: None,
+            shadow_checker: None,
             #[cfg(feature = "display")]
             op_count: FxHashMap::default(),
             #[cfg(feature = "display")]
             advice_alloc_cache: [(); MAX_PHASE].map(|_| vec![]),
             #[cfg(feature = "display")]
+            advice_rows_cache: [(); MAX_PHASE].map(|_| vec![]),
+            #[cfg(feature = "display")]
             total_lookup_cells: [0; MAX_PHASE],
             #[cfg(feature = "display")]
             total_fixed: 0,
         }
     }
 
+    /// Returns the region of this context, i.e., the former public field `region`.
+    ///
+    /// Panics for a virtual context, see [`Context::new_virtual`]; use [`Context::try_region`] if the context may be virtual.
+    pub fn region(&mut self) -> &mut Region<'a, F> {
+        self.region.as_mut().expect("a virtual context has no region")
+    }
+
+    /// Returns the region of this context, or `None` for a virtual context.
+    pub fn try_region(&mut self) -> Option<&mut Region<'a, F>> {
+        self.region.as_mut()
+    }
+
+    /// Returns the region of this context, consuming it.
+    ///
+    /// Panics for a virtual context.
+    pub fn into_region(self) -> Region<'a, F> {
+        self.region.expect("a virtual context has no region")
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.virtual_ctx.is_some()
+    }
+
+    pub(crate) fn virtual_ctx_mut(&mut self) -> Option<&mut VirtualContext<'a, F>> {
+        self.virtual_ctx.as_mut()
+    }
+
+    /// Returns a cell of this virtual context with the value of `a`, a cell of the real context.
+    ///
+    /// The cells of the real context can only be used in a virtual context through this, and the returned cell is replaced by `a`
+    /// itself when the virtual context is assigned.
+    pub fn load_external(&mut self, a: &AssignedValue<'a, F>) -> AssignedValue<'a, F> {
+        self.virtual_ctx
+            .as_mut()
+            .expect("external cells can only be loaded into a virtual context")
+            .load_external(a)
+    }
+
+    /// Finishes a virtual context, returning everything it recorded to pass to [`assign_virtual_contexts`](virtual_context::assign_virtual_contexts).
+    ///
+    /// Unlike `Context`, the returned [`VirtualContext`] can be sent back to the thread that owns the real context.
+    pub fn into_virtual(mut self) -> VirtualContext<'a, F> {
+        let mut virtual_ctx = self.virtual_ctx.take().expect("not a virtual context");
+        virtual_ctx.record_lookups(
+            std::mem::take(&mut self.cells_to_lookup),
+            std::mem::take(&mut self.cells_to_byte_lookup),
+            std::mem::take(&mut self.cells_to_table_lookup),
+        );
+        virtual_ctx
+    }
+
     pub fn preallocated_vec_to_assign(&self) -> Rc<RefCell<Vec<AssignedValue<'a, F>>>> {
         Rc::clone(&self.preallocated_vec_to_assign)
     }
@@ -278,16 +496,29 @@ impl<'a, F: ScalarField> Context<'a, F> {
             self.cells_to_lookup.is_empty(),
             "THERE ARE STILL ADVICE CELLS THAT NEED TO BE LOOKED UP"
         );
+        assert!(
+            self.cells_to_byte_lookup.is_empty(),
+            "THERE ARE STILL ADVICE CELLS THAT NEED TO BE LOOKED UP IN THE BYTE TABLE"
+        );
+        assert!(
+            self.cells_to_table_lookup.values().all(|rows| rows.is_empty()),
+            "THERE ARE STILL ADVICE CELLS THAT NEED TO BE LOOKED UP IN A CUSTOM TABLE"
+        );
+        assert!(!self.is_virtual(), "a virtual context stays in the phase it was created in");
         #[cfg(feature = "display")]
         {
             self.advice_alloc_cache[self.current_phase] = self.advice_alloc.clone();
+            self.advice_rows_cache[self.current_phase] = self.advice_rows_with_current_alloc();
         }
         #[cfg(feature = "halo2-axiom")]
-        self.region.next_phase();
+        self.region().next_phase();
         self.current_phase += 1;
         for advice_alloc in self.advice_alloc.iter_mut() {
             *advice_alloc = (0, 0);
         }
+        for advice_rows in self.advice_rows.iter_mut() {
+            advice_rows.clear();
+        }
         assert!(self.current_phase < MAX_PHASE);
     }
 
@@ -295,6 +526,210 @@ impl<'a, F: ScalarField> Context<'a, F> {
         self.current_phase
     }
 
+    /// Registers `challenge`, created with `meta.challenge_usable_after(phase)`, so that it can be read with [`Context::get_challenge`].
+    ///
+    /// `value` should be `layouter.get_challenge(challenge)`, read before `layouter.assign_region`: with `halo2-pse` this is the only way to
+    /// get the challenge. With `halo2-axiom` all phases are synthesized in a single pass, so the challenge is instead read from the region
+    /// once the context has moved past phase `phase`, and `value` may be unknown.
+    pub fn set_challenge(&mut self, phase: usize, challenge: Challenge, value: Value<F>) {
+        assert!(phase + 1 < MAX_PHASE, "there is no phase after phase {phase}");
+        self.challenges[phase] = Some((challenge, value));
+    }
+
+    /// Returns the value of the verifier challenge usable after phase `phase`, registered with [`Context::set_challenge`].
+    ///
+    /// The challenge is only drawn after all advice of phase `phase` is committed to, so this can only be called in a later phase.
+    /// The returned value is not constrained to anything; use [`RlcConfig::load_gamma`](gates::rlc::RlcConfig::load_gamma) to get an
+    /// assigned cell constrained to equal the challenge.
+    pub fn get_challenge(&self, phase: usize) -> Value<F> {
+        assert!(
+            phase < self.current_phase,
+            "the challenge usable after phase {phase} is not available in phase {}",
+            self.current_phase
+        );
+        match self.challenges[phase] {
+            #[cfg(feature = "halo2-axiom")]
+            Some((challenge, _)) => self
+                .region
+                .as_ref()
+                .expect("a virtual context has no challenges")
+                .get_challenge(challenge),
+            #[cfg(feature = "halo2-pse")]
+            Some((_, value)) => value,
+            None => {
+                panic!("the challenge usable after phase {phase} was not set with `set_challenge`")
+            }
+        }
+    }
+
+    /// Starts recording the scope path and value of every advice cell assigned from now on, see [`debug`].
+    ///
+    /// The recorded cells can be retrieved with [`Context::take_debug_info`] at the end of synthesis.
+    pub fn enable_debug_scopes(&mut self) {
+        assert!(
+            !self.is_virtual(),
+            "the cells of a virtual context are recorded when it is assigned"
+        );
+        self.debug_scopes.get_or_insert_with(debug::DebugScopes::new);
+    }
+
+    /// Returns the cells recorded since [`Context::enable_debug_scopes`] and stops recording, or `None` if debug scopes are not enabled.
+    ///
+    /// Call this after `finalize` so the cells copied for lookups are included.
+    pub fn take_debug_info(&mut self) -> Option<debug::DebugInfo> {
+        self.debug_scopes.take().map(debug::DebugScopes::into_info)
+    }
+
+    /// Enters the scope `name`, nested in the current scope. Does nothing unless debug scopes are enabled.
+    pub fn push_scope(&mut self, name: &str) {
+        if let Some(debug_scopes) = &mut self.debug_scopes {
+            debug_scopes.push_scope(name);
+        }
+    }
+
+    /// Leaves the current scope. Does nothing unless debug scopes are enabled.
+    pub fn pop_scope(&mut self) {
+        if let Some(debug_scopes) = &mut self.debug_scopes {
+            debug_scopes.pop_scope();
+        }
+    }
+
+    /// Starts checking every basic gate, copy constraint, and lookup against the witness as it is assigned, panicking on the first violation.
+    ///
+    /// The panic message names the current scope if debug scopes are enabled, see [`Context::enable_debug_scopes`].
+    pub fn enable_shadow_checks(&mut self) {
+        assert!(
+            !self.is_virtual(),
+            "the cells of a virtual context are checked when it is assigned"
+        );
+        self.shadow_checker.get_or_insert_with(debug::ShadowChecker::new);
+    }
+
+    pub fn is_shadow_checking(&self) -> bool {
+        self.shadow_checker.is_some()
+    }
+
+    /// If shadow checks are enabled, checks that `holds()` is true, otherwise panics with the description `what()` of the violated constraint
+    pub fn shadow_check(&self, holds: impl FnOnce() -> bool, what: impl FnOnce() -> String) {
+        if self.shadow_checker.is_some() && !holds() {
+            self.shadow_violation(what());
+        }
+    }
+
+    /// If shadow checks are enabled, checks the basic gate enabled at `row` of `column` with coefficients `[q_mul, q_left, q_right]`, see [`debug::ShadowChecker`]
+    pub fn shadow_enable_gate(&mut self, column: Column<Advice>, row: usize, q: [F; 3]) {
+        if let Some(shadow_checker) = &mut self.shadow_checker {
+            if let Err(err) = shadow_checker.enable_gate(column.index(), row, q) {
+                self.shadow_violation(err);
+            }
+        }
+    }
+
+    fn shadow_violation(&self, what: String) -> ! {
+        match &self.debug_scopes {
+            Some(debug_scopes) => {
+                panic!("shadow check failed in scope \"{}\": {what}", debug_scopes.current_scope())
+            }
+            None => panic!("shadow check failed: {what}"),
+        }
+    }
+
+    /// Adds `acell` to the cells to look up at the end of the phase
+    pub fn push_cell_to_lookup(&mut self, acell: AssignedValue<'a, F>) {
+        if let Some(debug_scopes) = &mut self.debug_scopes {
+            debug_scopes.record_lookup();
+        }
+        self.cells_to_lookup.push(acell);
+    }
+
+    fn record_assigned_cell(&mut self, column: Column<Advice>, row: usize, value: Value<&F>) {
+        if let Some(debug_scopes) = &mut self.debug_scopes {
+            debug_scopes.record_cell(
+                column.index(),
+                row,
+                format!("{:?}", utils::value_to_option(value)),
+            );
+        }
+        if let Some(shadow_checker) = &mut self.shadow_checker {
+            let value = utils::value_to_option(value).copied();
+            if let Err(err) = shadow_checker.record_cell(column.index(), row, value) {
+                self.shadow_violation(err);
+            }
+        }
+    }
+
+    /// Returns `(index, offset)` of the advice column and row offset to start assigning `len` new cells for `context_id` in the current phase,
+    /// where there are `num_columns` advice columns available, and sets `advice_alloc[context_id]` to it.
+    ///
+    /// The caller is responsible for incrementing `advice_alloc[context_id].1` after assigning the cells.
+    ///
+    /// If `overlap` is true, the new cells are gated together with the cells of the previous assignment, so with `AdviceAllocStrategy::Balanced` they stay in the same column.
+    ///
+    /// With `AdviceAllocStrategy::Balanced`, panics if no advice column has room for `len` more cells.
+    pub fn advice_alloc_start(
+        &mut self,
+        context_id: usize,
+        len: usize,
+        num_columns: usize,
+        overlap: bool,
+    ) -> (usize, usize) {
+        let alloc = self.advice_alloc.get_mut(context_id).expect("context id out of bounds");
+        match self.advice_alloc_strategy {
+            AdviceAllocStrategy::Vertical => {
+                if alloc.1 + len >= self.max_rows {
+                    alloc.1 = 0;
+                    alloc.0 += 1;
+                }
+            }
+            AdviceAllocStrategy::Balanced => {
+                let rows = &mut self.advice_rows[context_id];
+                if rows.len() < num_columns.max(alloc.0 + 1) {
+                    rows.resize(num_columns.max(alloc.0 + 1), 0);
+                }
+                // sync the row count of the column used by the previous assignment
+                // offsets only increase within a phase, so taking the max also ignores manual resets of `advice_alloc`
+                rows[alloc.0] = rows[alloc.0].max(alloc.1);
+                if overlap && rows[alloc.0] + len < self.max_rows {
+                    alloc.1 = rows[alloc.0];
+                } else {
+                    // first column with the fewest rows used
+                    let (index, offset) = rows[..num_columns]
+                        .iter()
+                        .copied()
+                        .enumerate()
+                        .min_by_key(|(_, offset)| *offset)
+                        .unwrap_or((0, 0));
+                    assert!(
+                        offset + len < self.max_rows,
+                        "context {context_id} ran out of advice cells: all {num_columns} advice columns have no room for {len} more cells below max_rows = {}",
+                        self.max_rows
+                    );
+                    *alloc = (index, offset);
+                }
+            }
+        }
+        *alloc
+    }
+
+    /// Returns `advice_rows` updated with the current allocation in `advice_alloc`
+    ///
+    /// Only meaningful when using `AdviceAllocStrategy::Balanced`
+    #[cfg(feature = "display")]
+    fn advice_rows_with_current_alloc(&self) -> Vec<Vec<usize>> {
+        self.advice_rows
+            .iter()
+            .zip(self.advice_alloc.iter())
+            .map(|(rows, &(index, offset))| {
+                let mut rows = rows.clone();
+                if rows.len() <= index {
+                    rows.resize(index + 1, 0);
+                }
+                rows[index] = rows[index].max(offset);
+                rows
+            })
+            .collect()
+    }
+
     #[cfg(feature = "display")]
     /// Returns (number of fixed columns used, total fixed cells used)
     pub fn fixed_stats(&self) -> (usize, usize) {
@@ -329,21 +764,13 @@ impl<'a, F: ScalarField> Context<'a, F> {
     ///
     /// In situations where you don't expect to reuse the value, you can assign the fixed value directly using this function.
     pub fn assign_fixed_without_caching(&mut self, c: F) -> Cell {
+        let (column, offset) = (self.fixed_columns[self.fixed_col], self.fixed_offset);
         #[cfg(feature = "halo2-axiom")]
-        let cell = self.region.assign_fixed(
-            self.fixed_columns[self.fixed_col],
-            self.fixed_offset,
-            Assigned::Trivial(c),
-        );
+        let cell = self.region().assign_fixed(column, offset, Assigned::Trivial(c));
         #[cfg(feature = "halo2-pse")]
         let cell = self
-            .region
-            .assign_fixed(
-                || "",
-                self.fixed_columns[self.fixed_col],
-                self.fixed_offset,
-                || Value::known(c),
-            )
+            .region()
+            .assign_fixed(|| "", column, offset, || Value::known(c))
             .expect("assign fixed should not fail")
             .cell();
         #[cfg(feature = "display")]
@@ -367,61 +794,32 @@ impl<'a, F: ScalarField> Context<'a, F> {
         #[cfg(feature = "display")] context_id: usize,
         row_offset: usize,
     ) -> AssignedValue<'v, F> {
-        match input {
-            QuantumCell::Existing(acell) => {
-                AssignedValue {
-                    cell: acell.copy_advice(
-                        // || "gate: copy advice",
-                        &mut self.region,
-                        column,
-                        row_offset,
-                    ),
-                    #[cfg(feature = "display")]
-                    context_id,
-                }
-            }
-            QuantumCell::ExistingOwned(acell) => {
-                AssignedValue {
-                    cell: acell.copy_advice(
-                        // || "gate: copy advice",
-                        &mut self.region,
-                        column,
-                        row_offset,
-                    ),
-                    #[cfg(feature = "display")]
-                    context_id,
-                }
-            }
-            QuantumCell::Witness(val) => AssignedValue {
-                cell: self
-                    .region
-                    .assign_advice(column, row_offset, val.map(Assigned::Trivial))
-                    .expect("assign advice should not fail"),
-                #[cfg(feature = "display")]
-                context_id,
-            },
-            QuantumCell::WitnessFraction(val) => AssignedValue {
-                cell: self
-                    .region
-                    .assign_advice(column, row_offset, val)
-                    .expect("assign advice should not fail"),
-                #[cfg(feature = "display")]
-                context_id,
-            },
+        let region = self.region();
+        let cell = match input {
+            QuantumCell::Existing(acell) => acell.copy_advice(region, column, row_offset),
+            QuantumCell::ExistingOwned(acell) => acell.copy_advice(region, column, row_offset),
+            QuantumCell::Witness(val) => region
+                .assign_advice(column, row_offset, val.map(Assigned::Trivial))
+                .expect("assign advice should not fail"),
+            QuantumCell::WitnessFraction(val) => region
+                .assign_advice(column, row_offset, val)
+                .expect("assign advice should not fail"),
             QuantumCell::Constant(c) => {
-                let acell = self
-                    .region
+                let acell = region
                     .assign_advice(column, row_offset, Value::known(Assigned::Trivial(c)))
                     .expect("assign fixed advice should not fail");
                 let c_cell = self.assign_fixed(c);
-                self.region.constrain_equal(acell.cell(), &c_cell);
-                AssignedValue {
-                    cell: acell,
-                    #[cfg(feature = "display")]
-                    context_id,
-                }
+                self.region().constrain_equal(acell.cell(), &c_cell);
+                acell
             }
-        }
+        };
+        let assigned = AssignedValue::new(
+            cell,
+            #[cfg(feature = "display")]
+            context_id,
+        );
+        self.record_assigned_cell(column, row_offset, assigned.value());
+        assigned
     }
 
     #[cfg(feature = "halo2-pse")]
@@ -433,87 +831,97 @@ impl<'a, F: ScalarField> Context<'a, F> {
         row_offset: usize,
         phase: u8,
     ) -> AssignedValue<'v, F> {
-        match input {
+        let region = self.region();
+        let (cell, value) = match input {
             QuantumCell::Existing(acell) => {
-                AssignedValue {
-                    cell: acell.copy_advice(
-                        // || "gate: copy advice",
-                        &mut self.region,
-                        column,
-                        row_offset,
-                    ),
-                    value: acell.value,
-                    row_offset,
-                    _marker: PhantomData,
-                    #[cfg(feature = "display")]
-                    context_id,
-                }
+                (acell.copy_advice(region, column, row_offset), acell.value().copied())
             }
             QuantumCell::ExistingOwned(acell) => {
-                AssignedValue {
-                    cell: acell.copy_advice(
-                        // || "gate: copy advice",
-                        &mut self.region,
-                        column,
-                        row_offset,
-                    ),
-                    value: acell.value,
-                    row_offset,
-                    _marker: PhantomData,
-                    #[cfg(feature = "display")]
-                    context_id,
-                }
+                (acell.copy_advice(region, column, row_offset), acell.value().copied())
             }
-            QuantumCell::Witness(value) => AssignedValue {
-                cell: self
-                    .region
+            QuantumCell::Witness(value) => (
+                region
                     .assign_advice(|| "", column, row_offset, || value)
                     .expect("assign advice should not fail")
                     .cell(),
                 value,
-                row_offset,
-                _marker: PhantomData,
-                #[cfg(feature = "display")]
-                context_id,
-            },
-            QuantumCell::WitnessFraction(val) => AssignedValue {
-                cell: self
-                    .region
+            ),
+            QuantumCell::WitnessFraction(val) => (
+                region
                     .assign_advice(|| "", column, row_offset, || val)
                     .expect("assign advice should not fail")
                     .cell(),
-                value: Value::unknown(),
-                row_offset,
-                _marker: PhantomData,
-                #[cfg(feature = "display")]
-                context_id,
-            },
+                Value::unknown(),
+            ),
             QuantumCell::Constant(c) => {
-                let acell = self
-                    .region
+                let acell = region
                     .assign_advice(|| "", column, row_offset, || Value::known(c))
                     .expect("assign fixed advice should not fail")
                     .cell();
                 let c_cell = self.assign_fixed(c);
-                self.region.constrain_equal(acell, c_cell).unwrap();
-                AssignedValue {
-                    cell: acell,
-                    value: Value::known(c),
-                    row_offset,
-                    _marker: PhantomData,
-                    #[cfg(feature = "display")]
-                    context_id,
-                }
+                self.region().constrain_equal(acell, c_cell).unwrap();
+                (acell, Value::known(c))
             }
-        }
+        };
+        let assigned = AssignedValue::new(
+            cell,
+            value,
+            row_offset,
+            #[cfg(feature = "display")]
+            context_id,
+        );
+        self.record_assigned_cell(column, row_offset, assigned.value());
+        assigned
     }
 
     // convenience function to deal with rust warnings
     pub fn constrain_equal(&mut self, a: &AssignedValue<F>, b: &AssignedValue<F>) {
+        self.shadow_check(
+            || utils::value_to_option(a.value().zip(b.value())).map_or(true, |(a, b)| a == b),
+            || {
+                format!(
+                    "copy constraint between cells at rows {} and {}: {:?} != {:?}",
+                    a.row(),
+                    b.row(),
+                    a.value(),
+                    b.value()
+                )
+            },
+        );
+        if let Some(virtual_ctx) = &mut self.virtual_ctx {
+            virtual_ctx.constrain_equal(a, b);
+            return;
+        }
         #[cfg(feature = "halo2-axiom")]
-        self.region.constrain_equal(a.cell(), b.cell());
+        self.region().constrain_equal(a.cell(), b.cell());
         #[cfg(not(feature = "halo2-axiom"))]
-        self.region.constrain_equal(a.cell(), b.cell()).unwrap();
+        self.region().constrain_equal(a.cell(), b.cell()).unwrap();
+    }
+
+    /// Constrains `a` to equal the constant `c`, assigned to a fixed cell with [`Context::assign_fixed`]
+    pub fn constrain_constant(&mut self, a: &AssignedValue<F>, c: F) {
+        if let Some(virtual_ctx) = &mut self.virtual_ctx {
+            virtual_ctx.constrain_constant(a, c, true);
+            return;
+        }
+        let c_cell = self.assign_fixed(c);
+        #[cfg(feature = "halo2-axiom")]
+        self.region().constrain_equal(a.cell(), &c_cell);
+        #[cfg(feature = "halo2-pse")]
+        self.region().constrain_equal(a.cell(), c_cell).unwrap();
+    }
+
+    /// Same as [`Context::constrain_constant`], but assigns `c` with [`Context::assign_fixed_without_caching`]
+    pub fn constrain_constant_without_caching(&mut self, a: &AssignedValue<F>, c: F) {
+        if let Some(virtual_ctx) = &mut self.virtual_ctx {
+            virtual_ctx.constrain_constant(a, c, false);
+            return;
+        }
+        let c_cell = self.assign_fixed_without_caching(c);
+        #[cfg(feature = "halo2-axiom")]
+        self.region().constrain_equal(a.cell(), &c_cell);
+        #[cfg(feature = "halo2-pse")]
+        self.region().constrain_equal(a.cell(), c_cell).unwrap();
     }
 
     /// Call this at the end of a phase
@@ -521,12 +929,20 @@ impl<'a, F: ScalarField> Context<'a, F> {
     /// assumes self.region is not in shape mode
     pub fn copy_and_lookup_cells(&mut self, lookup_advice: Vec<Column<Advice>>) -> usize {
         let total_cells = self.cells_to_lookup.len();
-        let mut cells_to_lookup = self.cells_to_lookup.iter().peekable();
+        let mut cells_to_lookup = self.cells_to_lookup.iter().enumerate().peekable();
         for column in lookup_advice.into_iter() {
             let mut offset = 0;
             while offset < self.max_rows && cells_to_lookup.peek().is_some() {
-                let acell = cells_to_lookup.next().unwrap();
-                acell.copy_advice(&mut self.region, column, offset);
+                let (idx, acell) = cells_to_lookup.next().unwrap();
+                acell.copy_advice(
+                    self.region.as_mut().expect("a virtual context has no region"),
+                    column,
+                    offset,
+                );
+                if let Some(debug_scopes) = &mut self.debug_scopes {
+                    let value = format!("{:?}", utils::value_to_option(acell.value()));
+                    debug_scopes.record_lookup_copy(idx, column.index(), offset, value);
+                }
                 offset += 1;
             }
         }
@@ -534,6 +950,9 @@ impl<'a, F: ScalarField> Context<'a, F> {
             panic!("NOT ENOUGH ADVICE COLUMNS WITH LOOKUP ENABLED");
         }
         self.cells_to_lookup.clear();
+        if let Some(debug_scopes) = &mut self.debug_scopes {
+            debug_scopes.clear_lookups();
+        }
         #[cfg(feature = "display")]
         {
             self.total_lookup_cells[self.current_phase] = total_cells;
@@ -542,14 +961,56 @@ impl<'a, F: ScalarField> Context<'a, F> {
     }
 
     #[cfg(feature = "display")]
-    pub fn print_stats(&mut self, context_names: &[&str]) {
+    fn update_stats_cache(&mut self) {
         let curr_phase = self.current_phase();
         self.advice_alloc_cache[curr_phase] = self.advice_alloc.clone();
-        for phase in 0..=curr_phase {
-            for (context_name, alloc) in
-                context_names.iter().zip(self.advice_alloc_cache[phase].iter())
+        self.advice_rows_cache[curr_phase] = self.advice_rows_with_current_alloc();
+    }
+
+    /// Returns `(number of advice columns used, total advice cells used)` for each context id in phase `phase`
+    ///
+    /// The total includes cells skipped at the bottom of a column when moving to the next column.
+    #[cfg(feature = "display")]
+    fn advice_stats(&self, phase: usize) -> Vec<(usize, usize)> {
+        self.advice_alloc_cache[phase]
+            .iter()
+            .zip(self.advice_rows_cache[phase].iter())
+            .map(|(alloc, rows)| match self.advice_alloc_strategy {
+                AdviceAllocStrategy::Vertical => (alloc.0 + 1, alloc.0 * self.max_rows + alloc.1),
+                AdviceAllocStrategy::Balanced => {
+                    (rows.iter().filter(|&&r| r != 0).count(), rows.iter().sum())
+                }
+            })
+            .collect()
+    }
+
+    /// Returns statistics about the cells used so far, summed over all context ids
+    #[cfg(feature = "display")]
+    pub fn stats(&mut self) -> ContextStats {
+        self.update_stats_cache();
+        let mut advice_cells = [0; MAX_PHASE];
+        for (phase, advice_cells) in
+            advice_cells.iter_mut().enumerate().take(self.current_phase + 1)
+        {
+            *advice_cells = self.advice_stats(phase).iter().map(|(_, cells)| cells).sum();
+        }
+        ContextStats {
+            max_rows: self.max_rows,
+            advice_cells,
+            lookup_cells: self.total_lookup_cells,
+            fixed_cells: self.total_fixed,
+        }
+    }
+
+    /// Prints the statistics of the cells used so far
+    #[cfg(feature = "display")]
+    pub fn print_stats(&mut self, context_names: &[&str]) {
+        self.update_stats_cache();
+        for phase in 0..=self.current_phase() {
+            for (context_name, (num_columns, total_cells)) in
+                context_names.iter().zip(self.advice_stats(phase))
             {
-                println!("Context \"{context_name}\" used {} advice columns and {} total advice cells in phase {phase}", alloc.0 + 1, alloc.0 * self.max_rows + alloc.1);
+                println!("Context \"{context_name}\" used {num_columns} advice columns and {total_cells} total advice cells in phase {phase}");
             }
             let num_lookup_advice_cells = self.total_lookup_cells[phase];
             println!("Special lookup advice cells: optimal columns: {}, total {num_lookup_advice_cells} cells used in phase {phase}.",  (num_lookup_advice_cells + self.max_rows - 1)/self.max_rows);
@@ -559,17 +1020,36 @@ impl<'a, F: ScalarField> Context<'a, F> {
     }
 }
 
+/// Statistics about the cells used by a [`Context`], summed over all context ids
+#[cfg(feature = "display")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContextStats {
+    /// The maximum number of usable rows per column
+    pub max_rows: usize,
+    /// `advice_cells[phase]` is the number of advice cells used in phase `phase`, including cells skipped at the bottom of a column when moving to the next column
+    pub advice_cells: [usize; MAX_PHASE],
+    /// `lookup_cells[phase]` is the number of cells copied to lookup enabled advice columns in phase `phase`
+    pub lookup_cells: [usize; MAX_PHASE],
+    pub fixed_cells: usize,
+}
+
+/// An [`AssignedValue`] constrained to fit in the unsigned integer type `T`, i.e., to be less than `2^T::BITS`.
+///
+/// Create and operate on these with [`PrimitiveChip`](gates::primitive::PrimitiveChip), which keeps this invariant.
 #[derive(Clone, Debug)]
-pub struct AssignedPrimitive<'a, T: Into<u64> + Copy, F: ScalarField> {
+pub struct AssignedPrimitive<'a, T: Primitive, F: ScalarField> {
     pub value: Value<T>,
+    pub assigned: AssignedValue<'a, F>,
+}
 
-    #[cfg(feature = "halo2-axiom")]
-    pub cell: AssignedCell<&'a Assigned<F>, F>,
-
-    #[cfg(feature = "halo2-pse")]
-    pub cell: Cell,
-    #[cfg(feature = "halo2-pse")]
-    row_offset: usize,
-    #[cfg(feature = "halo2-pse")]
-    _marker: PhantomData<&'a F>,
+impl<'a, T: Primitive, F: ScalarField> AssignedPrimitive<'a, T, F> {
+    /// Wraps `assigned` without any constraints: the caller must ensure `assigned` is less than `2^T::BITS`
+    pub fn new_unchecked(assigned: AssignedValue<'a, F>) -> Self {
+        let value = assigned.value().map(|v| T::from_u64_truncate(v.get_lower_128() as u64));
+        Self { value, assigned }
+    }
 }
+
+pub type AssignedU8<'a, F> = AssignedPrimitive<'a, u8, F>;
+pub type AssignedU32<'a, F> = AssignedPrimitive<'a, u32, F>;
+pub type AssignedU64<'a, F> = AssignedPrimitive<'a, u64, F>;
@@ -0,0 +1,330 @@
+//! Arithmetic of polynomials over the native field whose coefficients or evaluations are assigned cells.
+//!
+//! A polynomial in coefficient form is a slice of cells `coeffs` representing `sum_i coeffs[i] * X^i`.
+//! A polynomial in evaluation form is a slice of cells `evals` with `evals[i] = f(omega^i)` on the roots of unity of an
+//! [`EvaluationDomain`]. All gadgets are built from [`GateInstructions`], so they work with any gate strategy.
+use crate::{
+    gates::GateInstructions,
+    halo2_proofs::circuit::Value,
+    utils::ScalarField,
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use ff::PrimeField;
+use std::marker::PhantomData;
+
+/// The multiplicative subgroup `{1, omega, ..., omega^(n - 1)}` of the `n = 2^k`-th roots of unity of the native field
+#[derive(Clone, Debug)]
+pub struct EvaluationDomain<F: ScalarField> {
+    k: u32,
+    n: usize,
+    omega: F,
+    omega_inv: F,
+    n_inv: F,
+}
+
+impl<F: ScalarField> EvaluationDomain<F> {
+    /// Creates the domain of the `2^k`-th roots of unity. Requires `k <= F::S`.
+    pub fn new(k: u32) -> Self {
+        assert!(k <= F::S, "field has no 2^{k}-th roots of unity");
+        // `root_of_unity` is a primitive 2^S-th root of unity
+        let mut omega = F::root_of_unity();
+        for _ in k..F::S {
+            omega = omega.square();
+        }
+        let n = 1usize << k;
+        Self {
+            k,
+            n,
+            omega,
+            omega_inv: omega.invert().unwrap(),
+            n_inv: F::from(n as u64).invert().unwrap(),
+        }
+    }
+
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    /// The number `n = 2^k` of elements of the domain
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    /// The generator `omega` of the domain, a primitive `n`-th root of unity
+    pub fn omega(&self) -> F {
+        self.omega
+    }
+
+    pub fn omega_inv(&self) -> F {
+        self.omega_inv
+    }
+
+    /// The elements `omega^i` for `i = 0..n`
+    pub fn elements(&self) -> Vec<F> {
+        std::iter::successors(Some(F::one()), |x| Some(*x * self.omega)).take(self.n).collect()
+    }
+
+    /// Returns the evaluations on the domain of the polynomial with coefficients `coeffs`.
+    ///
+    /// Requires `coeffs.len() <= n`.
+    pub fn coeffs_to_evals(&self, coeffs: &[F]) -> Vec<F> {
+        assert!(coeffs.len() <= self.n, "polynomial degree too large for domain");
+        let mut values = coeffs.to_vec();
+        values.resize(self.n, F::zero());
+        fft(&mut values, self.omega);
+        values
+    }
+
+    /// Returns the coefficients of the polynomial of degree less than `n` with evaluations `evals` on the domain.
+    ///
+    /// Requires `evals.len() == n`.
+    pub fn evals_to_coeffs(&self, evals: &[F]) -> Vec<F> {
+        assert_eq!(evals.len(), self.n);
+        let mut values = evals.to_vec();
+        fft(&mut values, self.omega_inv);
+        for value in values.iter_mut() {
+            *value *= self.n_inv;
+        }
+        values
+    }
+}
+
+/// In-place radix-2 FFT: replaces `values[i]` with `sum_j values[j] * omega^(i * j)`.
+///
+/// `values.len()` must be a power of two and `omega` a primitive `values.len()`-th root of unity.
+pub fn fft<F: PrimeField>(values: &mut [F], omega: F) {
+    let n = values.len();
+    assert!(n.is_power_of_two());
+    if n == 1 {
+        return;
+    }
+    let log_n = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - log_n);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let mut w_len = omega;
+        for _ in 0..(n / len).trailing_zeros() {
+            w_len = w_len.square();
+        }
+        for chunk in values.chunks_mut(len) {
+            let mut w = F::one();
+            let (lo, hi) = chunk.split_at_mut(len / 2);
+            for (u, v) in lo.iter_mut().zip(hi.iter_mut()) {
+                let t = *v * w;
+                *v = *u - t;
+                *u += t;
+                w *= w_len;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Gadgets for polynomials with assigned coefficients or evaluations
+#[derive(Clone, Debug)]
+pub struct PolyChip<'g, F: ScalarField, G: GateInstructions<F>> {
+    pub gate: &'g G,
+    _marker: PhantomData<F>,
+}
+
+impl<'g, F: ScalarField, G: GateInstructions<F>> PolyChip<'g, F, G> {
+    pub fn construct(gate: &'g G) -> Self {
+        Self { gate, _marker: PhantomData }
+    }
+
+    /// Returns `f(x)` for `f` with coefficients `coeffs`, using Horner's rule.
+    ///
+    /// Uses one `mul_add` per coefficient after the first.
+    pub fn evaluate<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        coeffs: &[AssignedValue<'v, F>],
+        x: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        let mut coeffs = coeffs.iter().rev();
+        let mut acc = match coeffs.next() {
+            Some(c) => c.clone(),
+            None => return self.gate.load_constant(ctx, F::zero()),
+        };
+        for c in coeffs {
+            acc = self.gate.mul_add(ctx, Existing(&acc), Existing(x), Existing(c));
+        }
+        acc
+    }
+
+    /// Returns `[1, x, ..., x^(len - 1)]`
+    pub fn powers<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        x: &AssignedValue<'v, F>,
+        len: usize,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let mut powers = Vec::with_capacity(len);
+        if len == 0 {
+            return powers;
+        }
+        powers.push(self.gate.load_constant(ctx, F::one()));
+        if len > 1 {
+            powers.push(x.clone());
+        }
+        for _ in 2..len {
+            let next = self.gate.mul(ctx, Existing(powers.last().unwrap()), Existing(x));
+            powers.push(next);
+        }
+        powers
+    }
+
+    /// Returns `sum_i coeffs[i] * powers[i]`, i.e., `f(x)` when `powers` are the [`PolyChip::powers`] of `x`.
+    ///
+    /// This is cheaper than [`PolyChip::evaluate`] when evaluating several polynomials at the same point.
+    /// Requires `coeffs.len() <= powers.len()`.
+    pub fn evaluate_at_powers<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        coeffs: &[AssignedValue<'v, F>],
+        powers: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        assert!(coeffs.len() <= powers.len(), "not enough powers to evaluate polynomial");
+        self.gate.inner_product(ctx, coeffs.iter().map(Existing), powers.iter().map(Existing))
+    }
+
+    /// Returns the coefficients of `a * b`, constrained by checking `a(r) * b(r) = (a * b)(r)` at the challenge `r`.
+    ///
+    /// The product coefficients are witnessed, so this is sound only if `r` is random and chosen after `a`, `b`, and the output are
+    /// committed to, e.g. `r` is a challenge of a later phase or derived from a hash of all coefficients. Then by the Schwartz-Zippel lemma
+    /// a wrong product passes with probability at most `(a.len() + b.len()) / |F|`.
+    pub fn mul_checked<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        a: &[AssignedValue<'v, F>],
+        b: &[AssignedValue<'v, F>],
+        r: &AssignedValue<'v, F>,
+    ) -> Vec<AssignedValue<'v, F>> {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+        let mut prod = vec![Value::known(F::zero()); a.len() + b.len() - 1];
+        for (i, a_i) in a.iter().enumerate() {
+            for (j, b_j) in b.iter().enumerate() {
+                prod[i + j] = prod[i + j] + a_i.value().zip(b_j.value()).map(|(a, b)| *a * b);
+            }
+        }
+        let prod = self.gate.assign_witnesses(ctx, prod);
+
+        let powers = self.powers(ctx, r, prod.len());
+        let a_r = self.evaluate_at_powers(ctx, a, &powers);
+        let b_r = self.evaluate_at_powers(ctx, b, &powers);
+        let prod_r = self.evaluate_at_powers(ctx, &prod, &powers);
+        // | 0 | a(r) | b(r) | prod(r) |
+        self.gate.assign_region_last(
+            ctx,
+            [Constant(F::zero()), Existing(&a_r), Existing(&b_r), Existing(&prod_r)],
+            [(0, None)],
+        );
+        prod
+    }
+
+    /// Returns `x^(2^k)`, using `k` squarings
+    pub fn pow_of_two_power<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        x: &AssignedValue<'v, F>,
+        k: u32,
+    ) -> AssignedValue<'v, F> {
+        let mut acc = x.clone();
+        for _ in 0..k {
+            acc = self.gate.mul(ctx, Existing(&acc), Existing(&acc));
+        }
+        acc
+    }
+
+    /// Returns `Z_H(x) = x^n - 1`, the vanishing polynomial of `domain` evaluated at `x`
+    pub fn vanishing_eval<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        domain: &EvaluationDomain<F>,
+        x: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        let x_pow_n = self.pow_of_two_power(ctx, x, domain.k());
+        self.gate.sub(ctx, Existing(&x_pow_n), Constant(F::one()))
+    }
+
+    /// Returns the evaluations at `x` of the Lagrange basis polynomials `L_i` of `domain` for each `i` in `indices`,
+    /// where `L_i(omega^j) = 1` if `i = j` and `0` otherwise.
+    ///
+    /// `z_h` must be [`PolyChip::vanishing_eval`] of `x`. Uses `L_i(x) = omega^i * Z_H(x) / (n * (x - omega^i))`.
+    ///
+    /// constrains x is not in the domain at the indices in `indices`
+    pub fn lagrange_basis_evals<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        domain: &EvaluationDomain<F>,
+        x: &AssignedValue<'v, F>,
+        z_h: &AssignedValue<'v, F>,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let omega_pows =
+            indices.into_iter().map(|i| domain.omega().pow_vartime([i as u64])).collect::<Vec<_>>();
+        let diffs = omega_pows
+            .iter()
+            .map(|omega_i| self.gate.sub(ctx, Existing(x), Constant(*omega_i)))
+            .collect::<Vec<_>>();
+        let inv_diffs = self.gate.batch_invert(ctx, &diffs);
+        omega_pows
+            .into_iter()
+            .zip(inv_diffs.iter())
+            .map(|(omega_i, inv_diff)| {
+                self.gate.sum_products_with_coeff_and_var(
+                    ctx,
+                    [(omega_i * domain.n_inv, Existing(z_h), Existing(inv_diff))],
+                    Constant(F::zero()),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns `f(x)` for the polynomial `f` of degree less than `n` with `f(omega^i) = evals[i]`, using the barycentric formula
+    /// `f(x) = Z_H(x) / n * sum_i evals[i] * omega^i / (x - omega^i)`.
+    ///
+    /// `z_h` must be [`PolyChip::vanishing_eval`] of `x`. Requires `evals.len() == n`.
+    ///
+    /// constrains x is not in the domain
+    pub fn evaluate_from_evals<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        domain: &EvaluationDomain<F>,
+        evals: &[AssignedValue<'v, F>],
+        x: &AssignedValue<'v, F>,
+        z_h: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        assert_eq!(evals.len(), domain.size());
+        let elements = domain.elements();
+        let diffs = elements
+            .iter()
+            .map(|omega_i| self.gate.sub(ctx, Existing(x), Constant(*omega_i)))
+            .collect::<Vec<_>>();
+        let inv_diffs = self.gate.batch_invert(ctx, &diffs);
+        let sum = self.gate.sum_products_with_coeff_and_var(
+            ctx,
+            elements
+                .into_iter()
+                .zip(evals.iter().zip(inv_diffs.iter()))
+                .map(|(omega_i, (eval, inv_diff))| (omega_i, Existing(eval), Existing(inv_diff))),
+            Constant(F::zero()),
+        );
+        self.gate.sum_products_with_coeff_and_var(
+            ctx,
+            [(domain.n_inv, Existing(&sum), Existing(z_h))],
+            Constant(F::zero()),
+        )
+    }
+}
+
+#[cfg(test)]
+pub mod tests;
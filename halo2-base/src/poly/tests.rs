@@ -0,0 +1,173 @@
+use super::{fft, EvaluationDomain, PolyChip};
+use crate::halo2_proofs::{
+    arithmetic::Field, circuit::*, dev::MockProver, halo2curves::bn256::Fr, plonk::*,
+};
+use crate::{
+    gates::{
+        flex_gate::{FlexGateConfig, GateStrategy},
+        GateInstructions,
+    },
+    Context, ContextParams,
+    QuantumCell::Existing,
+    SKIP_FIRST_PASS,
+};
+use rand::rngs::OsRng;
+
+fn eval_native(coeffs: &[Fr], x: Fr) -> Fr {
+    coeffs.iter().rev().fold(Fr::zero(), |acc, c| acc * x + c)
+}
+
+#[test]
+fn test_fft() {
+    let mut rng = OsRng;
+    for k in 0..5 {
+        let domain = EvaluationDomain::<Fr>::new(k);
+        let coeffs = (0..domain.size()).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let evals = domain.coeffs_to_evals(&coeffs);
+        for (omega_i, eval) in domain.elements().into_iter().zip(evals.iter()) {
+            assert_eq!(eval_native(&coeffs, omega_i), *eval);
+        }
+        assert_eq!(domain.evals_to_coeffs(&evals), coeffs);
+    }
+    // omega^n = 1 and omega^(n / 2) = -1
+    let domain = EvaluationDomain::<Fr>::new(4);
+    assert_eq!(domain.omega().pow_vartime([16]), Fr::one());
+    assert_eq!(domain.omega().pow_vartime([8]), -Fr::one());
+    let mut values = vec![Fr::one(); 4];
+    fft(&mut values, EvaluationDomain::<Fr>::new(2).omega());
+    assert_eq!(values, vec![Fr::from(4), Fr::zero(), Fr::zero(), Fr::zero()]);
+}
+
+#[derive(Default)]
+struct PolyCircuit {
+    a: Vec<Fr>,
+    b: Vec<Fr>,
+    x: Fr,
+    r: Fr,
+    // the `a` polynomial is evaluated from its evaluations on the domain of size `2^domain_k`
+    domain_k: u32,
+}
+
+impl Circuit<Fr> for PolyCircuit {
+    type Config = FlexGateConfig<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        FlexGateConfig::configure(meta, GateStrategy::PlonkPlus, &[2], 1, 0, 10)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let mut first_pass = SKIP_FIRST_PASS;
+
+        layouter.assign_region(
+            || "poly",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = Context::new(
+                    region,
+                    ContextParams::new(config.max_rows, 1, config.constants.clone()),
+                );
+                let ctx = &mut aux;
+                let chip = PolyChip::construct(&config);
+
+                let a = config.assign_witnesses(ctx, self.a.iter().map(|c| Value::known(*c)));
+                let b = config.assign_witnesses(ctx, self.b.iter().map(|c| Value::known(*c)));
+                let x = config.load_witness(ctx, Value::known(self.x));
+                let r = config.load_witness(ctx, Value::known(self.r));
+
+                // Horner and inner product evaluations agree
+                let a_x = chip.evaluate(ctx, &a, &x);
+                let powers = chip.powers(ctx, &x, a.len());
+                let a_x_powers = chip.evaluate_at_powers(ctx, &a, &powers);
+                ctx.constrain_equal(&a_x, &a_x_powers);
+                config.assert_is_const(ctx, &a_x, eval_native(&self.a, self.x));
+
+                let prod = chip.mul_checked(ctx, &a, &b, &r);
+                let prod_x = chip.evaluate(ctx, &prod, &x);
+                let b_x = chip.evaluate(ctx, &b, &x);
+                let a_x_b_x = config.mul(ctx, Existing(&a_x), Existing(&b_x));
+                ctx.constrain_equal(&prod_x, &a_x_b_x);
+
+                let domain = EvaluationDomain::new(self.domain_k);
+                let z_h = chip.vanishing_eval(ctx, &domain, &x);
+                config.assert_is_const(
+                    ctx,
+                    &z_h,
+                    self.x.pow_vartime([domain.size() as u64]) - Fr::one(),
+                );
+
+                let evals = config.assign_witnesses(
+                    ctx,
+                    domain.coeffs_to_evals(&self.a).into_iter().map(Value::known),
+                );
+                let a_x_evals = chip.evaluate_from_evals(ctx, &domain, &evals, &x, &z_h);
+                ctx.constrain_equal(&a_x, &a_x_evals);
+
+                // the Lagrange basis is a partition of unity
+                let lagrange_evals =
+                    chip.lagrange_basis_evals(ctx, &domain, &x, &z_h, 0..domain.size());
+                let sum = config.sum(ctx, lagrange_evals.iter().map(Existing));
+                config.assert_is_const(ctx, &sum, Fr::one());
+                // L_0(x) = (x^n - 1) / (n * (x - 1))
+                config.assert_is_const(
+                    ctx,
+                    &lagrange_evals[0],
+                    (self.x.pow_vartime([domain.size() as u64]) - Fr::one())
+                        * (Fr::from(domain.size() as u64) * (self.x - Fr::one())).invert().unwrap(),
+                );
+
+                #[cfg(feature = "display")]
+                {
+                    println!("total advice cells: {}", ctx.total_advice);
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn test_poly() {
+    let k = 10;
+    let mut rng = OsRng;
+    let circuit = PolyCircuit {
+        a: (0..8).map(|_| Fr::random(&mut rng)).collect(),
+        b: (0..5).map(|_| Fr::random(&mut rng)).collect(),
+        x: Fr::random(&mut rng),
+        r: Fr::random(&mut rng),
+        domain_k: 3,
+    };
+
+    let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+    prover.assert_satisfied();
+}
+
+#[test]
+fn test_poly_x_in_domain() {
+    let k = 10;
+    let mut rng = OsRng;
+    let domain = EvaluationDomain::<Fr>::new(3);
+    let circuit = PolyCircuit {
+        a: (0..8).map(|_| Fr::random(&mut rng)).collect(),
+        b: (0..5).map(|_| Fr::random(&mut rng)).collect(),
+        x: domain.omega(),
+        r: Fr::random(&mut rng),
+        domain_k: 3,
+    };
+
+    let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
@@ -103,7 +103,31 @@ pub fn log2_ceil(x: u64) -> usize {
     (u64::BITS - x.leading_zeros() - (x & (x - 1) == 0) as u32) as usize
 }
 
-pub fn modulus<F: PrimeField>() -> BigUint {
+/// Unsigned integer types of a fixed bit width, see [`AssignedPrimitive`](crate::AssignedPrimitive)
+pub trait Primitive: Into<u64> + Copy + std::fmt::Debug {
+    const BITS: usize;
+
+    /// Returns the lowest `BITS` bits of `x`
+    fn from_u64_truncate(x: u64) -> Self;
+}
+
+macro_rules! impl_primitive {
+    ($($t:ty),*) => {
+        $(
+            impl Primitive for $t {
+                const BITS: usize = <$t>::BITS as usize;
+
+                fn from_u64_truncate(x: u64) -> Self {
+                    x as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!(u8, u16, u32, u64);
+
+pub fn modulus<F: ff::PrimeField>() -> BigUint {
     fe_to_biguint(&-F::one()) + 1u64
 }
 
@@ -120,10 +144,7 @@ pub fn biguint_to_fe<F: PrimeField>(e: &BigUint) -> F {
 
     #[cfg(feature = "halo2-pse")]
     {
-        let mut repr = F::Repr::default();
-        let bytes = e.to_bytes_le();
-        repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
-        F::from_repr(repr).unwrap()
+        biguint_to_prime_fe(e)
     }
 }
 
@@ -140,15 +161,30 @@ pub fn bigint_to_fe<F: PrimeField>(e: &BigInt) -> F {
     }
     #[cfg(feature = "halo2-pse")]
     {
-        let (sign, bytes) = e.to_bytes_le();
-        let mut repr = F::Repr::default();
-        repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
-        let f_abs = F::from_repr(repr).unwrap();
-        if sign == Sign::Minus {
-            -f_abs
-        } else {
-            f_abs
-        }
+        bigint_to_prime_fe(e)
+    }
+}
+
+/// Same as [`biguint_to_fe`] for any prime field whose `Repr` is the little-endian bytes of the element, including fields
+/// with 48-byte representations such as the base fields of BLS12-381 and P-384.
+///
+/// assume `e` less than modulus of F
+pub fn biguint_to_prime_fe<F: ff::PrimeField>(e: &BigUint) -> F {
+    let mut repr = F::Repr::default();
+    let bytes = e.to_bytes_le();
+    repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+    F::from_repr(repr).unwrap()
+}
+
+/// Same as [`bigint_to_fe`] for any prime field whose `Repr` is the little-endian bytes of the element.
+///
+/// assume `|e|` less than modulus of F
+pub fn bigint_to_prime_fe<F: ff::PrimeField>(e: &BigInt) -> F {
+    let f_abs = biguint_to_prime_fe::<F>(e.magnitude());
+    if e.sign() == Sign::Minus {
+        -f_abs
+    } else {
+        f_abs
     }
 }
 
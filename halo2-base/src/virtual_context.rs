@@ -0,0 +1,350 @@
+//! Virtual contexts for parallel witness generation.
+//!
+//! A [`Context`] wraps a single `Region`, so witness generation with it is single-threaded.
+//! A virtual context, created with [`Context::new_virtual`], has no region: it records the regions, copy constraints, constants,
+//! and lookups of the chips run on it instead of assigning them. So independent sub-computations (e.g., each signature in a batch)
+//! can be witnessed in parallel with the usual chips, one virtual context per thread.
+//! Afterwards [`assign_virtual_contexts`] replays the recorded [`VirtualContext`]s into the real context, in order.
+use crate::{
+    gates::GateInstructions,
+    halo2_proofs::{circuit::Value, plonk::Assigned},
+    utils::ScalarField,
+    AssignedValue, Context,
+    QuantumCell::{self, Constant, Existing, ExistingOwned, Witness, WitnessFraction},
+};
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// every virtual context gets its own id, so that its cells cannot be mixed up with those of another virtual context
+static NEXT_VIRTUAL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The location of a cell in a virtual context
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VirtualCell {
+    /// The `i`-th cell of the real [`Context`] loaded with [`Context::load_external`]
+    External(usize),
+    /// The `i`-th cell assigned in the virtual context
+    Advice(usize),
+}
+
+/// Analogue of [`QuantumCell`] recorded by a virtual context
+#[derive(Clone, Debug)]
+enum VirtualInput<F: ScalarField> {
+    Existing(VirtualCell),
+    Witness(Value<F>),
+    WitnessFraction(Value<Assigned<F>>),
+    Constant(F),
+}
+
+/// A call to [`GateInstructions::assign_region`] recorded by a virtual context, together with the calls after it whose gates overlap with it
+#[derive(Clone, Debug)]
+struct VirtualRegion<F: ScalarField> {
+    context_id: usize,
+    // index of the first cell of the region in the virtual context
+    start: usize,
+    inputs: Vec<VirtualInput<F>>,
+    gate_offsets: Vec<(isize, Option<[F; 3]>)>,
+}
+
+/// Everything recorded by a virtual [`Context`], returned by [`Context::into_virtual`] and assigned with [`assign_virtual_contexts`].
+///
+/// `VirtualContext` is `Send` (unlike `Context`), so virtual contexts can be built in parallel and collected in the thread that owns the region.
+#[derive(Clone, Debug)]
+pub struct VirtualContext<'a, F: ScalarField> {
+    id: usize,
+    phase: usize,
+    externals: Vec<AssignedValue<'a, F>>,
+    regions: Vec<VirtualRegion<F>>,
+    // pairs of cells to constrain equal after all cells have been assigned
+    copy_constraints: Vec<(VirtualCell, VirtualCell)>,
+    // `(cell, c, cached)` constrains `cell` to equal the constant `c`, assigned with `Context::assign_fixed` if `cached`
+    constants: Vec<(VirtualCell, F, bool)>,
+    cells_to_lookup: Vec<VirtualCell>,
+    cells_to_byte_lookup: Vec<(u64, Vec<VirtualCell>)>,
+    cells_to_table_lookup: FxHashMap<usize, Vec<(u64, Vec<VirtualCell>)>>,
+    num_cells: usize,
+}
+
+impl<'a, F: ScalarField> VirtualContext<'a, F> {
+    pub(crate) fn new(phase: usize) -> Self {
+        Self {
+            id: NEXT_VIRTUAL_ID.fetch_add(1, Ordering::Relaxed),
+            phase,
+            externals: vec![],
+            regions: vec![],
+            copy_constraints: vec![],
+            constants: vec![],
+            cells_to_lookup: vec![],
+            cells_to_byte_lookup: vec![],
+            cells_to_table_lookup: FxHashMap::default(),
+            num_cells: 0,
+        }
+    }
+
+    /// Number of advice cells assigned in this virtual context, not counting lookups
+    pub fn num_cells(&self) -> usize {
+        self.num_cells
+    }
+
+    /// Returns the location of `a` in this virtual context
+    fn cell(&self, a: &AssignedValue<F>) -> VirtualCell {
+        match a.virtual_cell() {
+            Some((id, cell)) if id == self.id => cell,
+            Some(_) => panic!("a cell of another virtual context cannot be used in this virtual context"),
+            None => panic!(
+                "a cell of the real context can only be used in a virtual context after `Context::load_external`"
+            ),
+        }
+    }
+
+    pub(crate) fn load_external(&mut self, a: &AssignedValue<'a, F>) -> AssignedValue<'a, F> {
+        assert!(!a.is_virtual(), "only cells of the real context can be loaded as external cells");
+        let cell = VirtualCell::External(self.externals.len());
+        self.externals.push(a.clone());
+        AssignedValue::new_virtual(
+            self.id,
+            cell,
+            a.value().copied(),
+            #[cfg(feature = "display")]
+            a.context_id,
+        )
+    }
+
+    /// Records the assignment of `inputs` in consecutive cells by the gate with context id `context_id`, enabling the gate at
+    /// each offset in `gate_offsets`, see [`GateInstructions::assign_region`].
+    ///
+    /// A region with a negative gate offset overlaps with the previous region, so it is merged into the previous region to be
+    /// assigned in the same column.
+    pub(crate) fn assign_region<'b>(
+        &mut self,
+        context_id: usize,
+        inputs: impl IntoIterator<Item = QuantumCell<'_, 'b, F>>,
+        gate_offsets: impl IntoIterator<Item = (isize, Option<[F; 3]>)>,
+    ) -> Vec<AssignedValue<'b, F>> {
+        let gate_offsets: Vec<_> = gate_offsets.into_iter().collect();
+        let start = self.num_cells;
+        let overlap = gate_offsets.iter().any(|(i, _)| *i < 0);
+        let region_start = if overlap {
+            let prev = self.regions.last().expect(
+                "the first region of a virtual context cannot overlap with a previous region",
+            );
+            assert_eq!(prev.context_id, context_id, "overlapping regions must use the same gate");
+            prev.start
+        } else {
+            start
+        };
+
+        let mut inputs_to_record = vec![];
+        let assignments = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let cell = VirtualCell::Advice(start + i);
+                let (input, value) = match input {
+                    Existing(a) => (self.existing_input(cell, a, region_start), a.value().copied()),
+                    ExistingOwned(a) => {
+                        (self.existing_input(cell, &a, region_start), a.value().copied())
+                    }
+                    Witness(value) => (VirtualInput::Witness(value), value),
+                    WitnessFraction(value) => {
+                        (VirtualInput::WitnessFraction(value), value.map(|v| v.evaluate()))
+                    }
+                    Constant(c) => (VirtualInput::Constant(c), Value::known(c)),
+                };
+                inputs_to_record.push(input);
+                AssignedValue::new_virtual(
+                    self.id,
+                    cell,
+                    value,
+                    #[cfg(feature = "display")]
+                    context_id,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.num_cells += assignments.len();
+
+        if overlap {
+            let prev = self.regions.last_mut().unwrap();
+            let shift = (start - prev.start) as isize;
+            assert!(
+                gate_offsets.iter().all(|(i, _)| shift + i >= 0),
+                "a gate cannot overlap with more than the previous region"
+            );
+            prev.inputs.extend(inputs_to_record);
+            prev.gate_offsets.extend(gate_offsets.into_iter().map(|(i, q)| (shift + i, q)));
+        } else {
+            self.regions.push(VirtualRegion {
+                context_id,
+                start,
+                inputs: inputs_to_record,
+                gate_offsets,
+            });
+        }
+        assignments
+    }
+
+    // An existing cell of the region starting at `region_start` is not assigned yet when the region is replayed, so instead
+    // `cell` is witnessed and constrained to equal it afterwards
+    fn existing_input(
+        &mut self,
+        cell: VirtualCell,
+        a: &AssignedValue<F>,
+        region_start: usize,
+    ) -> VirtualInput<F> {
+        match self.cell(a) {
+            VirtualCell::Advice(i) if i >= region_start => {
+                self.copy_constraints.push((cell, VirtualCell::Advice(i)));
+                VirtualInput::Witness(a.value().copied())
+            }
+            existing => VirtualInput::Existing(existing),
+        }
+    }
+
+    pub(crate) fn constrain_equal(&mut self, a: &AssignedValue<F>, b: &AssignedValue<F>) {
+        let (a, b) = (self.cell(a), self.cell(b));
+        self.copy_constraints.push((a, b));
+    }
+
+    pub(crate) fn constrain_constant(&mut self, a: &AssignedValue<F>, c: F, cached: bool) {
+        let a = self.cell(a);
+        self.constants.push((a, c, cached));
+    }
+
+    pub(crate) fn record_lookups(
+        &mut self,
+        cells_to_lookup: Vec<AssignedValue<F>>,
+        cells_to_byte_lookup: Vec<(u64, Vec<AssignedValue<F>>)>,
+        cells_to_table_lookup: FxHashMap<usize, Vec<(u64, Vec<AssignedValue<F>>)>>,
+    ) {
+        let rows = |rows: Vec<(u64, Vec<AssignedValue<F>>)>| {
+            rows.into_iter()
+                .map(|(tag, cells)| (tag, cells.iter().map(|a| self.cell(a)).collect()))
+                .collect::<Vec<_>>()
+        };
+        let cells_to_byte_lookup = rows(cells_to_byte_lookup);
+        let cells_to_table_lookup: FxHashMap<_, _> = cells_to_table_lookup
+            .into_iter()
+            .map(|(table_id, table_rows)| (table_id, rows(table_rows)))
+            .collect();
+        let cells_to_lookup = cells_to_lookup.iter().map(|a| self.cell(a)).collect::<Vec<_>>();
+
+        self.cells_to_lookup.extend(cells_to_lookup);
+        self.cells_to_byte_lookup.extend(cells_to_byte_lookup);
+        for (table_id, table_rows) in cells_to_table_lookup {
+            self.cells_to_table_lookup.entry(table_id).or_default().extend(table_rows);
+        }
+    }
+}
+
+/// The cells assigned for a [`VirtualContext`] by [`assign_virtual_contexts`]
+#[derive(Clone, Debug)]
+pub struct AssignedVirtualContext<'v, F: ScalarField> {
+    id: usize,
+    externals: Vec<AssignedValue<'v, F>>,
+    assigned: Vec<AssignedValue<'v, F>>,
+}
+
+impl<'v, F: ScalarField> AssignedVirtualContext<'v, F> {
+    /// Returns the cell of the real context that `a`, a cell of the virtual context, was assigned to
+    pub fn get(&self, a: &AssignedValue<F>) -> &AssignedValue<'v, F> {
+        match a.virtual_cell() {
+            Some((id, cell)) if id == self.id => {
+                resolve_cell(&self.externals, &self.assigned, cell)
+            }
+            _ => panic!("not a cell of this virtual context"),
+        }
+    }
+
+    /// The cells assigned in the virtual context, in order
+    pub fn cells(&self) -> &[AssignedValue<'v, F>] {
+        &self.assigned
+    }
+}
+
+/// Assigns the virtual contexts `virtual_ctxs` into `ctx` using `gate`, in order: their regions are assigned with
+/// [`GateInstructions::assign_region`], then their copy constraints and constants are constrained, and their lookups are added
+/// to those of `ctx`, to be finalized with the rest of `ctx`.
+///
+/// All regions of the virtual contexts must have been assigned by `gate`, in the current phase of `ctx`.
+pub fn assign_virtual_contexts<'v, F: ScalarField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    virtual_ctxs: impl IntoIterator<Item = VirtualContext<'v, F>>,
+) -> Vec<AssignedVirtualContext<'v, F>> {
+    virtual_ctxs
+        .into_iter()
+        .map(|virtual_ctx| {
+            assert_eq!(
+                virtual_ctx.phase,
+                ctx.current_phase(),
+                "a virtual context must be assigned in the phase it was created in"
+            );
+            let externals = virtual_ctx.externals;
+            let mut assigned: Vec<AssignedValue<'v, F>> = Vec::with_capacity(virtual_ctx.num_cells);
+            for region in virtual_ctx.regions {
+                assert_eq!(
+                    region.context_id,
+                    gate.context_id(),
+                    "the virtual context was recorded with a different gate"
+                );
+                let inputs = region.inputs.into_iter().map(|input| match input {
+                    VirtualInput::Existing(cell) => {
+                        Existing(resolve_cell(&externals, &assigned, cell))
+                    }
+                    VirtualInput::Witness(a) => Witness(a),
+                    VirtualInput::WitnessFraction(a) => WitnessFraction(a),
+                    VirtualInput::Constant(c) => Constant(c),
+                });
+                let new_cells = gate.assign_region(
+                    ctx,
+                    inputs.collect::<Vec<QuantumCell<F>>>(),
+                    region.gate_offsets,
+                );
+                assigned.extend(new_cells);
+            }
+            for (a, b) in virtual_ctx.copy_constraints {
+                let a = resolve_cell(&externals, &assigned, a);
+                let b = resolve_cell(&externals, &assigned, b);
+                ctx.constrain_equal(a, b);
+            }
+            for (a, c, cached) in virtual_ctx.constants {
+                let a = resolve_cell(&externals, &assigned, a);
+                if cached {
+                    ctx.constrain_constant(a, c);
+                } else {
+                    ctx.constrain_constant_without_caching(a, c);
+                }
+            }
+
+            let resolve_row = |(tag, cells): (u64, Vec<VirtualCell>)| {
+                let cells =
+                    cells.into_iter().map(|a| resolve_cell(&externals, &assigned, a).clone());
+                (tag, cells.collect())
+            };
+            for a in virtual_ctx.cells_to_lookup {
+                ctx.push_cell_to_lookup(resolve_cell(&externals, &assigned, a).clone());
+            }
+            ctx.cells_to_byte_lookup
+                .extend(virtual_ctx.cells_to_byte_lookup.into_iter().map(resolve_row));
+            for (table_id, rows) in virtual_ctx.cells_to_table_lookup {
+                ctx.cells_to_table_lookup
+                    .entry(table_id)
+                    .or_default()
+                    .extend(rows.into_iter().map(resolve_row));
+            }
+
+            AssignedVirtualContext { id: virtual_ctx.id, externals, assigned }
+        })
+        .collect()
+}
+
+fn resolve_cell<'a, 'v, F: ScalarField>(
+    external: &'a [AssignedValue<'v, F>],
+    assigned: &'a [AssignedValue<'v, F>],
+    cell: VirtualCell,
+) -> &'a AssignedValue<'v, F> {
+    match cell {
+        VirtualCell::External(i) => &external[i],
+        VirtualCell::Advice(i) => &assigned[i],
+    }
+}
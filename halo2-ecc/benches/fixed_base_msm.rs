@@ -24,7 +24,7 @@ use halo2_base::halo2_proofs::{
     transcript::TranscriptWriterBuffer,
     transcript::{Blake2bWrite, Challenge255},
 };
-use halo2_base::{gates::GateInstructions, utils::PrimeField};
+use halo2_base::utils::PrimeField;
 use halo2_ecc::{
     ecc::EccChip,
     fields::fp::{FpConfig, FpStrategy},
@@ -62,6 +62,8 @@ const BEST_100_CONFIG: MSMCircuitParams = MSMCircuitParams {
 };
 
 const TEST_CONFIG: MSMCircuitParams = BEST_100_CONFIG;
+// scalars are loaded as two limbs, each of which is compared to the curve order with `is_less_than` on `SCALAR_LIMB_BITS + 1` bits
+const SCALAR_LIMB_BITS: usize = 128;
 
 #[derive(Clone, Debug)]
 struct MSMConfig<F: PrimeField> {
@@ -133,23 +135,21 @@ impl Circuit<Fr> for MSMCircuit<Fr> {
                 let ctx = &mut aux;
 
                 let witness_time = start_timer!(|| "Witness generation");
+                let ecc_chip = EccChip::construct(config.fp_chip.clone());
                 let mut scalars_assigned = Vec::new();
                 for scalar in &self.scalars {
-                    let assignment = config
-                        .fp_chip
-                        .range
-                        .gate
-                        .assign_witnesses(ctx, vec![scalar.map_or(Value::unknown(), Value::known)]);
+                    let assignment = ecc_chip.load_scalar(
+                        ctx,
+                        scalar.map_or(Value::unknown(), Value::known),
+                        SCALAR_LIMB_BITS,
+                    );
                     scalars_assigned.push(assignment);
                 }
 
-                let ecc_chip = EccChip::construct(config.fp_chip.clone());
-
                 let _msm = ecc_chip.fixed_base_msm::<G1Affine>(
                     ctx,
                     &self.bases,
                     &scalars_assigned,
-                    Fr::NUM_BITS as usize,
                     0,
                     config.clump_factor,
                 );
@@ -22,11 +22,7 @@ use halo2_base::halo2_proofs::{
     transcript::TranscriptWriterBuffer,
     transcript::{Blake2bWrite, Challenge255},
 };
-use halo2_base::{
-    gates::GateInstructions,
-    utils::{biguint_to_fe, fe_to_biguint, PrimeField},
-    QuantumCell::Witness,
-};
+use halo2_base::utils::{biguint_to_fe, fe_to_biguint, PrimeField};
 use halo2_ecc::{
     ecc::EccChip,
     fields::fp::{FpConfig, FpStrategy},
@@ -63,6 +59,8 @@ const BEST_100_CONFIG: MSMCircuitParams = MSMCircuitParams {
 };
 
 const TEST_CONFIG: MSMCircuitParams = BEST_100_CONFIG;
+// scalars are loaded as two limbs, each of which is compared to the curve order with `is_less_than` on `SCALAR_LIMB_BITS + 1` bits
+const SCALAR_LIMB_BITS: usize = 128;
 
 #[derive(Clone, Debug)]
 struct MSMConfig<F: PrimeField> {
@@ -179,17 +177,17 @@ impl Circuit<Fr> for MSMCircuit<Fr> {
                 let mut aux = config.fp_chip.new_context(region);
                 let ctx = &mut aux;
 
+                let ecc_chip = EccChip::construct(config.fp_chip.clone());
                 let mut scalars_assigned = Vec::new();
                 for scalar in &self.scalars {
-                    let assignment = config.fp_chip.range.gate.assign_region_last(
+                    let assignment = ecc_chip.load_scalar(
                         ctx,
-                        vec![Witness(scalar.map_or(Value::unknown(), Value::known))],
-                        vec![],
+                        scalar.map_or(Value::unknown(), Value::known),
+                        SCALAR_LIMB_BITS,
                     );
-                    scalars_assigned.push(vec![assignment]);
+                    scalars_assigned.push(assignment);
                 }
 
-                let ecc_chip = EccChip::construct(config.fp_chip.clone());
                 let mut bases_assigned = Vec::new();
                 for base in &self.bases {
                     let base_assigned = ecc_chip.load_private(
@@ -204,13 +202,17 @@ impl Circuit<Fr> for MSMCircuit<Fr> {
                     bases_assigned.push(base_assigned);
                 }
 
+                // compare with the estimate of the cost model printed by `variable_base_msm`
+                #[cfg(feature = "display")]
+                let cells_before = ctx.stats().advice_cells[0];
                 let _msm = ecc_chip.variable_base_msm::<G1Affine>(
                     ctx,
                     &bases_assigned,
                     &scalars_assigned,
-                    254,
                     config.window_bits,
                 );
+                #[cfg(feature = "display")]
+                println!("MSM used {} advice cells", ctx.stats().advice_cells[0] - cells_before);
 
                 config.fp_chip.finalize(ctx);
                 end_timer!(witness_time);
@@ -1,4 +1,4 @@
-use super::{check_carry_to_zero, CRTInteger, OverflowInteger};
+use super::{check_carry_to_zero, limb_bits_at, range_check_limbs, CRTInteger, OverflowInteger};
 use crate::halo2_proofs::circuit::Value;
 use halo2_base::{
     gates::{range::RangeStrategy, GateInstructions, RangeInstructions},
@@ -59,11 +59,12 @@ pub fn crt<'a, F: PrimeField>(
     // Let n' <= quot_max_bits - n(k-1) - 1
     // If quot[i] <= 2^n for i < k - 1 and quot[k-1] <= 2^{n'} then
     // quot < 2^{n(k-1)+1} + 2^{n' + n(k-1)} = (2+2^{n'}) 2^{n(k-1)} < 2^{n'+1} * 2^{n(k-1)} <= 2^{quot_max_bits - n(k-1)} * 2^{n(k-1)}
-    let quot_last_limb_bits = quot_max_bits - n * (k - 1);
+    // When `k` limbs hold more than `quot_max_bits` bits, which happens for large moduli that need extra limbs so that the
+    // product of two reduced elements satisfies the assumption on `a`, the same holds with the last nonzero limb in place of
+    // `quot[k-1]` and the limbs past it constrained to be zero.
 
+    // `modulus` may need fewer than `k` limbs to represent, in which case the limbs of `out` past it are zero
     let out_max_bits = modulus.bits() as usize;
-    // we assume `modulus` requires *exactly* `k` limbs to represent (if `< k` limbs ok, you should just be using that)
-    let out_last_limb_bits = out_max_bits - n * (k - 1);
 
     // these are witness vectors:
     // we need to find `out_vec` as a proper BigInt with k limbs
@@ -183,19 +184,21 @@ pub fn crt<'a, F: PrimeField>(
     //    }
     //}
 
-    // range check limbs of `out` are in [0, 2^n) except last limb should be in [0, 2^out_last_limb_bits)
-    for (out_index, out_cell) in out_assigned.iter().enumerate() {
-        let limb_bits = if out_index == k - 1 { out_last_limb_bits } else { n };
-        range.range_check(ctx, out_cell, limb_bits);
-    }
+    // range check limbs of `out` are in [0, 2^n) except the last nonzero limb should be in [0, 2^{out_max_bits - n * i})
+    range_check_limbs(range, ctx, &out_assigned, out_max_bits, n);
 
-    // range check that quot_cell in quot_assigned is in [-2^n, 2^n) except for last cell check it's in [-2^quot_last_limb_bits, 2^quot_last_limb_bits)
+    // range check that quot_cell in quot_assigned is in [-2^n, 2^n) except for the last nonzero cell check it's in
+    // [-2^{quot_max_bits - n * i}, 2^{quot_max_bits - n * i}) and any cells past it are zero
     for (q_index, quot_cell) in quot_assigned.iter().enumerate() {
-        let limb_bits = if q_index == k - 1 { quot_last_limb_bits } else { n };
-        let limb_base = if q_index == k - 1 {
-            biguint_to_fe(&(BigUint::one() << limb_bits))
-        } else {
+        let limb_bits = limb_bits_at(quot_max_bits, n, q_index);
+        if limb_bits == 0 {
+            range.gate().assert_is_const(ctx, quot_cell, F::zero());
+            continue;
+        }
+        let limb_base = if limb_bits == n {
             limb_bases[1]
+        } else {
+            biguint_to_fe(&(BigUint::one() << limb_bits))
         };
 
         // compute quot_cell + 2^n and range check with n + 1 bits
@@ -1,4 +1,4 @@
-use super::{check_carry_to_zero, CRTInteger, OverflowInteger};
+use super::{check_carry_to_zero, limb_bits_at, CRTInteger, OverflowInteger};
 use crate::halo2_proofs::circuit::Value;
 use halo2_base::{
     gates::{GateInstructions, RangeInstructions},
@@ -46,7 +46,6 @@ pub fn crt<'a, F: PrimeField>(
     // see carry_mod.rs for explanation
     let quot_max_bits = trunc_len - 1 + (F::NUM_BITS as usize) - 1 - (modulus.bits() as usize);
     assert!(quot_max_bits < trunc_len);
-    let quot_last_limb_bits = quot_max_bits - n * (k - 1);
 
     // these are witness vectors:
     // we need to find `quot_vec` as a proper BigInt with k limbs
@@ -123,13 +122,18 @@ pub fn crt<'a, F: PrimeField>(
     //    }
     // }
 
-    // range check that quot_cell in quot_assigned is in [-2^n, 2^n) except for last cell check it's in [-2^quot_last_limb_bits, 2^quot_last_limb_bits)
+    // range check that quot_cell in quot_assigned is in [-2^n, 2^n) except for the last nonzero cell check it's in
+    // [-2^{quot_max_bits - n * i}, 2^{quot_max_bits - n * i}) and any cells past it are zero
     for (q_index, quot_cell) in quot_assigned.iter().enumerate() {
-        let limb_bits = if q_index == k - 1 { quot_last_limb_bits } else { n };
-        let limb_base = if q_index == k - 1 {
-            biguint_to_fe(&(BigUint::one() << limb_bits))
-        } else {
+        let limb_bits = limb_bits_at(quot_max_bits, n, q_index);
+        if limb_bits == 0 {
+            range.gate().assert_is_const(ctx, quot_cell, F::zero());
+            continue;
+        }
+        let limb_base = if limb_bits == n {
             limb_bases[1]
+        } else {
+            biguint_to_fe(&(BigUint::one() << limb_bits))
         };
 
         // compute quot_cell + 2^n and range check with n + 1 bits
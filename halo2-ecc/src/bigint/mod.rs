@@ -3,12 +3,11 @@ use crate::halo2_proofs::{
     plonk::ConstraintSystem,
 };
 use halo2_base::{
-    gates::{flex_gate::FlexGateConfig, GateInstructions},
+    gates::{flex_gate::FlexGateConfig, GateInstructions, RangeInstructions},
     utils::{biguint_to_fe, decompose_biguint, fe_to_biguint, PrimeField},
     AssignedValue, Context,
     QuantumCell::{Constant, Existing, Witness},
 };
-use itertools::Itertools;
 use num_bigint::{BigInt, BigUint};
 use num_traits::Zero;
 use std::{marker::PhantomData, rc::Rc};
@@ -232,34 +231,19 @@ impl<F: PrimeField> FixedCRTInteger<F> {
         limb_bits: usize,
         native_modulus: &BigUint,
     ) -> CRTInteger<'a, F> {
-        let fixed_cells = self
-            .truncation
-            .limbs
-            .iter()
-            .map(|limb| ctx.assign_fixed_without_caching(*limb))
-            .collect_vec();
         let assigned_limbs = gate.assign_region(
             ctx,
-            self.truncation.limbs.into_iter().map(|v| Witness(Value::known(v))),
+            self.truncation.limbs.iter().map(|v| Witness(Value::known(*v))),
             vec![],
         );
-        for (cell, acell) in fixed_cells.iter().zip(assigned_limbs.iter()) {
-            #[cfg(feature = "halo2-axiom")]
-            ctx.region.constrain_equal(cell, acell.cell());
-            #[cfg(feature = "halo2-pse")]
-            ctx.region.constrain_equal(*cell, acell.cell()).unwrap();
+        for (limb, acell) in self.truncation.limbs.iter().zip(assigned_limbs.iter()) {
+            ctx.constrain_constant_without_caching(acell, *limb);
         }
         let assigned_native = {
             let native_val = biguint_to_fe(&(&self.value % native_modulus));
-            let cell = ctx.assign_fixed_without_caching(native_val);
             let acell =
                 gate.assign_region_last(ctx, vec![Witness(Value::known(native_val))], vec![]);
-
-            #[cfg(feature = "halo2-axiom")]
-            ctx.region.constrain_equal(&cell, acell.cell());
-            #[cfg(feature = "halo2-pse")]
-            ctx.region.constrain_equal(cell, acell.cell()).unwrap();
-
+            ctx.constrain_constant_without_caching(&acell, native_val);
             acell
         };
         CRTInteger::construct(
@@ -270,6 +254,33 @@ impl<F: PrimeField> FixedCRTInteger<F> {
     }
 }
 
+/// Returns the number of bits of limb `i`, with limbs of `limb_bits` bits, of a nonnegative integer with at most `max_bits` bits.
+///
+/// This is `limb_bits` for the low limbs and `0` for the limbs past `max_bits`, which occur when an integer uses more limbs
+/// than its size needs, e.g., for moduli of 381 bits with 5 limbs of 104 bits.
+pub fn limb_bits_at(max_bits: usize, limb_bits: usize, i: usize) -> usize {
+    max_bits.saturating_sub(limb_bits * i).min(limb_bits)
+}
+
+/// Range checks `limbs` to be the limbs of `limb_bits` bits of a nonnegative integer with at most `max_bits` bits.
+///
+/// The limbs past `max_bits` are constrained to be zero.
+pub fn range_check_limbs<'a, F: PrimeField>(
+    range: &impl RangeInstructions<F>,
+    ctx: &mut Context<'a, F>,
+    limbs: &[AssignedValue<'a, F>],
+    max_bits: usize,
+    limb_bits: usize,
+) {
+    debug_assert!(max_bits <= limb_bits * limbs.len());
+    for (i, limb) in limbs.iter().enumerate() {
+        match limb_bits_at(max_bits, limb_bits, i) {
+            0 => range.gate().assert_is_const(ctx, limb, F::zero()),
+            bits => range.range_check(ctx, limb, bits),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 #[allow(dead_code)]
 pub struct BigIntConfig<F: PrimeField> {
@@ -130,23 +130,22 @@ impl Circuit<Fr> for MSMCircuit<Fr> {
                 let mut aux = config.fp_chip.new_context(region);
                 let ctx = &mut aux;
 
+                let ecc_chip = EccChip::construct(config.fp_chip.clone());
                 let mut scalars_assigned = Vec::new();
                 for scalar in &self.scalars {
-                    let assignment = config
-                        .fp_chip
-                        .range
-                        .gate
-                        .assign_witnesses(ctx, vec![scalar.map_or(Value::unknown(), Value::known)]);
+                    let assignment = ecc_chip.load_scalar(
+                        ctx,
+                        scalar.map_or(Value::unknown(), Value::known),
+                        SCALAR_LIMB_BITS,
+                    );
                     scalars_assigned.push(assignment);
                 }
 
-                let ecc_chip = EccChip::construct(config.fp_chip.clone());
-
                 // baseline
                 /*
                 let msm = {
                     let sm = self.bases.iter().zip(scalars_assigned.iter()).map(|(base, scalar)|
-                        ecc_chip.fixed_base_scalar_mult(ctx, &FixedEcPoint::<Fr, G1Affine>::from_g1(base, config.fp_chip.num_limbs, config.fp_chip.limb_bits), scalar, Fr::NUM_BITS as usize, 4)).collect::<Vec<_>>();
+                        ecc_chip.fixed_base_scalar_mult(ctx, &FixedEcPoint::<Fr, G1Affine>::from_g1(base, config.fp_chip.num_limbs, config.fp_chip.limb_bits), scalar, 4)).collect::<Vec<_>>();
                     ecc_chip.sum::<G1Affine>(ctx, sm.iter())
                 };
                 */
@@ -155,7 +154,6 @@ impl Circuit<Fr> for MSMCircuit<Fr> {
                     ctx,
                     &self.bases,
                     &scalars_assigned,
-                    Fr::NUM_BITS as usize,
                     config._radix,
                     config._clump_factor,
                 );
@@ -22,11 +22,7 @@ use crate::halo2_proofs::{
     transcript::{TranscriptReadBuffer, TranscriptWriterBuffer},
 };
 use crate::{ecc::EccChip, fields::fp::FpStrategy};
-use halo2_base::{
-    gates::GateInstructions,
-    utils::{biguint_to_fe, fe_to_biguint, value_to_option, PrimeField},
-    QuantumCell::Witness,
-};
+use halo2_base::utils::{biguint_to_fe, fe_to_biguint, value_to_option, PrimeField};
 use num_bigint::BigUint;
 use num_traits::Num;
 
@@ -34,3 +30,6 @@ pub mod ec_add;
 pub mod fixed_base_msm;
 pub mod msm;
 pub mod pairing;
+
+// scalars are loaded as two limbs, each of which is compared to the curve order with `is_less_than` on `SCALAR_LIMB_BITS + 1` bits
+const SCALAR_LIMB_BITS: usize = 128;
@@ -138,19 +138,17 @@ impl Circuit<Fr> for MSMCircuit<Fr> {
                 let ctx = &mut aux;
 
                 let witness_time = start_timer!(|| "Witness generation");
+                let ecc_chip = EccChip::construct(config.fp_chip.clone());
                 let mut scalars_assigned = Vec::new();
                 for scalar in &self.scalars {
-                    let assignment = config.fp_chip.range.gate.assign_region_smart(
+                    let assignment = ecc_chip.load_scalar(
                         ctx,
-                        vec![Witness(scalar.map_or(Value::unknown(), Value::known))],
-                        vec![],
-                        vec![],
-                        vec![],
+                        scalar.map_or(Value::unknown(), Value::known),
+                        SCALAR_LIMB_BITS,
                     );
-                    scalars_assigned.push(vec![assignment.last().unwrap().clone()]);
+                    scalars_assigned.push(assignment);
                 }
 
-                let ecc_chip = EccChip::construct(config.fp_chip.clone());
                 let mut bases_assigned = Vec::new();
                 for base in &self.bases {
                     let base_assigned = ecc_chip.load_private(
@@ -169,7 +167,6 @@ impl Circuit<Fr> for MSMCircuit<Fr> {
                     ctx,
                     &bases_assigned,
                     &scalars_assigned,
-                    254,
                     config.window_bits,
                 );
 
@@ -8,6 +8,8 @@ use halo2_base::{
 };
 
 use super::fixed_base;
+use super::fixed_base_lookup::FixedBaseLookupConfig;
+use super::glv::{scalar_multiply_glv, GlvCurve};
 use super::{ec_add_unequal, scalar_multiply, EcPoint};
 // CF is the coordinate field of GA
 // SF is the scalar field of GA
@@ -27,6 +29,152 @@ pub fn ecdsa_verify_no_pubkey_check<'v, F: PrimeField, CF: PrimeField, SF: Prime
 where
     GA: CurveAffineExt<Base = CF, ScalarExt = SF>,
 {
+    ecdsa_verify_with::<F, CF, SF, GA>(
+        base_chip,
+        ctx,
+        r,
+        s,
+        msghash,
+        |ctx, u1| {
+            fixed_base::scalar_multiply::<F, _, _>(
+                base_chip,
+                ctx,
+                &GA::generator(),
+                &u1.truncation.limbs,
+                base_chip.limb_bits,
+                fixed_window_bits,
+            )
+        },
+        |ctx, _, u2| {
+            scalar_multiply::<F, _, GA>(
+                base_chip,
+                ctx,
+                pubkey,
+                &u2.truncation.limbs,
+                base_chip.limb_bits,
+                var_window_bits,
+            )
+        },
+    )
+}
+
+/// Same as [`ecdsa_verify_no_pubkey_check`], but computes `u2 * pubkey` using the GLV endomorphism of `GA`, which takes
+/// fewer rows.
+pub fn ecdsa_verify_no_pubkey_check_glv<'v, F: PrimeField, CF: PrimeField, SF: PrimeField, GA>(
+    base_chip: &FpConfig<F, CF>,
+    ctx: &mut Context<'v, F>,
+    pubkey: &EcPoint<F, <FpConfig<F, CF> as FieldChip<F>>::FieldPoint<'v>>,
+    r: &CRTInteger<'v, F>,
+    s: &CRTInteger<'v, F>,
+    msghash: &CRTInteger<'v, F>,
+    var_window_bits: usize,
+    fixed_window_bits: usize,
+) -> AssignedValue<'v, F>
+where
+    GA: GlvCurve<Base = CF, ScalarExt = SF>,
+{
+    ecdsa_verify_with::<F, CF, SF, GA>(
+        base_chip,
+        ctx,
+        r,
+        s,
+        msghash,
+        |ctx, u1| {
+            fixed_base::scalar_multiply::<F, _, _>(
+                base_chip,
+                ctx,
+                &GA::generator(),
+                &u1.truncation.limbs,
+                base_chip.limb_bits,
+                fixed_window_bits,
+            )
+        },
+        |ctx, scalar_chip, u2| {
+            scalar_multiply_glv::<F, _, GA>(
+                base_chip,
+                scalar_chip,
+                ctx,
+                pubkey,
+                u2,
+                var_window_bits,
+            )
+        },
+    )
+}
+
+/// Same as [`ecdsa_verify_no_pubkey_check_glv`], but computes `u1 * G` by looking up the window multiples of the generator
+/// `G` in `fixed_base_table`, which takes far fewer rows than selecting among them.
+///
+/// `fixed_base_table` must have the generator of `GA` as one of its bases, and at least `base_chip.num_limbs * base_chip.limb_bits`
+/// bits of windows.
+pub fn ecdsa_verify_no_pubkey_check_glv_lookup<
+    'v,
+    F: PrimeField,
+    CF: PrimeField,
+    SF: PrimeField,
+    GA,
+>(
+    base_chip: &FpConfig<F, CF>,
+    fixed_base_table: &FixedBaseLookupConfig<GA>,
+    ctx: &mut Context<'v, F>,
+    pubkey: &EcPoint<F, <FpConfig<F, CF> as FieldChip<F>>::FieldPoint<'v>>,
+    r: &CRTInteger<'v, F>,
+    s: &CRTInteger<'v, F>,
+    msghash: &CRTInteger<'v, F>,
+    var_window_bits: usize,
+) -> AssignedValue<'v, F>
+where
+    GA: GlvCurve<Base = CF, ScalarExt = SF>,
+{
+    let generator_idx = fixed_base_table
+        .base_index(&GA::generator())
+        .expect("generator is not a base of the fixed base lookup table");
+    ecdsa_verify_with::<F, CF, SF, GA>(
+        base_chip,
+        ctx,
+        r,
+        s,
+        msghash,
+        |ctx, u1| {
+            fixed_base_table.scalar_multiply(
+                base_chip,
+                ctx,
+                generator_idx,
+                &u1.truncation.limbs,
+                base_chip.limb_bits,
+            )
+        },
+        |ctx, scalar_chip, u2| {
+            scalar_multiply_glv::<F, _, GA>(
+                base_chip,
+                scalar_chip,
+                ctx,
+                pubkey,
+                u2,
+                var_window_bits,
+            )
+        },
+    )
+}
+
+// `u1_mul` computes `u1 * G` and `u2_mul` computes `u2 * pubkey` given the chip for the scalar field
+fn ecdsa_verify_with<'v, F: PrimeField, CF: PrimeField, SF: PrimeField, GA>(
+    base_chip: &FpConfig<F, CF>,
+    ctx: &mut Context<'v, F>,
+    r: &CRTInteger<'v, F>,
+    s: &CRTInteger<'v, F>,
+    msghash: &CRTInteger<'v, F>,
+    u1_mul: impl FnOnce(&mut Context<'v, F>, &CRTInteger<'v, F>) -> EcPoint<F, CRTInteger<'v, F>>,
+    u2_mul: impl FnOnce(
+        &mut Context<'v, F>,
+        &FpConfig<F, SF>,
+        &CRTInteger<'v, F>,
+    ) -> EcPoint<F, CRTInteger<'v, F>>,
+) -> AssignedValue<'v, F>
+where
+    GA: CurveAffineExt<Base = CF, ScalarExt = SF>,
+{
+    ctx.push_scope("ecdsa");
     let scalar_chip = FpConfig::<F, SF>::construct(
         base_chip.range.clone(),
         base_chip.limb_bits,
@@ -46,22 +194,12 @@ where
     //let r_crt = scalar_chip.to_crt(ctx, r)?;
 
     // compute u1 * G and u2 * pubkey
-    let u1_mul = fixed_base::scalar_multiply::<F, _, _>(
-        base_chip,
-        ctx,
-        &GA::generator(),
-        &u1.truncation.limbs,
-        base_chip.limb_bits,
-        fixed_window_bits,
-    );
-    let u2_mul = scalar_multiply::<F, _>(
-        base_chip,
-        ctx,
-        pubkey,
-        &u2.truncation.limbs,
-        base_chip.limb_bits,
-        var_window_bits,
-    );
+    ctx.push_scope("u1_mul");
+    let u1_mul = u1_mul(ctx, &u1);
+    ctx.pop_scope();
+    ctx.push_scope("u2_mul");
+    let u2_mul = u2_mul(ctx, &scalar_chip, &u2);
+    ctx.pop_scope();
 
     // check u1 * G and u2 * pubkey are not negatives and not equal
     //     TODO: Technically they could be equal for a valid signature, but this happens with vanishing probability
@@ -76,7 +214,9 @@ where
     //          invalid unless p is very close to n in size.
     base_chip.enforce_less_than_p(ctx, u1_mul.x());
     base_chip.enforce_less_than_p(ctx, u2_mul.x());
+    ctx.push_scope("ec_add_unequal");
     let sum = ec_add_unequal(base_chip, ctx, &u1_mul, &u2_mul, false);
+    ctx.pop_scope();
     let equal_check = base_chip.is_equal(ctx, &sum.x, r);
 
     // TODO: maybe the big_less_than is optional?
@@ -103,5 +243,6 @@ where
     let res3 = base_chip.range.gate().and(ctx, Existing(&res2), Existing(&u2_small));
     let res4 = base_chip.range.gate().and(ctx, Existing(&res3), Existing(&u1_u2_not_neg));
     let res5 = base_chip.range.gate().and(ctx, Existing(&res4), Existing(&equal_check));
+    ctx.pop_scope();
     res5
 }
@@ -0,0 +1,334 @@
+#![allow(non_snake_case)]
+//! Fixed base scalar multiplication with the window multiples of the base stored in a fixed lookup table.
+//!
+//! [`fixed_base::scalar_multiply`](super::fixed_base::scalar_multiply) assigns the `2^w` multiples of the base in each window as
+//! constants and selects one of them with `ec_select_from_bits`, which costs a number of cells linear in `2^w` per window.
+//! Here the multiples are rows of a fixed table instead, so each window only witnesses the limbs of its multiple and checks
+//! them with a single multi-column lookup.
+use super::{ec_add_unequal, ec_select, EcPoint};
+use crate::bigint::{CRTInteger, OverflowInteger};
+use crate::fields::{PrimeFieldChip, Selectable};
+use crate::halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, TableColumn},
+    poly::Rotation,
+};
+use group::Curve;
+use halo2_base::{
+    gates::GateInstructions,
+    utils::{decompose_biguint, fe_to_biguint, CurveAffineExt, PrimeField},
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use itertools::Itertools;
+use num_bigint::BigInt;
+
+/// Fixed lookup table of the window multiples of some fixed base points, in the first phase.
+///
+/// The table has rows `(tag, digit, x_0, ..., x_{num_limbs - 1}, y_0, ..., y_{num_limbs - 1})`, where `tag = 1 + idx * num_windows + i`
+/// for the `i`-th window of `bases[idx]` and `(x, y)` are the limbs of `[digit * 2^{i * window_bits}] * bases[idx]`. For `digit = 0`
+/// the row holds `[2^{i * window_bits}] * bases[idx]` instead of the point at infinity, as a dummy value just like in
+/// `fixed_base::scalar_multiply`.
+///
+/// The looked up cells are copied to `lookup_advice` at the end of the phase, with the tag of each row in the fixed column `lookup_tag`.
+/// The table also has an all zero row, so no selector is needed.
+#[derive(Clone, Debug)]
+pub struct FixedBaseLookupConfig<C: CurveAffineExt> {
+    /// key of the rows to look up in `ctx.cells_to_table_lookup`, must be distinct from other tables using it
+    pub table_id: usize,
+    pub bases: Vec<C>,
+    pub window_bits: usize,
+    pub num_windows: usize,
+    pub limb_bits: usize,
+    pub num_limbs: usize,
+    /// `[tag, digit, x limbs, y limbs]`
+    pub table: Vec<TableColumn>,
+    /// `[digit, x limbs, y limbs]`
+    pub lookup_advice: Vec<Vec<Column<Advice>>>,
+    pub lookup_tag: Vec<Column<Fixed>>,
+    // `multiples[tag - 1][digit]` is the point in the row `(tag, digit, ..)`
+    multiples: Vec<Vec<C>>,
+}
+
+impl<C: CurveAffineExt> FixedBaseLookupConfig<C>
+where
+    C::Base: PrimeField,
+{
+    /// `max_scalar_bits` is the total number of bits of the scalars to multiply the bases by.
+    pub fn configure<F: PrimeField>(
+        meta: &mut ConstraintSystem<F>,
+        bases: &[C],
+        max_scalar_bits: usize,
+        window_bits: usize,
+        limb_bits: usize,
+        num_limbs: usize,
+        num_lookup_advice: usize,
+        table_id: usize,
+        // params.k()
+        circuit_degree: usize,
+    ) -> Self {
+        assert!(window_bits > 0 && window_bits < 32);
+        let num_windows = (max_scalar_bits + window_bits - 1) / window_bits;
+        assert!(
+            1 + ((bases.len() * num_windows) << window_bits) < 1usize << circuit_degree,
+            "fixed base lookup table does not fit in 2^{circuit_degree} rows"
+        );
+
+        // first we compute all multiples in Jacobian coordinates since it's fastest
+        let mut multiples_jacobian = Vec::with_capacity((bases.len() * num_windows) << window_bits);
+        for base in bases {
+            assert!(!bool::from(base.is_identity()), "fixed base cannot be the point at infinity");
+            let mut increment = base.to_curve();
+            for _ in 0..num_windows {
+                // start with increment at index 0 instead of identity just as a dummy value to avoid divide by 0 issues
+                multiples_jacobian.push(increment);
+                let mut curr = increment;
+                for _ in 1..(1usize << window_bits) {
+                    multiples_jacobian.push(curr);
+                    curr += increment;
+                }
+                increment = curr;
+            }
+        }
+        let mut multiples_affine = vec![C::default(); multiples_jacobian.len()];
+        C::Curve::batch_normalize(&multiples_jacobian, &mut multiples_affine);
+        let multiples =
+            multiples_affine.chunks(1usize << window_bits).map(|window| window.to_vec()).collect();
+
+        let table = (0..2 + 2 * num_limbs).map(|_| meta.lookup_table_column()).collect_vec();
+        let lookup_advice = (0..num_lookup_advice)
+            .map(|_| {
+                (0..1 + 2 * num_limbs)
+                    .map(|_| {
+                        let a = meta.advice_column();
+                        meta.enable_equality(a);
+                        a
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let lookup_tag = (0..num_lookup_advice).map(|_| meta.fixed_column()).collect_vec();
+
+        for (advice, tag) in lookup_advice.iter().zip(lookup_tag.iter()) {
+            meta.lookup("fixed base lookup", |meta| {
+                let tag = meta.query_fixed(*tag, Rotation::cur());
+                std::iter::once(tag)
+                    .chain(advice.iter().map(|c| meta.query_advice(*c, Rotation::cur())))
+                    .zip(table.iter().cloned())
+                    .collect()
+            });
+        }
+
+        Self {
+            table_id,
+            bases: bases.to_vec(),
+            window_bits,
+            num_windows,
+            limb_bits,
+            num_limbs,
+            table,
+            lookup_advice,
+            lookup_tag,
+            multiples,
+        }
+    }
+
+    /// The tag of the rows of the `window`-th window of `bases[base_idx]`
+    pub fn tag(&self, base_idx: usize, window: usize) -> u64 {
+        assert!(base_idx < self.bases.len() && window < self.num_windows);
+        (1 + base_idx * self.num_windows + window) as u64
+    }
+
+    /// Returns the index of `base` in `bases`, if it is one of them.
+    pub fn base_index(&self, base: &C) -> Option<usize> {
+        self.bases.iter().position(|b| b == base)
+    }
+
+    // the limbs of the coordinates of `point`
+    fn coordinate_limbs<F: PrimeField>(&self, point: &C) -> [Vec<F>; 2] {
+        let (x, y) = point.into_coordinates();
+        [x, y].map(|c| decompose_biguint(&fe_to_biguint(&c), self.num_limbs, self.limb_bits))
+    }
+
+    pub fn load_lookup_table<F: PrimeField>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "fixed base lookup",
+            |mut table| {
+                let zero_row = vec![F::zero(); self.table.len()];
+                let rows = std::iter::once(zero_row).chain(
+                    self.multiples.iter().enumerate().flat_map(|(tag_idx, window)| {
+                        window.iter().enumerate().map(move |(digit, point)| {
+                            let [x, y] = self.coordinate_limbs(point);
+                            [F::from(tag_idx as u64 + 1), F::from(digit as u64)]
+                                .into_iter()
+                                .chain(x)
+                                .chain(y)
+                                .collect_vec()
+                        })
+                    }),
+                );
+                for (offset, row) in rows.enumerate() {
+                    for (column, val) in self.table.iter().zip(row) {
+                        table.assign_cell(
+                            || "fixed base lookup table",
+                            *column,
+                            offset,
+                            || Value::known(val),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Call this at the end of the first phase to copy the cells in `ctx.cells_to_table_lookup[table_id]` to `lookup_advice`
+    ///
+    /// returns total number of rows looked up
+    pub fn finalize<F: PrimeField>(&self, ctx: &mut Context<'_, F>) -> usize {
+        let rows = ctx.cells_to_table_lookup.remove(&self.table_id).unwrap_or_default();
+        if rows.is_empty() {
+            return 0;
+        }
+        assert_eq!(
+            ctx.current_phase(),
+            0,
+            "fixed base lookups are only supported in the first phase"
+        );
+        let total_rows = rows.len();
+        let mut rows = rows.iter();
+        for (advice, tag_column) in self.lookup_advice.iter().zip(self.lookup_tag.iter()) {
+            for (offset, (tag, cells)) in rows.by_ref().take(ctx.max_rows).enumerate() {
+                for (acell, column) in cells.iter().zip(advice) {
+                    acell.copy_advice(ctx.region(), *column, offset);
+                }
+                #[cfg(feature = "halo2-axiom")]
+                ctx.region().assign_fixed(
+                    *tag_column,
+                    offset,
+                    crate::halo2_proofs::plonk::Assigned::Trivial(F::from(*tag)),
+                );
+                #[cfg(feature = "halo2-pse")]
+                ctx.region()
+                    .assign_fixed(|| "", *tag_column, offset, || Value::known(F::from(*tag)))
+                    .expect("assign fixed should not fail");
+            }
+        }
+        if rows.next().is_some() {
+            panic!("NOT ENOUGH ADVICE COLUMNS WITH FIXED BASE LOOKUP ENABLED");
+        }
+        total_rows
+    }
+
+    // Witnesses the multiple of `bases[base_idx]` in the `window`-th window for `digit` and queues its row for lookup
+    fn load_multiple<'v, F, FC>(
+        &self,
+        chip: &FC,
+        ctx: &mut Context<'v, F>,
+        base_idx: usize,
+        window: usize,
+        digit: &AssignedValue<'v, F>,
+    ) -> EcPoint<F, FC::FieldPoint<'v>>
+    where
+        F: PrimeField,
+        FC: PrimeFieldChip<F, FieldType = C::Base, FieldPoint<'v> = CRTInteger<'v, F>>,
+    {
+        let tag = self.tag(base_idx, window);
+        let point =
+            digit.value().map(|d| self.multiples[tag as usize - 1][d.get_lower_32() as usize]);
+        let mut row = vec![digit.clone()];
+        let [x, y] = [0, 1].map(|i| {
+            let limbs = chip.gate().assign_witnesses(
+                ctx,
+                point.map(|p| self.coordinate_limbs(&p)[i].clone()).transpose_vec(self.num_limbs),
+            );
+            let native = OverflowInteger::evaluate(
+                chip.gate(),
+                ctx,
+                &limbs,
+                chip.limb_bases().iter().cloned(),
+            );
+            let value = point.map(|p| {
+                let (x, y) = p.into_coordinates();
+                BigInt::from(fe_to_biguint(&[x, y][i]))
+            });
+            row.extend(limbs.iter().cloned());
+            CRTInteger::construct(OverflowInteger::construct(limbs, self.limb_bits), native, value)
+        });
+        ctx.cells_to_table_lookup.entry(self.table_id).or_default().push((tag, row));
+        EcPoint::construct(x, y)
+    }
+
+    /// Computes `[scalar] * bases[base_idx]` in the same way as [`fixed_base::scalar_multiply`](super::fixed_base::scalar_multiply),
+    /// but looks up the multiple of the base in each window in this table.
+    ///
+    /// `scalar = sum_i scalar_i * 2^{max_bits * i}` and assumes `scalar_i < 2^{max_bits}` for all `i` (constrained by num_to_bits).
+    /// The table must have at least `max_bits * scalar.len()` bits of windows.
+    pub fn scalar_multiply<'v, F, FC>(
+        &self,
+        chip: &FC,
+        ctx: &mut Context<'v, F>,
+        base_idx: usize,
+        scalar: &[AssignedValue<'v, F>],
+        max_bits: usize,
+    ) -> EcPoint<F, FC::FieldPoint<'v>>
+    where
+        F: PrimeField,
+        FC: PrimeFieldChip<F, FieldType = C::Base, FieldPoint<'v> = CRTInteger<'v, F>>
+            + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+    {
+        assert!(!scalar.is_empty());
+        assert!((max_bits as u32) <= F::NUM_BITS);
+        assert_eq!(chip.limb_bits(), self.limb_bits);
+        assert_eq!(chip.num_limbs(), self.num_limbs);
+        let total_bits = max_bits * scalar.len();
+        assert!(
+            total_bits <= self.num_windows * self.window_bits,
+            "fixed base lookup table only has {} windows of {} bits",
+            self.num_windows,
+            self.window_bits
+        );
+
+        let bits = scalar
+            .iter()
+            .flat_map(|scalar_chunk| chip.gate().num_to_bits(ctx, scalar_chunk, max_bits))
+            .collect_vec();
+
+        let mut curr_point = None;
+        // `is_started` is just a way to deal with if `curr_point` is actually identity
+        let mut is_started = chip.gate().load_zero(ctx);
+        for (window, bit_window) in bits.chunks(self.window_bits).enumerate().rev() {
+            let digit = chip.gate().inner_product(
+                ctx,
+                bit_window.iter().map(Existing),
+                chip.gate().pow_of_two()[..bit_window.len()].iter().map(|c| Constant(*c)),
+            );
+            // are we just adding a window of all 0s? if so, skip
+            let is_zero_window = chip.gate().is_zero(ctx, &digit);
+            let add_point = self.load_multiple(chip, ctx, base_idx, window, &digit);
+            curr_point = if let Some(curr_point) = curr_point {
+                let sum = ec_add_unequal(chip, ctx, &curr_point, &add_point, false);
+                let zero_sum = ec_select(chip, ctx, &curr_point, &sum, &is_zero_window);
+                Some(ec_select(chip, ctx, &zero_sum, &add_point, &is_started))
+            } else {
+                Some(add_point)
+            };
+            is_started = {
+                // is_started || !is_zero_window
+                // (a || !b) = (1-b) + a*b
+                let not_zero_window = chip.gate().not(ctx, Existing(&is_zero_window));
+                chip.gate().mul_add(
+                    ctx,
+                    Existing(&is_started),
+                    Existing(&is_zero_window),
+                    Existing(&not_zero_window),
+                )
+            };
+        }
+        curr_point.unwrap()
+    }
+}
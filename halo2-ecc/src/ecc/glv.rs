@@ -0,0 +1,210 @@
+#![allow(non_snake_case)]
+//! Scalar multiplication using the GLV endomorphism.
+//!
+//! On curves `y^2 = x^3 + b` over a field with a nontrivial cube root of unity `beta`, the map `phi(x, y) = (beta * x, y)` is an
+//! endomorphism that acts on the prime order subgroup as multiplication by a cube root of unity `lambda` in the scalar field.
+//! Writing `k = k1 + k2 * lambda mod n` with `|k1|, |k2| ~ sqrt(n)` turns `[k] P` into the 2-point multi-scalar multiplication
+//! `[k1] P + [k2] phi(P)` with half-length scalars, which halves the number of doublings.
+//! See https://www.iacr.org/archive/crypto2001/21390189.pdf
+use super::{ec_select, multi_scalar_multiply, EcPoint};
+use crate::bigint::CRTInteger;
+use crate::fields::{fp::FpConfig, FieldChip, Selectable};
+use crate::halo2_proofs::{
+    circuit::Value,
+    halo2curves::{
+        bn256::{self, G1Affine},
+        secp256k1::{self, Secp256k1Affine},
+    },
+};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::{fe_to_biguint, modulus, CurveAffineExt, PrimeField},
+    AssignedValue, Context,
+};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+
+/// A curve with an endomorphism `phi(x, y) = (BETA * x, y) = [LAMBDA] (x, y)`.
+pub trait GlvCurve: CurveAffineExt {
+    /// Nontrivial cube root of unity in the base field
+    const BETA: Self::Base;
+    /// Nontrivial cube root of unity in the scalar field matching `BETA`
+    const LAMBDA: Self::ScalarExt;
+
+    /// Returns a reduced basis `[(a1, b1), (a2, b2)]` of the lattice `{ (a, b) : a + b * LAMBDA = 0 mod n }`.
+    fn glv_basis() -> [(BigInt, BigInt); 2];
+}
+
+impl GlvCurve for Secp256k1Affine {
+    const BETA: secp256k1::Fp = secp256k1::Fp::from_raw([
+        0xc1396c28719501ee,
+        0x9cf0497512f58995,
+        0x6e64479eac3434e9,
+        0x7ae96a2b657c0710,
+    ]);
+    const LAMBDA: secp256k1::Fq = secp256k1::Fq::from_raw([
+        0xdf02967c1b23bd72,
+        0x122e22ea20816678,
+        0xa5261c028812645a,
+        0x5363ad4cc05c30e0,
+    ]);
+
+    fn glv_basis() -> [(BigInt, BigInt); 2] {
+        [
+            (
+                BigInt::from(0x3086d221a7d46bcde86c90e49284eb15u128),
+                -BigInt::from(0xe4437ed6010e88286f547fa90abfe4c3u128),
+            ),
+            (
+                BigInt::parse_bytes(b"114ca50f7a8e2f3f657c1108d9d44cfd8", 16).unwrap(),
+                BigInt::from(0x3086d221a7d46bcde86c90e49284eb15u128),
+            ),
+        ]
+    }
+}
+
+impl GlvCurve for G1Affine {
+    const BETA: bn256::Fq = bn256::Fq::from_raw([
+        0x5763473177fffffe,
+        0xd4f263f1acdb5c4f,
+        0x59e26bcea0d48bac,
+        0x0000000000000000,
+    ]);
+    const LAMBDA: bn256::Fr = bn256::Fr::from_raw([
+        0x8b17ea66b99c90dd,
+        0x5bfc41088d8daaa7,
+        0xb3c4d79d41a91758,
+        0x0000000000000000,
+    ]);
+
+    fn glv_basis() -> [(BigInt, BigInt); 2] {
+        [
+            (
+                BigInt::from(0x89d3256894d213e3u128),
+                -BigInt::from(0x6f4d8248eeb859fc8211bbeb7d4f1128u128),
+            ),
+            (
+                BigInt::from(0x6f4d8248eeb859fd0be4e1541221250bu128),
+                BigInt::from(0x89d3256894d213e3u128),
+            ),
+        ]
+    }
+}
+
+/// Returns an upper bound on the bit length of `|k1|` and `|k2|` produced by [`decompose_scalar`].
+pub fn glv_max_bits<C: GlvCurve>() -> usize {
+    let [(a1, b1), (a2, b2)] = C::glv_basis();
+    // |k1| <= (|a1| + |a2|) / 2 and |k2| <= (|b1| + |b2|) / 2
+    let a_bits = (a1.magnitude() + a2.magnitude()).bits();
+    let b_bits = (b1.magnitude() + b2.magnitude()).bits();
+    a_bits.max(b_bits) as usize
+}
+
+/// Returns `(k1, k2)` with `k = k1 + k2 * LAMBDA mod n` and `|k1|, |k2| < 2^{glv_max_bits}`, for `0 <= k < n`.
+pub fn decompose_scalar<C: GlvCurve>(k: &BigUint) -> (BigInt, BigInt)
+where
+    C::ScalarExt: PrimeField,
+{
+    let n = BigInt::from(modulus::<C::ScalarExt>());
+    let k = BigInt::from(k.clone());
+    let [(a1, b1), (a2, b2)] = C::glv_basis();
+    // round(a / n) = floor((2a + n) / 2n)
+    let round = |a: BigInt| (a * 2u32 + &n).div_floor(&(&n * 2u32));
+    let c1 = round(&b2 * &k);
+    let c2 = round(-&b1 * &k);
+    let k1 = k - &c1 * a1 - &c2 * a2;
+    let k2 = -c1 * b1 - c2 * b2;
+    (k1, k2)
+}
+
+// Loads `|k|` as a `CRTInteger` constrained to `max_bits` bits, together with the sign bit of `k` (1 if negative).
+fn load_signed_half<'v, F: PrimeField, SF: PrimeField>(
+    scalar_chip: &FpConfig<F, SF>,
+    ctx: &mut Context<'v, F>,
+    k: Value<BigInt>,
+    max_bits: usize,
+) -> (CRTInteger<'v, F>, AssignedValue<'v, F>) {
+    let gate = scalar_chip.range.gate();
+    let sign =
+        gate.load_witness(ctx, k.as_ref().map(|k| F::from((k.sign() == Sign::Minus) as u64)));
+    gate.assert_bit(ctx, &sign);
+    let abs = scalar_chip.load_private(ctx, k.map(|k| BigInt::from(k.magnitude().clone())));
+    // `load_private` only range checks the limbs up to the size of the scalar field modulus
+    for (i, limb) in abs.truncation.limbs.iter().enumerate() {
+        let limb_bits =
+            max_bits.saturating_sub(scalar_chip.limb_bits * i).min(scalar_chip.limb_bits);
+        if limb_bits == 0 {
+            gate.assert_is_const(ctx, limb, F::zero());
+        } else if limb_bits < scalar_chip.limb_bits {
+            scalar_chip.range.range_check(ctx, limb, limb_bits);
+        }
+    }
+    (abs, sign)
+}
+
+/// Computes `[scalar] * P` on a curve with the GLV endomorphism, as the 2-point multi-scalar multiplication `[k1] P + [k2] phi(P)`.
+///
+/// `scalar` is an element of the scalar field of `C` loaded by `scalar_chip`, and need not be reduced. The decomposition
+/// `scalar = k1 + k2 * LAMBDA mod n` is witnessed and constrained with `scalar_chip`.
+///
+/// Assumes the same as [`multi_scalar_multiply`], in particular that `P` has order `n` and `scalar` is not `0 mod n`.
+pub fn scalar_multiply_glv<'v, F, FC, C>(
+    chip: &FC,
+    scalar_chip: &FpConfig<F, C::ScalarExt>,
+    ctx: &mut Context<'v, F>,
+    P: &EcPoint<F, FC::FieldPoint<'v>>,
+    scalar: &CRTInteger<'v, F>,
+    window_bits: usize,
+) -> EcPoint<F, FC::FieldPoint<'v>>
+where
+    F: PrimeField,
+    FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+    C: GlvCurve<Base = FC::FieldType>,
+    C::ScalarExt: PrimeField,
+{
+    let max_bits = glv_max_bits::<C>();
+    assert!(max_bits < F::CAPACITY as usize);
+
+    let n = BigInt::from(modulus::<C::ScalarExt>());
+    let (k1, k2) =
+        scalar.value.as_ref().map(|k| decompose_scalar::<C>(k.mod_floor(&n).magnitude())).unzip();
+    let (k1_abs, k1_neg) = load_signed_half(scalar_chip, ctx, k1, max_bits);
+    let (k2_abs, k2_neg) = load_signed_half(scalar_chip, ctx, k2, max_bits);
+
+    // constrain scalar = (+-k1) + (+-k2) * LAMBDA mod n
+    let k1_signed = {
+        let neg = scalar_chip.negate(ctx, &k1_abs);
+        scalar_chip.select(ctx, &neg, &k1_abs, &k1_neg)
+    };
+    let k2_signed = {
+        let neg = scalar_chip.negate(ctx, &k2_abs);
+        scalar_chip.select(ctx, &neg, &k2_abs, &k2_neg)
+    };
+    let lambda = scalar_chip.load_constant(ctx, fe_to_biguint(&C::LAMBDA));
+    let lambda_k2 = scalar_chip.mul_no_carry(ctx, &k2_signed, &lambda);
+    let sum = scalar_chip.add_no_carry(ctx, &k1_signed, &lambda_k2);
+    let diff = scalar_chip.sub_no_carry(ctx, &sum, scalar);
+    scalar_chip.check_carry_mod_to_zero(ctx, &diff);
+
+    // [k] P = [|k1|] (+-P) + [|k2|] (+-phi(P))
+    let neg_y = chip.negate(ctx, &P.y);
+    let beta = chip.load_constant(ctx, FC::fe_to_constant(C::BETA));
+    let phi_x = chip.mul(ctx, &P.x, &beta);
+    let P1 = ec_select(chip, ctx, &EcPoint::construct(P.x.clone(), neg_y.clone()), P, &k1_neg);
+    let P2 = ec_select(
+        chip,
+        ctx,
+        &EcPoint::construct(phi_x.clone(), neg_y),
+        &EcPoint::construct(phi_x, P.y.clone()),
+        &k2_neg,
+    );
+    // `|k_i| < 2^max_bits < modulus::<F>()`, so the native limb of the `CRTInteger` is the integer itself
+    multi_scalar_multiply::<F, FC, C>(
+        chip,
+        ctx,
+        &[P1, P2],
+        &[vec![k1_abs.native], vec![k2_abs.native]],
+        max_bits,
+        window_bits,
+    )
+}
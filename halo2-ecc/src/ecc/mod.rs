@@ -1,25 +1,77 @@
 #![allow(non_snake_case)]
-use crate::bigint::CRTInteger;
+use crate::bigint::{big_less_than, CRTInteger, OverflowInteger};
 use crate::fields::{fp::FpConfig, FieldChip, PrimeFieldChip, Selectable};
 use crate::halo2_proofs::{arithmetic::CurveAffine, circuit::Value};
 use group::{Curve, Group};
 use halo2_base::{
     gates::{GateInstructions, RangeInstructions},
-    utils::{modulus, CurveAffineExt, PrimeField},
+    utils::{biguint_to_fe, fe_to_biguint, modulus, CurveAffineExt, PrimeField},
     AssignedValue, Context,
     QuantumCell::Existing,
 };
 use itertools::Itertools;
-use rand::SeedableRng;
+use num_bigint::{BigInt, BigUint};
+use rand::{RngCore, SeedableRng};
 use rand_chacha::ChaCha20Rng;
 use std::marker::PhantomData;
 
 pub mod ecdsa;
 pub mod fixed_base;
+pub mod fixed_base_lookup;
+pub mod glv;
+pub mod msm_cost;
 // pub mod fixed_base_pippenger;
 pub mod pippenger;
+pub mod sec1;
+
+/// Coefficients of a short Weierstrass curve `y^2 = x^3 + a x + b` over `Self::Base`, with a prime order subgroup of order
+/// the modulus of `Self::ScalarField`.
+///
+/// Implemented for every [`CurveAffine`] using its own `a()` and `b()`. Curves without a `CurveAffine` implementation can
+/// implement it directly to use the functions in this module that only need the curve equation, such as [`ec_double`],
+/// [`is_on_curve`], [`scalar_multiply`] and [`multi_scalar_multiply`].
+pub trait CurveParams {
+    type Base: ff::Field;
+    type ScalarField: ff::Field;
+
+    fn coeff_a() -> Self::Base;
+    fn coeff_b() -> Self::Base;
+
+    /// Returns the coordinates of a random point on the curve, by sampling `x` until `x^3 + a x + b` is a square.
+    ///
+    /// On curves with a cofactor this point need not be in the prime order subgroup, so implementations should override it.
+    fn random_point(mut rng: impl RngCore) -> (Self::Base, Self::Base) {
+        loop {
+            let x = Self::Base::random(&mut rng);
+            let rhs = x.square() * x + Self::coeff_a() * x + Self::coeff_b();
+            if let Some(y) = Option::from(rhs.sqrt()) {
+                return (x, y);
+            }
+        }
+    }
+}
+
+impl<C: CurveAffine> CurveParams for C {
+    type Base = C::Base;
+    type ScalarField = C::ScalarExt;
+
+    fn coeff_a() -> C::Base {
+        C::a()
+    }
 
-// EcPoint and EccChip take in a generic `FieldChip` to implement generic elliptic curve operations on arbitrary field extensions (provided chip exists) for short Weierstrass curves (currently further assuming a4 = 0 for optimization purposes)
+    fn coeff_b() -> C::Base {
+        C::b()
+    }
+
+    fn random_point(rng: impl RngCore) -> (C::Base, C::Base) {
+        let point: C = C::CurveExt::random(rng).to_affine();
+        let coordinates = point.coordinates().unwrap();
+        (*coordinates.x(), *coordinates.y())
+    }
+}
+
+// EcPoint and EccChip take in a generic `FieldChip` to implement generic elliptic curve operations on arbitrary field extensions (provided chip exists) for short Weierstrass curves `y^2 = x^3 + a x + b`, given by a `CurveParams`
+// The operations skip the terms involving `a` when `a = 0`, so curves such as secp256k1 and BN254 pay nothing for the generality
 #[derive(Debug)]
 pub struct EcPoint<F: PrimeField, FieldPoint: Clone> {
     pub x: FieldPoint,
@@ -47,6 +99,65 @@ impl<F: PrimeField, FieldPoint: Clone> EcPoint<F, FieldPoint> {
     }
 }
 
+/// A scalar of a curve with scalar field `SF`, as little-endian limbs of `limb_bits` bits each.
+///
+/// Only constructed by [`EccChip::load_scalar`] and [`EccChip::assign_scalar`], which constrain the limbs to be the canonical
+/// representation of a scalar, i.e., less than the modulus of `SF`. Otherwise a scalar `s` could also be represented as `s + n`,
+/// which gives the same point in scalar multiplication but may be treated as a different value elsewhere in the circuit.
+#[derive(Debug)]
+pub struct AssignedScalar<'v, F: PrimeField, SF> {
+    limbs: Vec<AssignedValue<'v, F>>,
+    limb_bits: usize,
+    _marker: PhantomData<SF>,
+}
+
+impl<'v, F: PrimeField, SF> Clone for AssignedScalar<'v, F, SF> {
+    fn clone(&self) -> Self {
+        Self { limbs: self.limbs.clone(), limb_bits: self.limb_bits, _marker: PhantomData }
+    }
+}
+
+impl<'v, F: PrimeField, SF> AssignedScalar<'v, F, SF> {
+    pub fn limbs(&self) -> &[AssignedValue<'v, F>] {
+        &self.limbs
+    }
+
+    pub fn limb_bits(&self) -> usize {
+        self.limb_bits
+    }
+}
+
+/// An elliptic curve point that may be the point at infinity (the identity of the group).
+///
+/// The point at infinity has no affine coordinates, so it is stored as `(0, 0)` together with the flag `is_infinity = 1`.
+/// Since `(0, 0)` is not on a curve `y^2 = x^3 + b` with `b != 0`, every other point has `is_infinity = 0`.
+#[derive(Debug)]
+pub struct EcPointOrInfinity<'v, F: PrimeField, FieldPoint: Clone> {
+    pub point: EcPoint<F, FieldPoint>,
+    pub is_infinity: AssignedValue<'v, F>,
+}
+
+impl<'v, F: PrimeField, FieldPoint: Clone> Clone for EcPointOrInfinity<'v, F, FieldPoint> {
+    fn clone(&self) -> Self {
+        Self { point: self.point.clone(), is_infinity: self.is_infinity.clone() }
+    }
+}
+
+impl<'v, F: PrimeField, FieldPoint: Clone> EcPointOrInfinity<'v, F, FieldPoint> {
+    /// Assumes that `point` is `(0, 0)` if `is_infinity` is 1.
+    pub fn construct(point: EcPoint<F, FieldPoint>, is_infinity: AssignedValue<'v, F>) -> Self {
+        Self { point, is_infinity }
+    }
+
+    pub fn point(&self) -> &EcPoint<F, FieldPoint> {
+        &self.point
+    }
+
+    pub fn is_infinity(&self) -> &AssignedValue<'v, F> {
+        &self.is_infinity
+    }
+}
+
 // Implements:
 //  Given P = (x_1, y_1) and Q = (x_2, y_2), ecc points over the field F_p
 //      assume x_1 != x_2
@@ -143,22 +254,29 @@ pub fn ec_sub_unequal<'v, F: PrimeField, FC: FieldChip<F>>(
 // formula from https://crypto.stanford.edu/pbc/notes/elliptic/explicit.html
 // assume y != 0 (otherwise 2P = O)
 
-// lamb =  3x^2 / (2 y) % p
+// lamb =  (3x^2 + a) / (2 y) % p
 // x_3 = out[0] = lambda^2 - 2 x % p
 // y_3 = out[1] = lambda (x - x_3) - y % p
 
-// we precompute lambda and constrain (2y) * lambda = 3 x^2 (mod p)
+// we precompute lambda and constrain (2y) * lambda = 3 x^2 + a (mod p)
 // then we compute x_3 = lambda^2 - 2 x (mod p)
 //                 y_3 = lambda (x - x_3) - y (mod p)
-pub fn ec_double<'v, F: PrimeField, FC: FieldChip<F>>(
+pub fn ec_double<'v, F: PrimeField, FC: FieldChip<F>, C>(
     chip: &FC,
     ctx: &mut Context<'v, F>,
     P: &EcPoint<F, FC::FieldPoint<'v>>,
-) -> EcPoint<F, FC::FieldPoint<'v>> {
+) -> EcPoint<F, FC::FieldPoint<'v>>
+where
+    C: CurveParams<Base = FC::FieldType>,
+{
     // removed optimization that computes `2 * lambda` while assigning witness to `lambda` simultaneously, in favor of readability. The difference is just copying `lambda` once
     let two_y = chip.scalar_mul_no_carry(ctx, &P.y, 2);
     let three_x = chip.scalar_mul_no_carry(ctx, &P.x, 3);
-    let three_x_sq = chip.mul_no_carry(ctx, &three_x, &P.x);
+    let mut three_x_sq = chip.mul_no_carry(ctx, &three_x, &P.x);
+    let a = C::coeff_a();
+    if !bool::from(ff::Field::is_zero(&a)) {
+        three_x_sq = chip.add_constant_no_carry(ctx, &three_x_sq, FC::fe_to_constant(a));
+    }
     let lambda = chip.divide(ctx, &three_x_sq, &two_y);
 
     // x_3 = lambda^2 - 2 x % p
@@ -176,6 +294,99 @@ pub fn ec_double<'v, F: PrimeField, FC: FieldChip<F>>(
     EcPoint::construct(x_3, y_3)
 }
 
+// Implements complete addition on y^2 = x^3 + b from Algorithm 7 of https://eprint.iacr.org/2015/1060.pdf (Renes-Costello-Batina)
+// The formulas are in projective coordinates (X : Y : Z), where the point at infinity is (0 : 1 : 0), and are complete on curves of odd order:
+// they hold for all inputs, including P = Q, P = -Q and P or Q the point at infinity.
+// With b3 = 3 * b and P = (X_1 : Y_1 : Z_1), Q = (X_2 : Y_2 : Z_2):
+//  t_0 = X_1 X_2, t_1 = Y_1 Y_2, t_2 = Z_1 Z_2
+//  t_3 = X_1 Y_2 + X_2 Y_1, t_4 = Y_1 Z_2 + Y_2 Z_1, t_5 = X_1 Z_2 + X_2 Z_1
+//  X_3 = t_3 (t_1 - b3 t_2) - b3 t_4 t_5
+//  Y_3 = (t_1 + b3 t_2)(t_1 - b3 t_2) + 3 b3 t_0 t_5
+//  Z_3 = t_4 (t_1 + b3 t_2) + 3 t_0 t_3
+// then the sum is (X_3 / Z_3, Y_3 / Z_3), or the point at infinity if Z_3 = 0.
+//
+/// Assumes that `P` and `Q` are each on the curve or the point at infinity, and that the curve has `a = 0` and odd order.
+///
+/// This costs about three times as much as [`ec_add_unequal`], so prefer that one where `P.x != Q.x` is guaranteed.
+pub fn ec_add_complete<'v, F, FC, C>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    P: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+    Q: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+) -> EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>
+where
+    F: PrimeField,
+    FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+    C: CurveParams<Base = FC::FieldType>,
+{
+    assert!(
+        bool::from(ff::Field::is_zero(&C::coeff_a())),
+        "complete addition is only implemented for curves with a = 0"
+    );
+    let zero = chip.load_constant(ctx, FC::fe_to_constant(<FC::FieldType as ff::Field>::zero()));
+    let one = chip.load_constant(ctx, FC::fe_to_constant(<FC::FieldType as ff::Field>::one()));
+    let b = C::coeff_b();
+    let b3 = chip.load_constant(ctx, FC::fe_to_constant(b + b + b));
+
+    // (x, y) -> (x : y : 1) and (0, 0) -> (0 : 1 : 0)
+    let [(X_1, Y_1, Z_1), (X_2, Y_2, Z_2)] = [P, Q].map(|P| {
+        let Y = chip.select(ctx, &one, &P.point.y, &P.is_infinity);
+        let Z = chip.select(ctx, &zero, &one, &P.is_infinity);
+        (P.point.x.clone(), Y, Z)
+    });
+
+    let t_0 = chip.mul(ctx, &X_1, &X_2);
+    let t_1 = chip.mul(ctx, &Y_1, &Y_2);
+    let t_2 = chip.mul(ctx, &Z_1, &Z_2);
+    // (A_1 + B_1)(A_2 + B_2) - A_1 A_2 - B_1 B_2 = A_1 B_2 + A_2 B_1
+    let cross = |ctx: &mut Context<'v, F>,
+                 (A_1, B_1): (&FC::FieldPoint<'v>, &FC::FieldPoint<'v>),
+                 (A_2, B_2): (&FC::FieldPoint<'v>, &FC::FieldPoint<'v>),
+                 (t_A, t_B): (&FC::FieldPoint<'v>, &FC::FieldPoint<'v>)| {
+        let sum_1 = chip.add_no_carry(ctx, A_1, B_1);
+        let sum_2 = chip.add_no_carry(ctx, A_2, B_2);
+        let prod = chip.mul_no_carry(ctx, &sum_1, &sum_2);
+        let prod = chip.sub_no_carry(ctx, &prod, t_A);
+        let prod = chip.sub_no_carry(ctx, &prod, t_B);
+        chip.carry_mod(ctx, &prod)
+    };
+    let t_3 = cross(ctx, (&X_1, &Y_1), (&X_2, &Y_2), (&t_0, &t_1));
+    let t_4 = cross(ctx, (&Y_1, &Z_1), (&Y_2, &Z_2), (&t_1, &t_2));
+    let t_5 = cross(ctx, (&X_1, &Z_1), (&X_2, &Z_2), (&t_0, &t_2));
+
+    let b3_t_2 = chip.mul(ctx, &b3, &t_2);
+    let b3_t_5 = chip.mul(ctx, &b3, &t_5);
+    let diff = chip.sub_no_carry(ctx, &t_1, &b3_t_2);
+    let sum = chip.add_no_carry(ctx, &t_1, &b3_t_2);
+    let three_t_0 = chip.scalar_mul_no_carry(ctx, &t_0, 3);
+
+    // X_3 = t_3 (t_1 - b3 t_2) - b3 t_4 t_5
+    let X_3 = chip.mul_no_carry(ctx, &t_3, &diff);
+    let t_4_b3_t_5 = chip.mul_no_carry(ctx, &t_4, &b3_t_5);
+    let X_3 = chip.sub_no_carry(ctx, &X_3, &t_4_b3_t_5);
+    let X_3 = chip.carry_mod(ctx, &X_3);
+    // Y_3 = (t_1 + b3 t_2)(t_1 - b3 t_2) + 3 b3 t_0 t_5
+    let Y_3 = chip.mul_no_carry(ctx, &sum, &diff);
+    let t_0_b3_t_5 = chip.mul_no_carry(ctx, &three_t_0, &b3_t_5);
+    let Y_3 = chip.add_no_carry(ctx, &Y_3, &t_0_b3_t_5);
+    let Y_3 = chip.carry_mod(ctx, &Y_3);
+    // Z_3 = t_4 (t_1 + b3 t_2) + 3 t_0 t_3
+    let Z_3 = chip.mul_no_carry(ctx, &t_4, &sum);
+    let t_0_t_3 = chip.mul_no_carry(ctx, &three_t_0, &t_3);
+    let Z_3 = chip.add_no_carry(ctx, &Z_3, &t_0_t_3);
+    let Z_3 = chip.carry_mod(ctx, &Z_3);
+
+    // back to affine coordinates, dividing by 1 instead of 0 for the point at infinity
+    let is_infinity = chip.is_zero(ctx, &Z_3);
+    let Z_3 = chip.select(ctx, &one, &Z_3, &is_infinity);
+    let x_3 = chip.divide(ctx, &X_3, &Z_3);
+    let y_3 = chip.divide(ctx, &Y_3, &Z_3);
+    let x_3 = chip.select(ctx, &zero, &x_3, &is_infinity);
+    let y_3 = chip.select(ctx, &zero, &y_3, &is_infinity);
+
+    EcPointOrInfinity::construct(EcPoint::construct(x_3, y_3), is_infinity)
+}
+
 pub fn ec_select<'v, F: PrimeField, FC>(
     chip: &FC,
     ctx: &mut Context<'_, F>,
@@ -191,6 +402,26 @@ where
     EcPoint::construct(Rx, Ry)
 }
 
+pub fn ec_select_or_infinity<'v, F: PrimeField, FC>(
+    chip: &FC,
+    ctx: &mut Context<'_, F>,
+    P: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+    Q: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+    sel: &AssignedValue<'v, F>,
+) -> EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>
+where
+    FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+{
+    let point = ec_select(chip, ctx, &P.point, &Q.point, sel);
+    let is_infinity = chip.range().gate().select(
+        ctx,
+        Existing(&P.is_infinity),
+        Existing(&Q.is_infinity),
+        Existing(sel),
+    );
+    EcPointOrInfinity::construct(point, is_infinity)
+}
+
 // takes the dot product of points with sel, where each is intepreted as
 // a _vector_
 pub fn ec_select_by_indicator<'v, F: PrimeField, FC>(
@@ -234,7 +465,7 @@ where
 // - `scalar_i < 2^{max_bits} for all i` (constrained by num_to_bits)
 // - `max_bits <= modulus::<F>.bits()`
 //   * P has order given by the scalar field modulus
-pub fn scalar_multiply<'v, F: PrimeField, FC>(
+pub fn scalar_multiply<'v, F: PrimeField, FC, C>(
     chip: &FC,
     ctx: &mut Context<'v, F>,
     P: &EcPoint<F, FC::FieldPoint<'v>>,
@@ -244,6 +475,7 @@ pub fn scalar_multiply<'v, F: PrimeField, FC>(
 ) -> EcPoint<F, FC::FieldPoint<'v>>
 where
     FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+    C: CurveParams<Base = FC::FieldType>,
 {
     assert!(!scalar.is_empty());
     assert!((max_bits as u64) <= modulus::<F>().bits());
@@ -297,7 +529,7 @@ where
     cached_points.push(P.clone());
     for idx in 2..cache_size {
         if idx == 2 {
-            let double = ec_double(chip, ctx, P /*, b*/);
+            let double = ec_double::<F, FC, C>(chip, ctx, P);
             cached_points.push(double.clone());
         } else {
             let new_point = ec_add_unequal(chip, ctx, &cached_points[idx - 1], P, false);
@@ -316,7 +548,7 @@ where
     for idx in 1..num_windows {
         let mut mult_point = curr_point.clone();
         for _ in 0..window_bits {
-            mult_point = ec_double(chip, ctx, &mult_point);
+            mult_point = ec_double::<F, FC, C>(chip, ctx, &mult_point);
         }
         let add_point = ec_select_from_bits::<F, FC>(
             chip,
@@ -335,22 +567,118 @@ where
     curr_point
 }
 
-pub fn is_on_curve<'v, F, FC, C>(
+// computes [scalar] * P on y^2 = x^3 + b using complete addition, so that both `P` and the result may be the point at infinity
+// - `scalar = sum_i scalar_i * 2^{max_bits * i}` as in `scalar_multiply`
+// assumes:
+// - `scalar_i < 2^{max_bits} for all i` (constrained by num_to_bits)
+// - `max_bits <= modulus::<F>.bits()`
+// - P is on the curve or the point at infinity, and the curve has odd order
+// unlike `scalar_multiply`, `scalar` may be zero or a multiple of the order of P
+pub fn scalar_multiply_complete<'v, F, FC, C>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    P: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+    scalar: &[AssignedValue<'v, F>],
+    max_bits: usize,
+    window_bits: usize,
+) -> EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>
+where
+    F: PrimeField,
+    FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+    C: CurveParams<Base = FC::FieldType>,
+{
+    assert!(!scalar.is_empty());
+    assert!((max_bits as u64) <= modulus::<F>().bits());
+
+    let total_bits = max_bits * scalar.len();
+    let num_windows = (total_bits + window_bits - 1) / window_bits;
+    let rounded_bitlen = num_windows * window_bits;
+
+    let mut bits = Vec::with_capacity(rounded_bitlen);
+    for x in scalar {
+        let mut new_bits = chip.gate().num_to_bits(ctx, x, max_bits);
+        bits.append(&mut new_bits);
+    }
+    let zero_cell = chip.gate().load_constant(ctx, F::zero());
+    bits.resize(rounded_bitlen, zero_cell);
+
+    // cached_points[idx] stores idx * P, with cached_points[0] the point at infinity
+    let cache_size = 1usize << window_bits;
+    let mut cached_points = Vec::with_capacity(cache_size);
+    let zero = chip.load_constant(ctx, FC::fe_to_constant(<FC::FieldType as ff::Field>::zero()));
+    let infinity = EcPointOrInfinity::construct(
+        EcPoint::construct(zero.clone(), zero),
+        chip.gate().load_constant(ctx, F::one()),
+    );
+    cached_points.push(infinity);
+    cached_points.push(P.clone());
+    for idx in 2..cache_size {
+        let new_point = ec_add_complete::<F, FC, C>(chip, ctx, &cached_points[idx - 1], P);
+        cached_points.push(new_point);
+    }
+
+    let select_window = |ctx: &mut Context<'v, F>, window: &[AssignedValue<'v, F>]| {
+        let coeffs = chip.gate().bits_to_indicator(ctx, window);
+        let points = cached_points.iter().map(|P| P.point.clone()).collect_vec();
+        let point = ec_select_by_indicator(chip, ctx, &points, &coeffs);
+        let is_infinity = chip.gate().select_by_indicator(
+            ctx,
+            cached_points.iter().map(|P| Existing(&P.is_infinity)),
+            &coeffs,
+        );
+        EcPointOrInfinity::construct(point, is_infinity)
+    };
+
+    let mut curr_point = select_window(ctx, &bits[rounded_bitlen - window_bits..rounded_bitlen]);
+    for idx in 1..num_windows {
+        for _ in 0..window_bits {
+            curr_point = ec_add_complete::<F, FC, C>(chip, ctx, &curr_point, &curr_point);
+        }
+        let add_point = select_window(
+            ctx,
+            &bits[rounded_bitlen - window_bits * (idx + 1)..rounded_bitlen - window_bits * idx],
+        );
+        curr_point = ec_add_complete::<F, FC, C>(chip, ctx, &curr_point, &add_point);
+    }
+    curr_point
+}
+
+/// Returns `y^2 - (x^3 + a x + b)` for `P = (x, y)` without carrying, which is `0 mod p` iff `P` is on the curve.
+pub fn curve_equation_no_carry<'v, F, FC, C>(
     chip: &FC,
     ctx: &mut Context<'v, F>,
     P: &EcPoint<F, FC::FieldPoint<'v>>,
-) where
+) -> FC::FieldPoint<'v>
+where
     F: PrimeField,
     FC: FieldChip<F>,
-    C: CurveAffine<Base = FC::FieldType>,
+    C: CurveParams<Base = FC::FieldType>,
 {
     let lhs = chip.mul_no_carry(ctx, &P.y, &P.y);
     let mut rhs = chip.mul(ctx, &P.x, &P.x);
     rhs = chip.mul_no_carry(ctx, &rhs, &P.x);
 
-    let b = FC::fe_to_constant(C::b());
+    let a = C::coeff_a();
+    if !bool::from(ff::Field::is_zero(&a)) {
+        let a = chip.load_constant(ctx, FC::fe_to_constant(a));
+        let ax = chip.mul_no_carry(ctx, &a, &P.x);
+        rhs = chip.add_no_carry(ctx, &rhs, &ax);
+    }
+    let b = FC::fe_to_constant(C::coeff_b());
     rhs = chip.add_constant_no_carry(ctx, &rhs, b);
-    let diff = chip.sub_no_carry(ctx, &lhs, &rhs);
+    chip.sub_no_carry(ctx, &lhs, &rhs)
+}
+
+pub fn is_on_curve<'v, F, FC, C>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    P: &EcPoint<F, FC::FieldPoint<'v>>,
+) where
+    F: PrimeField,
+    FC: FieldChip<F>,
+    C: CurveParams<Base = FC::FieldType>,
+{
+    let diff = curve_equation_no_carry::<F, FC, C>(chip, ctx, P);
     chip.check_carry_mod_to_zero(ctx, &diff)
 }
 
@@ -361,10 +689,9 @@ pub fn load_random_point<'v, F, FC, C>(
 where
     F: PrimeField,
     FC: FieldChip<F>,
-    C: CurveAffineExt<Base = FC::FieldType>,
+    C: CurveParams<Base = FC::FieldType>,
 {
-    let base_point: C = C::CurveExt::random(ChaCha20Rng::from_entropy()).to_affine();
-    let (x, y) = base_point.into_coordinates();
+    let (x, y) = C::random_point(ChaCha20Rng::from_entropy());
     let pt_x = FC::fe_to_witness(&Value::known(x));
     let pt_y = FC::fe_to_witness(&Value::known(y));
     let base = {
@@ -377,7 +704,7 @@ where
     base
 }
 
-// need to supply an extra generic `C` implementing `CurveAffine` trait in order to generate random witness points on the curve in question
+// need to supply an extra generic `C` implementing `CurveParams` trait in order to generate random witness points on the curve in question
 // Using Simultaneous 2^w-Ary Method, see https://www.bmoeller.de/pdf/multiexp-sac2001.pdf
 // Random Accumlation point trick learned from halo2wrong: https://hackmd.io/ncuKqRXzR-Cw-Au2fGzsMg?view
 // Input:
@@ -393,7 +720,7 @@ pub fn multi_scalar_multiply<'v, F: PrimeField, FC, C>(
 ) -> EcPoint<F, FC::FieldPoint<'v>>
 where
     FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
-    C: CurveAffineExt<Base = FC::FieldType>,
+    C: CurveParams<Base = FC::FieldType>,
 {
     let k = P.len();
     assert_eq!(k, scalars.len());
@@ -428,7 +755,7 @@ where
     let mut rand_start_vec = Vec::with_capacity(k + window_bits);
     rand_start_vec.push(base);
     for idx in 1..(k + window_bits) {
-        let base_mult = ec_double(chip, ctx, &rand_start_vec[idx - 1]);
+        let base_mult = ec_double::<F, FC, C>(chip, ctx, &rand_start_vec[idx - 1]);
         rand_start_vec.push(base_mult);
     }
     assert!(rand_start_vec.len() >= k + window_bits);
@@ -479,7 +806,7 @@ where
     // compute \sum_i x_i P_i + (2^{k + 1} - 1) * A
     for idx in 0..num_windows {
         for _ in 0..window_bits {
-            curr_point = ec_double(chip, ctx, &curr_point);
+            curr_point = ec_double::<F, FC, C>(chip, ctx, &curr_point);
         }
         for (cached_points, rounded_bits) in cached_points
             .chunks(cache_size)
@@ -608,12 +935,107 @@ impl<F: PrimeField, FC: FieldChip<F>> EccChip<F, FC> {
         EcPoint::construct(x, y)
     }
 
+    /// Loads `g`, which may be the point at infinity, and constrains it to be on the curve or the point at infinity.
+    pub fn assign_point_or_infinity<'v, C>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        g: Value<C>,
+    ) -> EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>
+    where
+        C: CurveAffineExt<Base = FC::FieldType>,
+    {
+        let (x, y) = g
+            .map(|g| {
+                if bool::from(g.is_identity()) {
+                    (<C::Base as ff::Field>::zero(), <C::Base as ff::Field>::zero())
+                } else {
+                    g.into_coordinates()
+                }
+            })
+            .unzip();
+        let P = self.load_private(ctx, (x, y));
+
+        let diff = curve_equation_no_carry::<F, FC, C>(&self.field_chip, ctx, &P);
+        let diff = self.field_chip.carry_mod(ctx, &diff);
+        let is_on_curve = self.field_chip.is_zero(ctx, &diff);
+
+        let x_is_zero = self.field_chip.is_zero(ctx, &P.x);
+        let y_is_zero = self.field_chip.is_zero(ctx, &P.y);
+        let gate = self.field_chip.range().gate();
+        let is_infinity = gate.and(ctx, Existing(&x_is_zero), Existing(&y_is_zero));
+        let is_valid = gate.or(ctx, Existing(&is_on_curve), Existing(&is_infinity));
+        gate.assert_is_const(ctx, &is_valid, F::one());
+
+        EcPointOrInfinity::construct(P, is_infinity)
+    }
+
+    /// Loads the scalar `s` as little-endian limbs of `limb_bits` bits each, constrained to be canonical.
+    ///
+    /// Assumes `limb_bits + 1` bits are supported by [`RangeInstructions::is_less_than`], e.g., `limb_bits <= 128`.
+    pub fn load_scalar<'v, SF: PrimeField>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        s: Value<SF>,
+        limb_bits: usize,
+    ) -> AssignedScalar<'v, F, SF> {
+        let num_limbs = (SF::NUM_BITS as usize + limb_bits - 1) / limb_bits;
+        let mask = (BigUint::from(1u64) << limb_bits) - 1u64;
+        let limbs = s
+            .map(|s| {
+                let s = fe_to_biguint(&s);
+                (0..num_limbs)
+                    .map(|i| biguint_to_fe::<F>(&((&s >> (limb_bits * i)) & &mask)))
+                    .collect::<Vec<_>>()
+            })
+            .transpose_vec(num_limbs);
+        let limbs = self.field_chip.gate().assign_witnesses(ctx, limbs);
+        self.assign_scalar(ctx, limbs, limb_bits)
+    }
+
+    /// Constrains `limbs` to be the little-endian limbs, of `limb_bits` bits each, of a scalar less than the modulus of `SF`.
+    ///
+    /// Assumes `limb_bits + 1` bits are supported by [`RangeInstructions::is_less_than`], e.g., `limb_bits <= 128`.
+    pub fn assign_scalar<'v, SF: PrimeField>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        limbs: Vec<AssignedValue<'v, F>>,
+        limb_bits: usize,
+    ) -> AssignedScalar<'v, F, SF> {
+        let n = modulus::<SF>();
+        assert!(
+            n.bits() as usize <= limbs.len() * limb_bits,
+            "{} limbs of {limb_bits} bits cannot hold all scalars",
+            limbs.len()
+        );
+        let range = self.field_chip.range();
+        for limb in limbs.iter() {
+            range.range_check(ctx, limb, limb_bits);
+        }
+        let mask = (BigUint::from(1u64) << limb_bits) - 1u64;
+        let n_limbs = (0..limbs.len())
+            .map(|i| {
+                let n_limb = biguint_to_fe::<F>(&((&n >> (limb_bits * i)) & &mask));
+                range.gate().load_constant(ctx, n_limb)
+            })
+            .collect();
+        let is_canonical = big_less_than::assign::<F>(
+            range,
+            ctx,
+            &OverflowInteger::construct(limbs.clone(), limb_bits),
+            &OverflowInteger::construct(n_limbs, limb_bits),
+            limb_bits,
+            range.gate().pow_of_two()[limb_bits],
+        );
+        range.gate().assert_is_const(ctx, &is_canonical, F::one());
+        AssignedScalar { limbs, limb_bits, _marker: PhantomData }
+    }
+
     pub fn load_random_point<'v, C>(
         &self,
         ctx: &mut Context<'v, F>,
     ) -> EcPoint<F, FC::FieldPoint<'v>>
     where
-        C: CurveAffineExt<Base = FC::FieldType>,
+        C: CurveParams<Base = FC::FieldType>,
     {
         load_random_point::<F, FC, C>(self.field_chip(), ctx)
     }
@@ -623,7 +1045,7 @@ impl<F: PrimeField, FC: FieldChip<F>> EccChip<F, FC> {
         ctx: &mut Context<'v, F>,
         P: &EcPoint<F, FC::FieldPoint<'v>>,
     ) where
-        C: CurveAffine<Base = FC::FieldType>,
+        C: CurveParams<Base = FC::FieldType>,
     {
         is_on_curve::<F, FC, C>(&self.field_chip, ctx, P)
     }
@@ -634,17 +1056,10 @@ impl<F: PrimeField, FC: FieldChip<F>> EccChip<F, FC> {
         P: &EcPoint<F, FC::FieldPoint<'v>>,
     ) -> AssignedValue<'v, F>
     where
-        C: CurveAffine<Base = FC::FieldType>,
-        C::Base: ff::PrimeField,
+        C: CurveParams<Base = FC::FieldType>,
     {
-        let lhs = self.field_chip.mul_no_carry(ctx, &P.y, &P.y);
-        let mut rhs = self.field_chip.mul(ctx, &P.x, &P.x);
-        rhs = self.field_chip.mul_no_carry(ctx, &rhs, &P.x);
-
-        let b = FC::fe_to_constant(C::b());
-        rhs = self.field_chip.add_constant_no_carry(ctx, &rhs, b);
-        let mut diff = self.field_chip.sub_no_carry(ctx, &lhs, &rhs);
-        diff = self.field_chip.carry_mod(ctx, &diff);
+        let diff = curve_equation_no_carry::<F, FC, C>(&self.field_chip, ctx, P);
+        let diff = self.field_chip.carry_mod(ctx, &diff);
 
         let is_on_curve = self.field_chip.is_zero(ctx, &diff);
 
@@ -691,12 +1106,15 @@ impl<F: PrimeField, FC: FieldChip<F>> EccChip<F, FC> {
         ec_sub_unequal(&self.field_chip, ctx, P, Q, is_strict)
     }
 
-    pub fn double<'v>(
+    pub fn double<'v, C>(
         &self,
         ctx: &mut Context<'v, F>,
         P: &EcPoint<F, FC::FieldPoint<'v>>,
-    ) -> EcPoint<F, FC::FieldPoint<'v>> {
-        ec_double(&self.field_chip, ctx, P)
+    ) -> EcPoint<F, FC::FieldPoint<'v>>
+    where
+        C: CurveParams<Base = FC::FieldType>,
+    {
+        ec_double::<F, FC, C>(&self.field_chip, ctx, P)
     }
 
     pub fn is_equal<'v>(
@@ -727,7 +1145,7 @@ impl<F: PrimeField, FC: FieldChip<F>> EccChip<F, FC> {
         points: impl Iterator<Item = &'b EcPoint<F, FC::FieldPoint<'v>>>,
     ) -> EcPoint<F, FC::FieldPoint<'v>>
     where
-        C: CurveAffineExt<Base = FC::FieldType>,
+        C: CurveParams<Base = FC::FieldType>,
         FC::FieldPoint<'v>: 'b,
     {
         let rand_point = self.load_random_point::<C>(ctx);
@@ -756,60 +1174,170 @@ where
         ec_select(&self.field_chip, ctx, P, Q, condition)
     }
 
-    pub fn scalar_mult<'v>(
+    pub fn select_or_infinity<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        P: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+        Q: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+        condition: &AssignedValue<'v, F>,
+    ) -> EcPointOrInfinity<'v, F, FC::FieldPoint<'v>> {
+        ec_select_or_infinity(&self.field_chip, ctx, P, Q, condition)
+    }
+
+    /// Complete addition: `P` and `Q` may be equal, negatives of each other, or the point at infinity.
+    ///
+    /// Assumes that `P` and `Q` are each on the curve or the point at infinity, and that the curve has odd order.
+    pub fn add_complete<'v, C>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        P: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+        Q: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+    ) -> EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>
+    where
+        C: CurveParams<Base = FC::FieldType>,
+    {
+        ec_add_complete::<F, FC, C>(&self.field_chip, ctx, P, Q)
+    }
+
+    /// Scalar multiplication with complete addition: `P` may be the point at infinity and `scalar` may be zero.
+    pub fn scalar_mult_complete<'v, C>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        P: &EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>,
+        scalar: &AssignedScalar<'v, F, C::ScalarField>,
+        window_bits: usize,
+    ) -> EcPointOrInfinity<'v, F, FC::FieldPoint<'v>>
+    where
+        C: CurveParams<Base = FC::FieldType>,
+    {
+        scalar_multiply_complete::<F, FC, C>(
+            &self.field_chip,
+            ctx,
+            P,
+            &scalar.limbs,
+            scalar.limb_bits,
+            window_bits,
+        )
+    }
+
+    /// Assumes that `P` has order the modulus of `C::ScalarField`, see [`scalar_multiply`]
+    pub fn scalar_mult<'v, C>(
         &self,
         ctx: &mut Context<'v, F>,
         P: &EcPoint<F, FC::FieldPoint<'v>>,
-        scalar: &Vec<AssignedValue<'v, F>>,
-        max_bits: usize,
+        scalar: &AssignedScalar<'v, F, C::ScalarField>,
         window_bits: usize,
-    ) -> EcPoint<F, FC::FieldPoint<'v>> {
-        scalar_multiply::<F, FC>(&self.field_chip, ctx, P, scalar, max_bits, window_bits)
+    ) -> EcPoint<F, FC::FieldPoint<'v>>
+    where
+        C: CurveParams<Base = FC::FieldType>,
+    {
+        scalar_multiply::<F, FC, C>(
+            &self.field_chip,
+            ctx,
+            P,
+            &scalar.limbs,
+            scalar.limb_bits,
+            window_bits,
+        )
     }
 
-    // TODO: put a check in place that scalar is < modulus of C::Scalar
+    /// Scalar multiplication using the GLV endomorphism of `C`, see [`glv::scalar_multiply_glv`].
+    ///
+    /// `scalar` must have the same limbs as `CRTInteger`s of `scalar_chip`, i.e., `scalar_chip.num_limbs` limbs of
+    /// `scalar_chip.limb_bits` bits.
+    pub fn scalar_mult_glv<'v, C>(
+        &self,
+        scalar_chip: &FpConfig<F, C::ScalarExt>,
+        ctx: &mut Context<'v, F>,
+        P: &EcPoint<F, FC::FieldPoint<'v>>,
+        scalar: &AssignedScalar<'v, F, C::ScalarExt>,
+        window_bits: usize,
+    ) -> EcPoint<F, FC::FieldPoint<'v>>
+    where
+        C: glv::GlvCurve<Base = FC::FieldType>,
+        C::ScalarExt: PrimeField,
+    {
+        assert_eq!(scalar.limb_bits, scalar_chip.limb_bits);
+        assert_eq!(scalar.limbs.len(), scalar_chip.num_limbs);
+        let native = OverflowInteger::evaluate(
+            scalar_chip.range.gate(),
+            ctx,
+            &scalar.limbs,
+            scalar_chip.limb_bases.iter().cloned(),
+        );
+        let value =
+            scalar.limbs.iter().rev().fold(Value::known(BigInt::from(0u64)), |acc, limb| {
+                acc.zip(limb.value()).map(|(acc, limb)| {
+                    (acc << scalar.limb_bits) + BigInt::from(fe_to_biguint(limb))
+                })
+            });
+        let scalar = CRTInteger::construct(
+            OverflowInteger::construct(scalar.limbs.clone(), scalar.limb_bits),
+            native,
+            value,
+        );
+        glv::scalar_multiply_glv::<F, FC, C>(
+            &self.field_chip,
+            scalar_chip,
+            ctx,
+            P,
+            &scalar,
+            window_bits,
+        )
+    }
+
+    /// All `scalars` must have the same number of limbs and `limb_bits`.
+    ///
+    /// Chooses between [`multi_scalar_multiply`] and [`pippenger::multi_exp`], with windows and clump factors of at most
+    /// `window_bits` bits, using the cost model in [`msm_cost`].
     pub fn variable_base_msm<'v, C>(
         &self,
         ctx: &mut Context<'v, F>,
         P: &[EcPoint<F, FC::FieldPoint<'v>>],
-        scalars: &[Vec<AssignedValue<'v, F>>],
-        max_bits: usize,
+        scalars: &[AssignedScalar<'v, F, C::ScalarField>],
         window_bits: usize,
     ) -> EcPoint<F, FC::FieldPoint<'v>>
     where
-        C: CurveAffineExt<Base = FC::FieldType>,
-        C::Base: ff::PrimeField,
+        C: CurveParams<Base = FC::FieldType>,
+        FC: PrimeFieldChip<F>,
+        FC::FieldType: ff::PrimeField,
     {
         #[cfg(feature = "display")]
         println!("computing length {} MSM", P.len());
 
-        if P.len() <= 25 {
-            multi_scalar_multiply::<F, FC, C>(
-                &self.field_chip,
-                ctx,
-                P,
-                scalars,
-                max_bits,
-                window_bits,
-            )
-        } else {
-            /*let mut radix = (f64::from((max_bits * scalars[0].len()) as u32)
-                / f64::from(P.len() as u32))
-            .sqrt()
-            .floor() as usize;
-            if radix == 0 {
-                radix = 1;
-            }*/
-            let radix = 1;
-            pippenger::multi_exp::<F, FC, C>(
-                &self.field_chip,
-                ctx,
-                P,
-                scalars,
-                max_bits,
-                radix,
-                window_bits,
-            )
+        let max_bits = scalars[0].limb_bits;
+        assert!(scalars.iter().all(|scalar| scalar.limb_bits == max_bits));
+        let scalars = scalars.iter().map(|scalar| scalar.limbs.clone()).collect_vec();
+        let scalars = &scalars;
+
+        let costs = self.field_chip.op_costs();
+        let (strategy, _cost) =
+            msm_cost::best_msm_strategy(&costs, P.len(), max_bits * scalars[0].len(), window_bits);
+        #[cfg(feature = "display")]
+        println!("MSM strategy {strategy:?} with estimated cost {_cost} advice cells");
+
+        match strategy {
+            msm_cost::MsmStrategy::MultiScalarMultiply { window_bits } => {
+                multi_scalar_multiply::<F, FC, C>(
+                    &self.field_chip,
+                    ctx,
+                    P,
+                    scalars,
+                    max_bits,
+                    window_bits,
+                )
+            }
+            msm_cost::MsmStrategy::Pippenger { radix, clump_factor } => {
+                pippenger::multi_exp::<F, FC, C>(
+                    &self.field_chip,
+                    ctx,
+                    P,
+                    scalars,
+                    max_bits,
+                    radix,
+                    clump_factor,
+                )
+            }
         }
     }
 }
@@ -818,13 +1346,11 @@ impl<F: PrimeField, FC: PrimeFieldChip<F>> EccChip<F, FC>
 where
     FC::FieldType: PrimeField,
 {
-    // TODO: put a check in place that scalar is < modulus of C::Scalar
     pub fn fixed_base_scalar_mult<'v, C>(
         &self,
         ctx: &mut Context<'v, F>,
         point: &C,
-        scalar: &[AssignedValue<'v, F>],
-        max_bits: usize,
+        scalar: &AssignedScalar<'v, F, C::ScalarExt>,
         window_bits: usize,
     ) -> EcPoint<F, FC::FieldPoint<'v>>
     where
@@ -836,23 +1362,68 @@ where
             &self.field_chip,
             ctx,
             point,
-            scalar,
-            max_bits,
+            &scalar.limbs,
+            scalar.limb_bits,
             window_bits,
         )
     }
 
+    /// Loads the point with SEC1 compressed encoding `bytes`, see [`sec1::load_sec1_compressed`].
+    pub fn load_sec1_compressed<'v, C>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        bytes: &[AssignedValue<'v, F>],
+    ) -> EcPoint<F, FC::FieldPoint<'v>>
+    where
+        C: CurveAffine<Base = FC::FieldType>,
+        FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    {
+        sec1::load_sec1_compressed::<F, FC, C>(&self.field_chip, ctx, bytes)
+    }
+
+    /// Returns the SEC1 encoding of `P`, see [`sec1::sec1_encode`].
+    pub fn sec1_encode<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        P: &EcPoint<F, FC::FieldPoint<'v>>,
+        compressed: bool,
+    ) -> Vec<AssignedValue<'v, F>>
+    where
+        FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    {
+        sec1::sec1_encode(&self.field_chip, ctx, P, compressed)
+    }
+
+    /// Same as [`Self::fixed_base_scalar_mult`], but looks up the window multiples of `point` in `table`, which must contain
+    /// `point` as one of its bases.
+    pub fn fixed_base_scalar_mult_lookup<'v, C>(
+        &self,
+        table: &fixed_base_lookup::FixedBaseLookupConfig<C>,
+        ctx: &mut Context<'v, F>,
+        point: &C,
+        scalar: &AssignedScalar<'v, F, C::ScalarExt>,
+    ) -> EcPoint<F, FC::FieldPoint<'v>>
+    where
+        C: CurveAffineExt,
+        FC: PrimeFieldChip<F, FieldType = C::Base, FieldPoint<'v> = CRTInteger<'v, F>>
+            + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+    {
+        let base_idx = table.base_index(point).expect("point is not a base of the lookup table");
+        table.scalar_multiply(&self.field_chip, ctx, base_idx, &scalar.limbs, scalar.limb_bits)
+    }
+
     /// `radix = 0` means auto-calculate
     ///
     /// `clump_factor = 0` means auto-calculate
     ///
     /// The user should filter out base points that are identity beforehand; we do not separately do this here
+    ///
+    /// All `scalars` must have the same number of limbs and `limb_bits`.
     pub fn fixed_base_msm<'v, C>(
         &self,
         ctx: &mut Context<'v, F>,
         points: &[C],
-        scalars: &[Vec<AssignedValue<'v, F>>],
-        max_scalar_bits_per_cell: usize,
+        scalars: &[AssignedScalar<'v, F, C::ScalarExt>],
         _radix: usize,
         clump_factor: usize,
     ) -> EcPoint<F, FC::FieldPoint<'v>>
@@ -865,7 +1436,10 @@ where
         #[cfg(feature = "display")]
         println!("computing length {} fixed base msm", points.len());
 
-        fixed_base::msm(self, ctx, points, scalars, max_scalar_bits_per_cell, clump_factor)
+        let max_scalar_bits_per_cell = scalars[0].limb_bits;
+        assert!(scalars.iter().all(|scalar| scalar.limb_bits == max_scalar_bits_per_cell));
+        let scalars = scalars.iter().map(|scalar| scalar.limbs.clone()).collect_vec();
+        fixed_base::msm(self, ctx, points, &scalars, max_scalar_bits_per_cell, clump_factor)
 
         // Empirically does not seem like pippenger is any better for fixed base msm right now, because of the cost of `select_by_indicator`
         // Cell usage becomes around comparable when `points.len() > 100`, and `clump_factor` should always be 4
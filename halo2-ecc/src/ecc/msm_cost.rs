@@ -0,0 +1,180 @@
+//! Cost model for choosing between [`multi_scalar_multiply`](super::multi_scalar_multiply) and
+//! [`pippenger::multi_exp`](super::pippenger::multi_exp), and their parameters, in a variable base MSM.
+//!
+//! The costs are estimated numbers of advice cells. They count the elliptic curve operations performed by each algorithm and
+//! weigh them by approximate costs of the underlying field operations, provided by the field chip, so they are only meant for
+//! comparing algorithms. `benches/msm.rs` prints the estimate next to the actual number of cells.
+
+/// Estimated advice cells of the field operations used in elliptic curve arithmetic, provided by
+/// [`PrimeFieldChip::op_costs`](crate::fields::PrimeFieldChip::op_costs)
+#[derive(Clone, Copy, Debug)]
+pub struct FieldOpCosts {
+    /// `add_no_carry`, `sub_no_carry`, or a scalar multiplication without carry
+    pub add: usize,
+    /// `mul_no_carry` followed by `carry_mod`, also used for `divide`
+    pub mul: usize,
+    /// `enforce_less_than`
+    pub enforce_less_than: usize,
+    /// `is_equal_unenforced`, also used for `is_zero`
+    pub is_equal: usize,
+    /// `select` of a single field element
+    pub select: usize,
+    /// each entry of `select_by_indicator` of a single field element
+    pub select_by_indicator: usize,
+}
+
+impl FieldOpCosts {
+    /// Costs of the CRT representation of [`FpConfig`](crate::fields::fp::FpConfig) with `num_limbs` limbs of `limb_bits` bits,
+    /// with vertical gates
+    pub fn new(limb_bits: usize, num_limbs: usize, lookup_bits: usize) -> Self {
+        // a range check on `bits` bits decomposes into `bits / lookup_bits` lookups with an inner product
+        let range_check = |bits: usize| 3 * ((bits + lookup_bits - 1) / lookup_bits) + 1;
+        let products = 3 * num_limbs * num_limbs;
+        // carry_mod witnesses the quotient and the output, range checks their limbs, and checks that `quot * p + out - a`
+        // carries to zero, which range checks `2 * num_limbs - 1` carries
+        let carry_mod = products
+            + 2 * num_limbs * range_check(limb_bits)
+            + (2 * num_limbs - 1) * range_check(limb_bits + 1);
+        Self {
+            // one gate per limb and for the native value
+            add: 4 * (num_limbs + 1),
+            mul: products + carry_mod,
+            // borrow of each limb with `is_less_than`
+            enforce_less_than: num_limbs * (range_check(limb_bits + 1) + 4),
+            // `is_equal` of each limb and `and` of the results
+            is_equal: num_limbs * 16,
+            // `select` of two gates per limb and for the native value
+            select: 8 * (num_limbs + 1),
+            // one step of an inner product per limb and for the native value
+            select_by_indicator: 3 * (num_limbs + 1),
+        }
+    }
+
+    /// Costs of a field element that is a single cell of the native field, as in [`NativeFieldChip`](crate::fields::native::NativeFieldChip):
+    /// every operation is a gate, there is no carrying, and `enforce_less_than` is free.
+    pub fn native() -> Self {
+        Self {
+            add: 4,
+            mul: 4,
+            enforce_less_than: 0,
+            is_equal: 12,
+            select: 8,
+            select_by_indicator: 3,
+        }
+    }
+
+    pub fn ec_double(&self) -> usize {
+        // `divide` for the slope and two multiplications, with additions and scalar multiplications in between
+        3 * self.mul + 6 * self.add
+    }
+
+    pub fn ec_add(&self) -> usize {
+        3 * self.mul + 6 * self.add
+    }
+
+    pub fn ec_add_strict(&self) -> usize {
+        self.ec_add() + self.is_equal
+    }
+
+    pub fn ec_select(&self) -> usize {
+        2 * self.select
+    }
+
+    /// `ec_select_from_bits` from `2^num_bits` points
+    pub fn ec_select_from_bits(&self, num_bits: usize) -> usize {
+        // `bits_to_indicator` and an inner product with the indicator for each limb of each coordinate
+        (1 << num_bits) * (3 + 2 * self.select_by_indicator)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MsmStrategy {
+    /// [`multi_scalar_multiply`](super::multi_scalar_multiply) with windows of `window_bits` bits
+    MultiScalarMultiply { window_bits: usize },
+    /// [`pippenger::multi_exp`](super::pippenger::multi_exp) with radix `radix` and `clump_factor`
+    Pippenger { radix: usize, clump_factor: usize },
+}
+
+/// Estimated advice cells of [`multi_scalar_multiply`](super::multi_scalar_multiply) of `num_points` points with scalars of
+/// `scalar_bits` bits.
+pub fn multi_scalar_multiply_cost(
+    costs: &FieldOpCosts,
+    num_points: usize,
+    scalar_bits: usize,
+    window_bits: usize,
+) -> usize {
+    let k = num_points;
+    let num_windows = (scalar_bits + window_bits - 1) / window_bits;
+    let cache_size = 1 << window_bits;
+    // random base points and the starting point
+    let setup = (k + window_bits) * costs.ec_double() + (k + 1) * costs.ec_add();
+    // cached_points, with a check whether each point is the point at infinity
+    let cache = k * (costs.is_equal + 2 * costs.enforce_less_than + costs.ec_add())
+        + k * (cache_size - 1)
+            * (costs.ec_add_strict() + costs.ec_select() + costs.enforce_less_than);
+    let windows = num_windows * window_bits * costs.ec_double()
+        + num_windows
+            * k
+            * (costs.ec_select_from_bits(window_bits)
+                + costs.enforce_less_than
+                + costs.ec_add_strict());
+    setup + cache + windows + costs.ec_add_strict()
+}
+
+/// Estimated advice cells of [`pippenger::multi_exp`](super::pippenger::multi_exp) of `num_points` points with scalars of
+/// `scalar_bits` bits.
+pub fn pippenger_cost(
+    costs: &FieldOpCosts,
+    num_points: usize,
+    scalar_bits: usize,
+    radix: usize,
+    clump_factor: usize,
+) -> usize {
+    let c = clump_factor;
+    // `decompose` multiplies each point by powers of `2^radix`, selecting the point at infinity around each doubling
+    let num_new_points = num_points * radix;
+    let mut decompose = 0;
+    if radix > 1 {
+        decompose = num_points
+            * (costs.enforce_less_than + (radix - 1) * (costs.ec_double() + 2 * costs.ec_select()));
+    }
+    let t = (scalar_bits + radix - 1) / radix;
+    let num_rounds = (num_new_points + c - 1) / c;
+    // each round builds a bucket of all `2^c` subset sums and adds one of them to each of the `t` accumulators
+    let buckets = num_rounds * (costs.ec_double() + costs.enforce_less_than)
+        + num_new_points * (costs.is_equal + costs.enforce_less_than)
+        + num_rounds
+            * ((1 << c) - 1)
+            * (costs.ec_add_strict() + costs.ec_select() + costs.enforce_less_than);
+    let accumulate = num_rounds * t * costs.ec_select_from_bits(c)
+        + (num_rounds - 1) * t * (costs.ec_add_strict() + costs.enforce_less_than);
+    let mut combine =
+        (t - 1) * (2 * radix * costs.ec_double() + costs.ec_add_strict() + costs.enforce_less_than);
+    if radix != 1 {
+        combine += (t - 1) * costs.ec_add();
+    }
+    decompose + buckets + accumulate + combine + 2 * costs.ec_add() + costs.ec_add_strict()
+}
+
+/// Returns the strategy with the fewest estimated advice cells for an MSM of `num_points` points with scalars of
+/// `scalar_bits` bits, among windows and clump factors of at most `max_window_bits` bits and all radices, together with its
+/// estimated cost.
+pub fn best_msm_strategy(
+    costs: &FieldOpCosts,
+    num_points: usize,
+    scalar_bits: usize,
+    max_window_bits: usize,
+) -> (MsmStrategy, usize) {
+    assert!(num_points > 0 && scalar_bits > 0 && max_window_bits > 0);
+    let straus = (1..=max_window_bits).map(|window_bits| {
+        let cost = multi_scalar_multiply_cost(costs, num_points, scalar_bits, window_bits);
+        (MsmStrategy::MultiScalarMultiply { window_bits }, cost)
+    });
+    let pippenger = (1..=scalar_bits).flat_map(|radix| {
+        (1..=max_window_bits).map(move |clump_factor| {
+            let cost = pippenger_cost(costs, num_points, scalar_bits, radix, clump_factor);
+            (MsmStrategy::Pippenger { radix, clump_factor }, cost)
+        })
+    });
+    straus.chain(pippenger).min_by_key(|(_, cost)| *cost).unwrap()
+}
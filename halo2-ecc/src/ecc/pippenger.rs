@@ -1,13 +1,9 @@
 use super::{
     ec_add_unequal, ec_double, ec_select, ec_select_from_bits, ec_sub_unequal, load_random_point,
-    EcPoint,
+    CurveParams, EcPoint,
 };
 use crate::fields::{FieldChip, Selectable};
-use halo2_base::{
-    gates::GateInstructions,
-    utils::{CurveAffineExt, PrimeField},
-    AssignedValue, Context,
-};
+use halo2_base::{gates::GateInstructions, utils::PrimeField, AssignedValue, Context};
 
 // Reference: https://jbootle.github.io/Misc/pippenger.pdf
 
@@ -15,7 +11,8 @@ use halo2_base::{
 // Output:
 // * new_points: length `points.len() * radix`
 // * new_bool_scalars: 2d array `ceil(scalar_bits / radix)` by `points.len() * radix`
-pub fn decompose<'v, F, FC>(
+// Points at infinity, represented by (0, 0), are allowed and stay (0, 0) after doubling
+pub fn decompose<'v, F, FC, C>(
     chip: &FC,
     ctx: &mut Context<'v, F>,
     points: &[EcPoint<F, FC::FieldPoint<'v>>],
@@ -25,7 +22,8 @@ pub fn decompose<'v, F, FC>(
 ) -> (Vec<EcPoint<F, FC::FieldPoint<'v>>>, Vec<Vec<AssignedValue<'v, F>>>)
 where
     F: PrimeField,
-    FC: FieldChip<F>,
+    FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
+    C: CurveParams<Base = FC::FieldType>,
 {
     assert_eq!(points.len(), scalars.len());
     let scalar_bits = max_scalar_bits_per_cell * scalars[0].len();
@@ -35,14 +33,24 @@ where
     let mut new_bool_scalars = vec![Vec::with_capacity(radix * points.len()); t];
 
     let zero_cell = chip.gate().load_zero(ctx);
+    // any point with nonzero y coordinate, doubled in place of the point at infinity
+    let dummy = (radix > 1).then(|| {
+        let one = chip.load_constant(ctx, FC::fe_to_constant(<FC::FieldType as ff::Field>::one()));
+        EcPoint::construct(one.clone(), one)
+    });
     for (point, scalar) in points.iter().zip(scalars.iter()) {
         assert_eq!(scalars[0].len(), scalar.len());
-        let mut g = point.clone();
-        new_points.push(g);
-        for _ in 1..radix {
-            // if radix > 1, this does not work if `points` contains identity point
-            g = ec_double(chip, ctx, new_points.last().unwrap());
-            new_points.push(g);
+        new_points.push(point.clone());
+        if let Some(dummy) = &dummy {
+            // the point at infinity (0, 0) cannot be doubled, so we double `dummy` instead and select (0, 0) afterwards
+            let is_infinity = chip.is_zero(ctx, &point.y);
+            for _ in 1..radix {
+                let g = new_points.last().unwrap();
+                let g_or_dummy = ec_select(chip, ctx, dummy, g, &is_infinity);
+                let double = ec_double::<F, FC, C>(chip, ctx, &g_or_dummy);
+                let g = ec_select(chip, ctx, point, &double, &is_infinity);
+                new_points.push(g);
+            }
         }
         let mut bits = Vec::with_capacity(scalar_bits);
         for x in scalar {
@@ -71,7 +79,7 @@ pub fn multi_product<'v, F: PrimeField, FC, C>(
 ) -> (Vec<EcPoint<F, FC::FieldPoint<'v>>>, EcPoint<F, FC::FieldPoint<'v>>)
 where
     FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
-    C: CurveAffineExt<Base = FC::FieldType>,
+    C: CurveParams<Base = FC::FieldType>,
 {
     let c = clumping_factor; // this is `b` in Section 3 of Bootle
 
@@ -91,7 +99,7 @@ where
         // for later addition collision-prevension, we need a different random point per round
         // we take 2^round * rand_base
         if round > 0 {
-            rand_point = ec_double(chip, ctx, &rand_point);
+            rand_point = ec_double::<F, FC, C>(chip, ctx, &rand_point);
         }
         // stores { rand_point, rand_point + points[0], rand_point + points[1], rand_point + points[0] + points[1] , ... }
         // since rand_point is random, we can always use add_unequal (with strict constraint checking that the points are indeed unequal and not negative of each other)
@@ -132,7 +140,7 @@ where
     }
 
     // we have acc[j] = G'[j] + (2^num_rounds - 1) * rand_base
-    rand_point = ec_double(chip, ctx, &rand_point);
+    rand_point = ec_double::<F, FC, C>(chip, ctx, &rand_point);
     rand_point = ec_sub_unequal(chip, ctx, &rand_point, &rand_base, false);
 
     (acc, rand_point)
@@ -149,10 +157,10 @@ pub fn multi_exp<'v, F: PrimeField, FC, C>(
 ) -> EcPoint<F, FC::FieldPoint<'v>>
 where
     FC: FieldChip<F> + Selectable<F, Point<'v> = FC::FieldPoint<'v>>,
-    C: CurveAffineExt<Base = FC::FieldType>,
+    C: CurveParams<Base = FC::FieldType>,
 {
     let (points, bool_scalars) =
-        decompose::<F, _>(chip, ctx, points, scalars, max_scalar_bits_per_cell, radix);
+        decompose::<F, _, C>(chip, ctx, points, scalars, max_scalar_bits_per_cell, radix);
 
     /*
     let t = bool_scalars.len();
@@ -182,8 +190,8 @@ where
     let mut rand_sum = rand_point.clone();
     for g in agg.iter().rev() {
         for _ in 0..radix {
-            sum = ec_double(chip, ctx, &sum);
-            rand_sum = ec_double(chip, ctx, &rand_sum);
+            sum = ec_double::<F, FC, C>(chip, ctx, &sum);
+            rand_sum = ec_double::<F, FC, C>(chip, ctx, &rand_sum);
         }
         sum = ec_add_unequal(chip, ctx, &sum, g, true);
         chip.enforce_less_than(ctx, sum.x());
@@ -195,7 +203,7 @@ where
     }
 
     if radix == 1 {
-        rand_sum = ec_double(chip, ctx, &rand_sum);
+        rand_sum = ec_double::<F, FC, C>(chip, ctx, &rand_sum);
         // assume 2^t != +-1 mod modulus::<F>()
         rand_sum = ec_sub_unequal(chip, ctx, &rand_sum, &rand_point, false);
     }
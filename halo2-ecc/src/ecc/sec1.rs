@@ -0,0 +1,239 @@
+#![allow(non_snake_case)]
+//! SEC1 encoding of points on curves over prime fields, see section 2.3.3 of https://www.secg.org/sec1-v2.pdf
+//!
+//! A coordinate is encoded as its `ceil(NUM_BITS / 8)` big-endian bytes, and a point `(x, y)` as `0x04 || x || y`
+//! uncompressed, or as `0x02 || x` if `y` is even and `0x03 || x` if `y` is odd compressed. The point at infinity is not
+//! supported.
+use super::{is_on_curve, EcPoint};
+use crate::bigint::{CRTInteger, OverflowInteger};
+use crate::fields::PrimeFieldChip;
+use crate::halo2_proofs::{arithmetic::CurveAffine, circuit::Value};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::{biguint_to_fe, fe_to_biguint, PrimeField},
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use itertools::Itertools;
+use num_bigint::BigInt;
+
+/// The number of bytes in the encoding of an element of `FT`
+pub fn num_coordinate_bytes<FT: PrimeField>() -> usize {
+    (FT::NUM_BITS as usize + 7) / 8
+}
+
+/// Returns the big-endian bytes of `a`, each constrained to be a byte, and constrains `a` to be reduced.
+///
+/// Assumes `a` has at most `limb_bits` bits in each limb, which is true for a `FieldPoint` returned by `load_private` or `carry_mod`.
+pub fn field_to_bytes_be<'v, F, FC>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    a: &CRTInteger<'v, F>,
+) -> Vec<AssignedValue<'v, F>>
+where
+    F: PrimeField,
+    FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    FC::FieldType: PrimeField,
+{
+    let num_bytes = num_coordinate_bytes::<FC::FieldType>();
+    let limb_bits = chip.limb_bits();
+    let gate = chip.gate();
+    // little-endian bits of `a`, the bits past `8 * num_bytes` are constrained to be zero
+    let mut bits = Vec::with_capacity(8 * num_bytes);
+    for (i, limb) in a.truncation.limbs.iter().enumerate() {
+        let num_bits = (8 * num_bytes).saturating_sub(limb_bits * i).min(limb_bits);
+        if num_bits == 0 {
+            gate.assert_is_const(ctx, limb, F::zero());
+        } else {
+            bits.extend(gate.num_to_bits(ctx, limb, num_bits));
+        }
+    }
+    while bits.len() < 8 * num_bytes {
+        bits.push(gate.load_zero(ctx));
+    }
+    chip.enforce_less_than(ctx, a);
+
+    let mut bytes = bits
+        .chunks(8)
+        .map(|byte_bits| {
+            gate.inner_product(
+                ctx,
+                byte_bits.iter().map(Existing),
+                gate.pow_of_two()[..8].iter().map(|c| Constant(*c)),
+            )
+        })
+        .collect_vec();
+    bytes.reverse();
+    bytes
+}
+
+/// Returns the field element with big-endian bytes `bytes`, constrained to be reduced, and constrains each of `bytes` to be a byte.
+pub fn field_from_bytes_be<'v, F, FC>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    bytes: &[AssignedValue<'v, F>],
+) -> CRTInteger<'v, F>
+where
+    F: PrimeField,
+    FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    FC::FieldType: PrimeField,
+{
+    assert_eq!(bytes.len(), num_coordinate_bytes::<FC::FieldType>());
+    let limb_bits = chip.limb_bits();
+    let gate = chip.gate();
+    let bits = bytes.iter().rev().flat_map(|byte| gate.num_to_bits(ctx, byte, 8)).collect_vec();
+    assert!(bits.len() <= limb_bits * chip.num_limbs());
+
+    let limbs = (0..chip.num_limbs())
+        .map(|i| {
+            let chunk =
+                &bits[(limb_bits * i).min(bits.len())..(limb_bits * (i + 1)).min(bits.len())];
+            if chunk.is_empty() {
+                gate.load_zero(ctx)
+            } else {
+                gate.inner_product(
+                    ctx,
+                    chunk.iter().map(Existing),
+                    gate.pow_of_two()[..chunk.len()].iter().map(|c| Constant(*c)),
+                )
+            }
+        })
+        .collect_vec();
+    let native = OverflowInteger::evaluate(gate, ctx, &limbs, chip.limb_bases().iter().cloned());
+    let value = bytes.iter().fold(Value::known(BigInt::from(0u64)), |acc, byte| {
+        acc.zip(byte.value()).map(|(acc, byte)| (acc << 8) + BigInt::from(fe_to_biguint(byte)))
+    });
+    let a = CRTInteger::construct(OverflowInteger::construct(limbs, limb_bits), native, value);
+    chip.enforce_less_than(ctx, &a);
+    a
+}
+
+// Constrains `a` to be reduced and returns its lowest bit, assuming each limb of `a` has at most `limb_bits` bits.
+fn parity<'v, F, FC>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    a: &CRTInteger<'v, F>,
+) -> AssignedValue<'v, F>
+where
+    F: PrimeField,
+    FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    FC::FieldType: PrimeField,
+{
+    chip.enforce_less_than(ctx, a);
+    let gate = chip.gate();
+    // a_0 = 2 * half + bit with half < 2^{limb_bits - 1}
+    let limb = &a.truncation.limbs[0];
+    let bit = gate.load_witness(ctx, limb.value().map(|a| F::from(fe_to_biguint(a).bit(0) as u64)));
+    gate.assert_bit(ctx, &bit);
+    let half =
+        gate.load_witness(ctx, limb.value().map(|a| biguint_to_fe::<F>(&(fe_to_biguint(a) >> 1))));
+    chip.range().range_check(ctx, &half, chip.limb_bits() - 1);
+    let limb_again = gate.mul_add(ctx, Existing(&half), Constant(F::from(2)), Existing(&bit));
+    ctx.constrain_equal(limb, &limb_again);
+    bit
+}
+
+/// Returns the point `(x, y)` on `C` with `y` odd if `y_is_odd` is 1 and even if it is 0, where `y` is witnessed as a square root
+/// of `x^3 + a x + b` and constrained by squaring.
+///
+/// The constraints fail if `x` is not the x-coordinate of a point on `C` or `y_is_odd` is not a bit. `x` need not be reduced.
+pub fn decompress_point<'v, F, FC, C>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    x: &CRTInteger<'v, F>,
+    y_is_odd: &AssignedValue<'v, F>,
+) -> EcPoint<F, FC::FieldPoint<'v>>
+where
+    F: PrimeField,
+    FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    FC::FieldType: PrimeField,
+    C: CurveAffine<Base = FC::FieldType>,
+{
+    let y = chip.get_assigned_value(x).zip(y_is_odd.value()).map(|(x, y_is_odd)| {
+        // if `x^3 + a x + b` is not a square, any witness fails the constraints below
+        let rhs = x * x * x + C::a() * x + C::b();
+        let y = <C::Base as ff::Field>::sqrt(&rhs).unwrap_or_else(<C::Base as ff::Field>::zero);
+        if fe_to_biguint(&y).bit(0) == (*y_is_odd == F::one()) {
+            y
+        } else {
+            -y
+        }
+    });
+    let y = chip.load_private(ctx, FC::fe_to_witness(&y));
+    let P = EcPoint::construct(x.clone(), y);
+    is_on_curve::<F, FC, C>(chip, ctx, &P);
+    let y_parity = parity(chip, ctx, &P.y);
+    ctx.constrain_equal(&y_parity, y_is_odd);
+    P
+}
+
+/// Loads the point with SEC1 compressed encoding `bytes`.
+///
+/// Constrains each of `bytes` to be a byte, the first byte to be `0x02` or `0x03`, and the encoded `x` to be the reduced
+/// x-coordinate of a point on `C`.
+pub fn load_sec1_compressed<'v, F, FC, C>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    bytes: &[AssignedValue<'v, F>],
+) -> EcPoint<F, FC::FieldPoint<'v>>
+where
+    F: PrimeField,
+    FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    FC::FieldType: PrimeField,
+    C: CurveAffine<Base = FC::FieldType>,
+{
+    assert_eq!(bytes.len(), 1 + num_coordinate_bytes::<C::Base>());
+    // `decompress_point` constrains `prefix - 2` to be a bit
+    let y_is_odd = chip.gate().sub(ctx, Existing(&bytes[0]), Constant(F::from(2)));
+    let x = field_from_bytes_be(chip, ctx, &bytes[1..]);
+    decompress_point::<F, FC, C>(chip, ctx, &x, &y_is_odd)
+}
+
+/// Returns the SEC1 encoding of `P`, compressed if `compressed` is true, with each byte constrained to be a byte.
+///
+/// Constrains the coordinates of `P` to be reduced. Assumes `P` is a point on the curve whose coordinates have at most
+/// `limb_bits` bits in each limb, which is true for points returned by `load_private` and the elliptic curve operations.
+pub fn sec1_encode<'v, F, FC>(
+    chip: &FC,
+    ctx: &mut Context<'v, F>,
+    P: &EcPoint<F, FC::FieldPoint<'v>>,
+    compressed: bool,
+) -> Vec<AssignedValue<'v, F>>
+where
+    F: PrimeField,
+    FC: PrimeFieldChip<F, FieldPoint<'v> = CRTInteger<'v, F>>,
+    FC::FieldType: PrimeField,
+{
+    let x_bytes = field_to_bytes_be(chip, ctx, &P.x);
+    if compressed {
+        let y_is_odd = parity(chip, ctx, &P.y);
+        let prefix = chip.gate().add(ctx, Existing(&y_is_odd), Constant(F::from(2)));
+        std::iter::once(prefix).chain(x_bytes).collect()
+    } else {
+        let prefix = chip.gate().load_constant(ctx, F::from(4));
+        let y_bytes = field_to_bytes_be(chip, ctx, &P.y);
+        std::iter::once(prefix).chain(x_bytes).chain(y_bytes).collect()
+    }
+}
+
+/// Returns the SEC1 encoding of `P` outside of the circuit, compressed if `compressed` is true. `P` must not be the point at infinity.
+pub fn sec1_encode_native<C>(P: &C, compressed: bool) -> Vec<u8>
+where
+    C: CurveAffine,
+    C::Base: PrimeField,
+{
+    let coordinates = P.coordinates().unwrap();
+    let to_bytes = |a: &C::Base| {
+        let bytes = fe_to_biguint(a).to_bytes_be();
+        let mut padded = vec![0u8; num_coordinate_bytes::<C::Base>() - bytes.len()];
+        padded.extend(bytes);
+        padded
+    };
+    let y = to_bytes(coordinates.y());
+    if compressed {
+        let prefix = 2 + y.last().unwrap() % 2;
+        std::iter::once(prefix).chain(to_bytes(coordinates.x())).collect()
+    } else {
+        std::iter::once(4).chain(to_bytes(coordinates.x())).chain(y).collect()
+    }
+}
@@ -8,13 +8,14 @@ use crate::halo2_proofs::{
     halo2curves::bn256::{Fq, Fr, G1Affine, G2Affine, G1, G2},
     plonk::*,
 };
-use group::Group;
-use halo2_base::utils::bigint_to_fe;
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Group};
+use halo2_base::utils::{bigint_to_fe, biguint_to_fe};
 use halo2_base::SKIP_FIRST_PASS;
 use halo2_base::{
     gates::range::RangeStrategy, utils::value_to_option, utils::PrimeField, ContextParams,
 };
-use num_bigint::{BigInt, RandBigInt};
+use num_bigint::{BigInt, BigUint, RandBigInt};
 use std::marker::PhantomData;
 use std::ops::Neg;
 
@@ -111,7 +112,7 @@ impl<F: PrimeField> Circuit<F> for MyCircuit<F> {
 
                 // test double
                 {
-                    let doub = chip.double(ctx, &P_assigned);
+                    let doub = chip.double::<G1Affine>(ctx, &P_assigned);
                     assert_eq!(
                         value_to_option(doub.x.truncation.to_bigint(config.limb_bits)),
                         value_to_option(doub.x.value.clone())
@@ -159,6 +160,611 @@ fn test_ecc() {
     prover.assert_satisfied();
 }
 
+/// Lookup tables configured next to the `FpConfig` of an [`EccTestCircuit`]
+trait EccTestTable: Clone {
+    fn configure_table(meta: &mut ConstraintSystem<Fr>) -> Self;
+    fn load_table(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error>;
+    fn finalize_table(&self, ctx: &mut Context<'_, Fr>);
+}
+
+impl EccTestTable for () {
+    fn configure_table(_: &mut ConstraintSystem<Fr>) -> Self {}
+
+    fn load_table(&self, _: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn finalize_table(&self, _: &mut Context<'_, Fr>) {}
+}
+
+// A circuit with `2^K` rows that runs `f` in a single region with an `EccChip` over `FpConfig<Fr, Fp>` with 3 limbs of 88 bits
+// and `K - 1` lookup bits, and the lookup tables `T`. The witnesses are the values captured by `f`.
+struct EccTestCircuit<Fp, T, Func, const K: u32> {
+    f: Func,
+    _marker: PhantomData<(Fp, T)>,
+}
+
+impl<Fp, T, Func, const K: u32> Circuit<Fr> for EccTestCircuit<Fp, T, Func, K>
+where
+    Fp: PrimeField,
+    T: EccTestTable,
+    Func: Fn(&EccChip<Fr, FpConfig<Fr, Fp>>, &T, &mut Context<'_, Fr>) + Clone,
+{
+    type Config = (FpConfig<Fr, Fp>, T);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // these circuits are only run by `MockProver`, which never synthesizes without witnesses
+        Self { f: self.f.clone(), _marker: PhantomData }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let fp_chip = FpConfig::<Fr, _>::configure(
+            meta,
+            FpStrategy::Simple,
+            &[NUM_ADVICE],
+            &[1],
+            NUM_FIXED,
+            K as usize - 1,
+            88,
+            3,
+            modulus::<Fp>(),
+            0,
+            K as usize,
+        );
+        (fp_chip, T::configure_table(meta))
+    }
+
+    fn synthesize(
+        &self,
+        (config, table): Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        config.load_lookup_table(&mut layouter)?;
+        table.load_table(&mut layouter)?;
+        let chip = EccChip::construct(config);
+
+        let mut first_pass = SKIP_FIRST_PASS;
+
+        layouter.assign_region(
+            || "ecc test",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = chip.field_chip().new_context(region);
+                let ctx = &mut aux;
+
+                (self.f)(&chip, &table, ctx);
+
+                chip.field_chip.finalize(ctx);
+                table.finalize_table(ctx);
+                Ok(())
+            },
+        )
+    }
+}
+
+// Runs `f` with the ECC chip over `Fp` in a `MockProver` with `2^K` rows
+fn mock_ecc<Fp: PrimeField, const K: u32>(
+    f: impl Fn(&EccChip<Fr, FpConfig<Fr, Fp>>, &mut Context<'_, Fr>) + Clone,
+) -> MockProver<Fr> {
+    mock_ecc_with_table::<Fp, (), K>(move |chip, _, ctx| f(chip, ctx))
+}
+
+// Runs `f` with the ECC chip over `Fp` and the lookup tables `T` in a `MockProver` with `2^K` rows
+fn mock_ecc_with_table<Fp: PrimeField, T: EccTestTable, const K: u32>(
+    f: impl Fn(&EccChip<Fr, FpConfig<Fr, Fp>>, &T, &mut Context<'_, Fr>) + Clone,
+) -> MockProver<Fr> {
+    let circuit = EccTestCircuit::<Fp, T, _, K> { f, _marker: PhantomData };
+    MockProver::run(K, &circuit, vec![]).unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn test_ecc_complete() {
+    let mut rng = rand::thread_rng();
+    // the second `P` is the point at infinity
+    for P in [G1Affine::random(&mut rng), G1Affine::identity()] {
+        let Q = G1Affine::random(&mut rng);
+        let scalar: u32 = rand::random();
+        mock_ecc::<Fq, 18>(|chip, ctx| {
+            let gate = chip.field_chip.range().gate();
+            let [P_assigned, Q_assigned] =
+                [P, Q].map(|P| chip.assign_point_or_infinity::<G1Affine>(ctx, Value::known(P)));
+            let neg_P = EcPointOrInfinity::construct(
+                chip.negate(ctx, &P_assigned.point),
+                P_assigned.is_infinity.clone(),
+            );
+            let scalar_assigned = gate.load_witness(ctx, Value::known(Fr::from(scalar as u64)));
+            let zero = gate.load_constant(ctx, Fr::zero());
+
+            let check = |R: &EcPointOrInfinity<Fr, CRTInteger<Fr>>, expected: G1| {
+                let expected = G1Affine::from(expected);
+                let is_infinity = bool::from(expected.is_identity());
+                let (x, y) =
+                    if is_infinity { (Fq::zero(), Fq::zero()) } else { (expected.x, expected.y) };
+                R.point.x.value.as_ref().assert_if_known(|v| bigint_to_fe::<Fq>(v) == x);
+                R.point.y.value.as_ref().assert_if_known(|v| bigint_to_fe::<Fq>(v) == y);
+                R.is_infinity.value().assert_if_known(|v| **v == Fr::from(is_infinity as u64));
+            };
+            let (p, q) = (G1::from(P), G1::from(Q));
+
+            let sum = chip.add_complete::<G1Affine>(ctx, &P_assigned, &Q_assigned);
+            check(&sum, p + q);
+            let double = chip.add_complete::<G1Affine>(ctx, &P_assigned, &P_assigned);
+            check(&double, p.double());
+            let zero_sum = chip.add_complete::<G1Affine>(ctx, &P_assigned, &neg_P);
+            check(&zero_sum, G1::identity());
+            let infinity_sum = chip.add_complete::<G1Affine>(ctx, &zero_sum, &Q_assigned);
+            check(&infinity_sum, q);
+
+            // 32 bit scalars keep the circuit small, `EccChip::scalar_mult_complete` takes a full `AssignedScalar`
+            let P_mul = scalar_multiply_complete::<Fr, _, G1Affine>(
+                chip.field_chip(),
+                ctx,
+                &P_assigned,
+                &[scalar_assigned],
+                32,
+                4,
+            );
+            check(&P_mul, p * Fr::from(scalar as u64));
+            let P_mul_zero = scalar_multiply_complete::<Fr, _, G1Affine>(
+                chip.field_chip(),
+                ctx,
+                &P_assigned,
+                &[zero],
+                32,
+                4,
+            );
+            check(&P_mul_zero, G1::identity());
+        })
+        .assert_satisfied();
+    }
+}
+
+// Loads `scalar` as two limbs of 128 bits, without reducing mod the order of `Fr`
+fn mock_assigned_scalar(scalar: &BigUint) -> MockProver<Fr> {
+    mock_ecc::<Fq, 11>(|chip, ctx| {
+        let mask = (BigUint::from(1u64) << 128) - 1u64;
+        let limbs =
+            [scalar & &mask, scalar >> 128].map(|limb| Value::known(biguint_to_fe::<Fr>(&limb)));
+        let limbs = chip.field_chip.range().gate().assign_witnesses(ctx, limbs);
+        let assigned = chip.assign_scalar::<Fr>(ctx, limbs, 128);
+        assert_eq!(assigned.limbs().len(), 2);
+
+        // loading the same value as a field element gives the same limbs
+        if scalar < &modulus::<Fr>() {
+            let loaded = chip.load_scalar(ctx, Value::known(biguint_to_fe::<Fr>(scalar)), 128);
+            for (a, b) in assigned.limbs().iter().zip(loaded.limbs()) {
+                ctx.constrain_equal(a, b);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+#[test]
+fn test_assigned_scalar() {
+    let n = modulus::<Fr>();
+    for scalar in [BigUint::from(0u64), BigUint::from(12345u64), &n - 1u64] {
+        mock_assigned_scalar(&scalar).assert_satisfied();
+    }
+    // non-canonical representations of 0 and 5
+    for scalar in [n.clone(), &n + 5u64] {
+        assert!(mock_assigned_scalar(&scalar).verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_pippenger_with_infinity() {
+    let mut rng = rand::thread_rng();
+    for radix in [1, 2, 3] {
+        let P = G1Affine::random(&mut rng);
+        let scalars: [u32; 2] = rand::random();
+        mock_ecc::<Fq, 18>(|chip, ctx| {
+            // the second point is the point at infinity
+            let points = [P, G1Affine::identity()]
+                .map(|P| chip.assign_point_or_infinity::<G1Affine>(ctx, Value::known(P)).point);
+            let scalars_assigned = scalars.map(|s| {
+                vec![chip
+                    .field_chip
+                    .range()
+                    .gate()
+                    .load_witness(ctx, Value::known(Fr::from(s as u64)))]
+            });
+            let msm = pippenger::multi_exp::<Fr, _, G1Affine>(
+                chip.field_chip(),
+                ctx,
+                &points,
+                &scalars_assigned,
+                32,
+                radix,
+                2,
+            );
+
+            let expected = chip
+                .assign_point(ctx, Value::known(G1Affine::from(P * Fr::from(scalars[0] as u64))));
+            chip.assert_equal(ctx, &msm, &expected);
+        })
+        .assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_msm_cost_model() {
+    use msm_cost::{best_msm_strategy, FieldOpCosts, MsmStrategy};
+
+    let costs = FieldOpCosts::new(88, 3, 18);
+    // few points: the window method, with the largest window
+    let (strategy, _) = best_msm_strategy(&costs, 2, 256, 4);
+    assert_eq!(strategy, MsmStrategy::MultiScalarMultiply { window_bits: 4 });
+    // many points: Pippenger's algorithm
+    let (strategy, cost) = best_msm_strategy(&costs, 1000, 256, 4);
+    assert!(matches!(strategy, MsmStrategy::Pippenger { .. }));
+    assert!(cost < msm_cost::multi_scalar_multiply_cost(&costs, 1000, 256, 4));
+}
+
+#[cfg(feature = "display")]
+const MSM_COST_K: u32 = 18;
+#[cfg(feature = "display")]
+const MSM_COST_SCALAR_BITS: usize = 32;
+
+// Runs an MSM with `strategy` and returns the advice cells it uses
+#[cfg(feature = "display")]
+fn msm_cost_cells(points: &[G1Affine], scalars: &[u32], strategy: msm_cost::MsmStrategy) -> usize {
+    let cells = std::cell::Cell::new(None);
+    mock_ecc::<Fq, MSM_COST_K>(|chip, ctx| {
+        let points = points
+            .iter()
+            .map(|P| chip.assign_point::<G1Affine>(ctx, Value::known(*P)))
+            .collect_vec();
+        let scalars = scalars
+            .iter()
+            .map(|s| {
+                vec![chip
+                    .field_chip
+                    .range()
+                    .gate()
+                    .load_witness(ctx, Value::known(Fr::from(*s as u64)))]
+            })
+            .collect_vec();
+
+        let cells_before = ctx.stats().advice_cells[0];
+        match strategy {
+            msm_cost::MsmStrategy::MultiScalarMultiply { window_bits } => {
+                multi_scalar_multiply::<Fr, _, G1Affine>(
+                    chip.field_chip(),
+                    ctx,
+                    &points,
+                    &scalars,
+                    MSM_COST_SCALAR_BITS,
+                    window_bits,
+                );
+            }
+            msm_cost::MsmStrategy::Pippenger { radix, clump_factor } => {
+                pippenger::multi_exp::<Fr, _, G1Affine>(
+                    chip.field_chip(),
+                    ctx,
+                    &points,
+                    &scalars,
+                    MSM_COST_SCALAR_BITS,
+                    radix,
+                    clump_factor,
+                );
+            }
+        }
+        cells.set(Some(ctx.stats().advice_cells[0] - cells_before));
+    })
+    .assert_satisfied();
+    cells.get().expect("the MSM should be synthesized")
+}
+
+#[cfg(feature = "display")]
+#[test]
+fn test_msm_cost_model_matches_stats() {
+    use msm_cost::{multi_scalar_multiply_cost, pippenger_cost, FieldOpCosts, MsmStrategy};
+
+    let mut rng = rand::thread_rng();
+    let costs = FieldOpCosts::new(88, 3, MSM_COST_K as usize - 1);
+    for (num_points, strategies) in [
+        (
+            2,
+            vec![
+                MsmStrategy::MultiScalarMultiply { window_bits: 2 },
+                MsmStrategy::MultiScalarMultiply { window_bits: 3 },
+                MsmStrategy::Pippenger { radix: 1, clump_factor: 2 },
+            ],
+        ),
+        (
+            4,
+            vec![
+                MsmStrategy::MultiScalarMultiply { window_bits: 3 },
+                MsmStrategy::Pippenger { radix: 1, clump_factor: 2 },
+                MsmStrategy::Pippenger { radix: 2, clump_factor: 3 },
+            ],
+        ),
+        (
+            8,
+            vec![
+                MsmStrategy::MultiScalarMultiply { window_bits: 3 },
+                MsmStrategy::Pippenger { radix: 1, clump_factor: 3 },
+                MsmStrategy::Pippenger { radix: 1, clump_factor: 4 },
+            ],
+        ),
+    ] {
+        let points = (0..num_points).map(|_| G1Affine::random(&mut rng)).collect_vec();
+        let scalars: Vec<u32> = (0..num_points).map(|_| rand::random()).collect_vec();
+        // (strategy, estimated cells, used cells)
+        let measured = strategies
+            .into_iter()
+            .map(|strategy| {
+                let cells = msm_cost_cells(&points, &scalars, strategy);
+                let estimate = match strategy {
+                    MsmStrategy::MultiScalarMultiply { window_bits } => multi_scalar_multiply_cost(
+                        &costs,
+                        num_points,
+                        MSM_COST_SCALAR_BITS,
+                        window_bits,
+                    ),
+                    MsmStrategy::Pippenger { radix, clump_factor } => pippenger_cost(
+                        &costs,
+                        num_points,
+                        MSM_COST_SCALAR_BITS,
+                        radix,
+                        clump_factor,
+                    ),
+                };
+                (strategy, estimate, cells)
+            })
+            .collect_vec();
+
+        // the strategy chosen by the model should be the cheapest one in practice, up to strategies within 5% of it
+        let (chosen, _, chosen_cells) =
+            *measured.iter().min_by_key(|(_, estimate, _)| *estimate).unwrap();
+        let (best, _, best_cells) = *measured.iter().min_by_key(|(_, _, cells)| *cells).unwrap();
+        assert!(
+            chosen_cells * 100 <= best_cells * 105,
+            "with {num_points} points the model chose {chosen:?} using {chosen_cells} advice cells, but {best:?} uses \
+             {best_cells}: {measured:?}"
+        );
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_glv_decompose() {
+    use glv::{decompose_scalar, glv_max_bits, GlvCurve};
+    let mut rng = rand::thread_rng();
+
+    let max_bits = glv_max_bits::<G1Affine>() as u64;
+    let n = modulus::<Fr>();
+    let scalars = [BigUint::from(1u64), &n - 1u64]
+        .into_iter()
+        .chain((0..100).map(|_| fe_to_biguint(&Fr::random(&mut rng))));
+    for k in scalars {
+        let (k1, k2) = decompose_scalar::<G1Affine>(&k);
+        assert!(k1.bits() <= max_bits && k2.bits() <= max_bits);
+        let k1 = bigint_to_fe::<Fr>(&k1);
+        let k2 = bigint_to_fe::<Fr>(&k2);
+        assert_eq!(k1 + k2 * G1Affine::LAMBDA, biguint_to_fe::<Fr>(&k));
+    }
+
+    // `BETA` and `LAMBDA` define the same endomorphism
+    let P = G1Affine::random(&mut rng);
+    let phi_P = G1Affine::from(P * G1Affine::LAMBDA);
+    assert_eq!((phi_P.x, phi_P.y), (P.x * G1Affine::BETA, P.y));
+}
+
+#[cfg(test)]
+#[test]
+fn test_glv() {
+    use glv::GlvCurve;
+
+    let mut rng = rand::thread_rng();
+    // `LAMBDA` decomposes as `k1 = 0`, `k2 = 1`
+    let scalars = [Fr::one(), -Fr::one(), G1Affine::LAMBDA, Fr::random(&mut rng)];
+    for scalar in scalars {
+        let P = G1Affine::random(&mut rng);
+        mock_ecc::<Fq, 19>(|chip, ctx| {
+            let config = &chip.field_chip;
+            let scalar_chip = FpConfig::<Fr, Fr>::construct(
+                config.range.clone(),
+                config.limb_bits,
+                config.num_limbs,
+                modulus::<Fr>(),
+            );
+            let P_assigned = chip.assign_point(ctx, Value::known(P));
+            let scalar_assigned = chip.load_scalar(ctx, Value::known(scalar), config.limb_bits);
+            let glv = chip.scalar_mult_glv::<G1Affine>(
+                &scalar_chip,
+                ctx,
+                &P_assigned,
+                &scalar_assigned,
+                4,
+            );
+
+            // the result agrees with scalar multiplication without the endomorphism
+            let expected = chip.assign_point(ctx, Value::known(G1Affine::from(P * scalar)));
+            chip.assert_equal(ctx, &glv, &expected);
+        })
+        .assert_satisfied();
+    }
+}
+
+fn fixed_base_lookup_bases() -> [G1Affine; 2] {
+    [G1Affine::generator(), G1Affine::from(G1Affine::generator() * Fr::from(3))]
+}
+
+impl EccTestTable for fixed_base_lookup::FixedBaseLookupConfig<G1Affine> {
+    fn configure_table(meta: &mut ConstraintSystem<Fr>) -> Self {
+        Self::configure(meta, &fixed_base_lookup_bases(), 264, 4, 88, 3, 1, 0, 18)
+    }
+
+    fn load_table(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        self.load_lookup_table(layouter)
+    }
+
+    fn finalize_table(&self, ctx: &mut Context<'_, Fr>) {
+        self.finalize(ctx);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_fixed_base_lookup() {
+    let mut rng = rand::thread_rng();
+    // `5` leaves all windows but the lowest zero
+    let scalars = [Fr::one(), Fr::from(5), -Fr::one(), Fr::random(&mut rng)];
+    for scalar in scalars {
+        mock_ecc_with_table::<Fq, fixed_base_lookup::FixedBaseLookupConfig<G1Affine>, 18>(
+            |chip, table, ctx| {
+                let scalar = chip.load_scalar(ctx, Value::known(scalar), chip.field_chip.limb_bits);
+                for base in fixed_base_lookup_bases() {
+                    let lookup = chip.fixed_base_scalar_mult_lookup(table, ctx, &base, &scalar);
+                    // the result agrees with fixed base scalar multiplication selecting among constants
+                    let expected = chip.fixed_base_scalar_mult(ctx, &base, &scalar, 4);
+                    chip.assert_equal(ctx, &lookup, &expected);
+                }
+            },
+        )
+        .assert_satisfied();
+    }
+}
+
+// Loads the compressed encoding of `P` with its first byte replaced by `prefix`, and checks the encodings of the loaded point
+fn mock_sec1<C: CurveAffineExt>(P: C, prefix: Option<u8>) -> MockProver<Fr>
+where
+    C::Base: PrimeField,
+{
+    mock_ecc::<C::Base, 18>(|chip, ctx| {
+        let gate = chip.field_chip().range().gate();
+
+        let mut compressed = sec1::sec1_encode_native(&P, true);
+        compressed[0] = prefix.unwrap_or(compressed[0]);
+        let bytes = gate.assign_witnesses(
+            ctx,
+            compressed.into_iter().map(|b| Value::known(Fr::from(b as u64))),
+        );
+        let P_assigned = chip.load_sec1_compressed::<C>(ctx, &bytes);
+        let expected = chip.assign_point(ctx, Value::known(P));
+        chip.assert_equal(ctx, &P_assigned, &expected);
+
+        // the encodings agree with the encodings outside of the circuit
+        for compressed in [true, false] {
+            let encoded = chip.sec1_encode(ctx, &expected, compressed);
+            let native = sec1::sec1_encode_native(&P, compressed);
+            assert_eq!(encoded.len(), native.len());
+            for (byte, native) in encoded.iter().zip(native) {
+                gate.assert_is_const(ctx, byte, Fr::from(native as u64));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+#[test]
+fn test_sec1_encode_native() {
+    use crate::halo2_proofs::halo2curves::secp256k1::Secp256k1Affine;
+
+    let G = Secp256k1Affine::generator();
+    let compressed = sec1::sec1_encode_native(&G, true);
+    assert_eq!(
+        BigUint::from_bytes_be(&compressed),
+        BigUint::parse_bytes(
+            b"0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+            16
+        )
+        .unwrap()
+    );
+    let uncompressed = sec1::sec1_encode_native(&G, false);
+    assert_eq!(uncompressed.len(), 65);
+    assert_eq!(uncompressed[..33], [&[4u8][..], &compressed[1..]].concat());
+
+    // the generator of BN254 G1 is (1, 2)
+    let compressed = sec1::sec1_encode_native(&G1Affine::generator(), true);
+    assert_eq!(compressed, [&[2u8][..], &[0u8; 31], &[1u8]].concat());
+}
+
+#[cfg(test)]
+#[test]
+fn test_sec1() {
+    use crate::halo2_proofs::halo2curves::secp256k1::Secp256k1Affine;
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..2 {
+        mock_sec1(G1Affine::random(&mut rng), None).assert_satisfied();
+        mock_sec1(Secp256k1Affine::random(&mut rng), None).assert_satisfied();
+    }
+
+    // the prefix of a compressed encoding is 0x02 or 0x03, and determines the parity of y
+    let P = Secp256k1Affine::random(&mut rng);
+    let prefix = sec1::sec1_encode_native(&P, true)[0];
+    for wrong_prefix in [5 - prefix, 4, 0] {
+        assert!(mock_sec1(P, Some(wrong_prefix)).verify().is_err());
+    }
+}
+
+/// `y^2 = x^3 + 5 x - 2` over the BN254 base field, which has no `CurveAffine` implementation
+pub struct NonzeroACurve;
+
+impl CurveParams for NonzeroACurve {
+    type Base = Fq;
+    // the order of the curve is not used by the test
+    type ScalarField = Fr;
+
+    fn coeff_a() -> Fq {
+        Fq::from(5)
+    }
+
+    fn coeff_b() -> Fq {
+        -Fq::from(2)
+    }
+}
+
+fn double_native<C: CurveParams<Base = Fq>>((x, y): (Fq, Fq)) -> (Fq, Fq) {
+    let lambda = (x.square() * Fq::from(3) + C::coeff_a()) * (y + y).invert().unwrap();
+    let x3 = lambda.square() - x - x;
+    (x3, lambda * (x - x3) - y)
+}
+
+// Loads `P` and checks that it and its doublings are on `NonzeroACurve`, comparing the doublings with `double_native`
+fn mock_nonzero_a(P: (Fq, Fq)) -> MockProver<Fr> {
+    mock_ecc::<Fq, 18>(|chip, ctx| {
+        let [x, y] = [P.0, P.1].map(|c| {
+            chip.field_chip().load_private(ctx, FpConfig::<Fr, Fq>::fe_to_witness(&Value::known(c)))
+        });
+        let P_assigned = EcPoint::construct(x, y);
+        chip.assert_is_on_curve::<NonzeroACurve>(ctx, &P_assigned);
+
+        let mut expected = P;
+        let mut R = P_assigned;
+        for _ in 0..2 {
+            R = chip.double::<NonzeroACurve>(ctx, &R);
+            expected = double_native::<NonzeroACurve>(expected);
+            chip.assert_is_on_curve::<NonzeroACurve>(ctx, &R);
+            R.x.value.as_ref().assert_if_known(|v| bigint_to_fe::<Fq>(v) == expected.0);
+            R.y.value.as_ref().assert_if_known(|v| bigint_to_fe::<Fq>(v) == expected.1);
+        }
+    })
+}
+
+#[cfg(test)]
+#[test]
+fn test_nonzero_a() {
+    // (1, 2) is on `y^2 = x^3 + 5 x - 2` and doubles to (2, -4)
+    let P = (Fq::from(1), Fq::from(2));
+    assert_eq!(double_native::<NonzeroACurve>(P), (Fq::from(2), -Fq::from(4)));
+    mock_nonzero_a(P).assert_satisfied();
+
+    // (3, 5) is on `y^2 = x^3 - 2` but not on `y^2 = x^3 + 5 x - 2`
+    assert!(mock_nonzero_a((Fq::from(3), Fq::from(5))).verify().is_err());
+}
+
 #[cfg(feature = "dev-graph")]
 #[cfg(test)]
 #[test]
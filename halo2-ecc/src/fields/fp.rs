@@ -1,8 +1,8 @@
 use super::{FieldChip, PrimeFieldChip, Selectable};
 use crate::bigint::{
     add_no_carry, big_is_equal, big_is_zero, carry_mod, check_carry_mod_to_zero, mul_no_carry,
-    scalar_mul_and_add_no_carry, scalar_mul_no_carry, select, select_by_indicator, sub,
-    sub_no_carry, CRTInteger, FixedCRTInteger, OverflowInteger,
+    range_check_limbs, scalar_mul_and_add_no_carry, scalar_mul_no_carry, select,
+    select_by_indicator, sub, sub_no_carry, CRTInteger, FixedCRTInteger, OverflowInteger,
 };
 use crate::halo2_proofs::{
     circuit::{Layouter, Region, Value},
@@ -15,7 +15,7 @@ use halo2_base::{
         GateInstructions, RangeInstructions,
     },
     utils::{
-        bigint_to_fe, biguint_to_fe, bit_length, decompose_bigint_option, decompose_biguint,
+        bigint_to_prime_fe, biguint_to_fe, bit_length, decompose_bigint_option, decompose_biguint,
         fe_to_biguint, modulus, PrimeField,
     },
     AssignedValue, Context, ContextParams,
@@ -34,8 +34,18 @@ pub enum FpStrategy {
 
 pub type BaseFieldChip<C> = FpConfig<<C as CurveAffine>::ScalarExt, <C as CurveAffine>::Base>;
 
+/// The fewest limbs of `limb_bits` bits with which `FpConfig<F, _>` can multiply two reduced elements modulo `p`.
+///
+/// `carry_mod` assumes its input has at most `limb_bits * num_limbs - 1 + F::NUM_BITS - 2` bits, so for moduli that are large
+/// compared to `F`, such as 381-bit or 384-bit primes, this is more limbs than are needed to represent `p`.
+pub fn num_limbs_for_modulus<F: PrimeField>(p: &BigUint, limb_bits: usize) -> usize {
+    let p_bits = p.bits() as usize;
+    let product_bits = (2 * p_bits + 3).saturating_sub(F::NUM_BITS as usize);
+    (max(p_bits, product_bits) + limb_bits - 1) / limb_bits
+}
+
 #[derive(Clone, Debug)]
-pub struct FpConfig<F: PrimeField, Fp: PrimeField> {
+pub struct FpConfig<F: PrimeField, Fp: ff::PrimeField> {
     pub range: RangeConfig<F>,
     // pub bigint_chip: BigIntConfig<F>,
     pub limb_bits: usize,
@@ -55,7 +65,7 @@ pub struct FpConfig<F: PrimeField, Fp: PrimeField> {
     _marker: PhantomData<Fp>,
 }
 
-impl<F: PrimeField, Fp: PrimeField> FpConfig<F, Fp> {
+impl<F: PrimeField, Fp: ff::PrimeField> FpConfig<F, Fp> {
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         strategy: FpStrategy,
@@ -124,14 +134,16 @@ impl<F: PrimeField, Fp: PrimeField> FpConfig<F, Fp> {
     }
 
     pub fn new_context<'a, 'b>(&'b self, region: Region<'a, F>) -> Context<'a, F> {
-        Context::new(
-            region,
-            ContextParams {
-                max_rows: self.range.gate.max_rows,
-                num_context_ids: 1,
-                fixed_columns: self.range.gate.constants.clone(),
-            },
-        )
+        Context::new(region, self.context_params())
+    }
+
+    /// Same as [`FpConfig::new_context`], but for a virtual context, see [`Context::new_virtual`]
+    pub fn new_virtual_context<'a>(&self) -> Context<'a, F> {
+        Context::new_virtual(self.context_params())
+    }
+
+    fn context_params(&self) -> ContextParams {
+        ContextParams::new(self.range.gate.max_rows, 1, self.range.gate.constants.clone())
     }
 
     pub fn load_lookup_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
@@ -170,7 +182,7 @@ impl<F: PrimeField, Fp: PrimeField> FpConfig<F, Fp> {
     }
 }
 
-impl<F: PrimeField, Fp: PrimeField> PrimeFieldChip<F> for FpConfig<F, Fp> {
+impl<F: PrimeField, Fp: ff::PrimeField> PrimeFieldChip<F> for FpConfig<F, Fp> {
     fn num_limbs(&self) -> usize {
         self.num_limbs
     }
@@ -182,7 +194,7 @@ impl<F: PrimeField, Fp: PrimeField> PrimeFieldChip<F> for FpConfig<F, Fp> {
     }
 }
 
-impl<F: PrimeField, Fp: PrimeField> FieldChip<F> for FpConfig<F, Fp> {
+impl<F: PrimeField, Fp: ff::PrimeField> FieldChip<F> for FpConfig<F, Fp> {
     const PRIME_FIELD_NUM_BITS: u32 = Fp::NUM_BITS;
     type ConstantType = BigUint;
     type WitnessType = Value<BigInt>;
@@ -201,7 +213,7 @@ impl<F: PrimeField, Fp: PrimeField> FieldChip<F> for FpConfig<F, Fp> {
     }
 
     fn get_assigned_value(&self, x: &CRTInteger<F>) -> Value<Fp> {
-        x.value.as_ref().map(|x| bigint_to_fe::<Fp>(&(x % &self.p)))
+        x.value.as_ref().map(|x| bigint_to_prime_fe::<Fp>(&(x % &self.p)))
     }
 
     fn fe_to_constant(x: Fp) -> BigUint {
@@ -373,21 +385,14 @@ impl<F: PrimeField, Fp: PrimeField> FieldChip<F> for FpConfig<F, Fp> {
         a: &CRTInteger<'v, F>,
         max_bits: usize, // the maximum bits that a.value could take
     ) {
-        let n = self.limb_bits;
-        let k = a.truncation.limbs.len();
-        debug_assert!(max_bits > n * (k - 1) && max_bits <= n * k);
-        let last_limb_bits = max_bits - n * (k - 1);
-
         #[cfg(debug_assertions)]
         a.value.as_ref().map(|v| {
             debug_assert!(v.bits() as usize <= max_bits);
         });
 
-        // range check limbs of `a` are in [0, 2^n) except last limb should be in [0, 2^last_limb_bits)
-        for (i, cell) in a.truncation.limbs.iter().enumerate() {
-            let limb_bits = if i == k - 1 { last_limb_bits } else { n };
-            self.range.range_check(ctx, cell, limb_bits);
-        }
+        // range check limbs of `a` are in [0, 2^n) except the limb containing bit `max_bits - 1` should be in
+        // [0, 2^{max_bits - n * i}) and any limbs past it should be zero
+        range_check_limbs(&self.range, ctx, &a.truncation.limbs, max_bits, self.limb_bits);
     }
 
     fn enforce_less_than<'v>(&self, ctx: &mut Context<'v, F>, a: &Self::FieldPoint<'v>) {
@@ -465,7 +470,7 @@ impl<F: PrimeField, Fp: PrimeField> FieldChip<F> for FpConfig<F, Fp> {
     }
 }
 
-impl<F: PrimeField, Fp: PrimeField> Selectable<F> for FpConfig<F, Fp> {
+impl<F: PrimeField, Fp: ff::PrimeField> Selectable<F> for FpConfig<F, Fp> {
     type Point<'v> = CRTInteger<'v, F>;
 
     fn select<'v>(
@@ -0,0 +1,281 @@
+//! Lazy reduction for [`FpConfig`]: [`LazyFpChip`] tracks bounds on the limbs and the value of each [`CRTInteger`] through
+//! no-carry operations and only inserts `carry_mod` when the next operation would overflow.
+use super::{fp::FpConfig, FieldChip};
+use crate::bigint::CRTInteger;
+use crate::halo2_proofs::circuit::Value;
+use halo2_base::{
+    utils::{log2_ceil, PrimeField},
+    AssignedValue, Context,
+};
+use num_bigint::{BigInt, BigUint};
+use std::cmp::max;
+
+/// A [`CRTInteger`] representing an element of `Fp` by a possibly negative or unreduced integer, together with a bound on
+/// the bits of that integer.
+#[derive(Clone, Debug)]
+pub struct LazyCRTInteger<'v, F: PrimeField> {
+    pub crt: CRTInteger<'v, F>,
+    // `abs(crt.value) < 2^max_bits`
+    pub max_bits: usize,
+    // `crt` has the form returned by `carry_mod`: limbs in `[0, 2^limb_bits)` and value in `[0, 2^{p.bits()})`
+    pub is_reduced: bool,
+}
+
+impl<'v, F: PrimeField> LazyCRTInteger<'v, F> {
+    pub fn max_limb_bits(&self) -> usize {
+        self.crt.truncation.max_limb_bits
+    }
+}
+
+/// Wrapper around [`FpConfig`] whose arithmetic takes and returns [`LazyCRTInteger`]s.
+///
+/// Each operation computes the bounds of its output before assigning anything. If the limbs of the output could overflow
+/// the native field, or its value could be too large for `carry_mod`, the operand with the larger value bound is reduced
+/// with `carry_mod` and the bounds are computed again.
+///
+/// This does not implement [`FieldChip`]: that trait leaves it to the caller to decide when to call
+/// `carry_mod` after each `*_no_carry` operation, and its `FieldPoint` carries no bounds, while the point of this chip is that
+/// its operations decide that themselves from the bounds tracked in [`LazyCRTInteger`]. Use [`fp_chip`](Self::fp_chip) for
+/// the operations of `FieldChip` on reduced integers, such as `range_check` or `divide`.
+#[derive(Clone, Debug)]
+pub struct LazyFpChip<'a, F: PrimeField, Fp: ff::PrimeField> {
+    pub fp_chip: &'a FpConfig<F, Fp>,
+    // the limbs of `a - out - p * quot` in `carry_mod` have one more bit than those of `a`, and need to stay below
+    // `F::NUM_BITS - 1` bits for their sign to be meaningful
+    pub max_limb_bits: usize,
+    // `carry_mod` assumes `abs(a) <= 2^{n * k - 1 + F::NUM_BITS - 2}`
+    pub max_value_bits: usize,
+}
+
+impl<'a, F: PrimeField, Fp: ff::PrimeField> LazyFpChip<'a, F, Fp> {
+    pub fn construct(fp_chip: &'a FpConfig<F, Fp>) -> Self {
+        let max_limb_bits = F::NUM_BITS as usize - 3;
+        let max_value_bits = fp_chip.limb_bits * fp_chip.num_limbs + F::NUM_BITS as usize - 3;
+        assert!(
+            2 * fp_chip.limb_bits + fp_chip.num_limbs_log2_ceil <= max_limb_bits,
+            "limbs of the product of two reduced integers overflow the native field"
+        );
+        Self { fp_chip, max_limb_bits, max_value_bits }
+    }
+
+    fn p_bits(&self) -> usize {
+        self.fp_chip.p.bits() as usize
+    }
+
+    /// Wraps a `FieldPoint` returned by `load_private`, `load_constant` or `carry_mod` of `fp_chip`.
+    pub fn from_reduced<'v>(&self, a: CRTInteger<'v, F>) -> LazyCRTInteger<'v, F> {
+        debug_assert_eq!(a.truncation.max_limb_bits, self.fp_chip.limb_bits);
+        LazyCRTInteger { crt: a, max_bits: self.p_bits(), is_reduced: true }
+    }
+
+    fn from_unreduced<'v>(&self, a: CRTInteger<'v, F>, max_bits: usize) -> LazyCRTInteger<'v, F> {
+        debug_assert!(a.truncation.max_limb_bits <= self.max_limb_bits);
+        debug_assert!(max_bits <= self.max_value_bits);
+        LazyCRTInteger { crt: a, max_bits, is_reduced: false }
+    }
+
+    fn fits(&self, (limb_bits, bits): (usize, usize)) -> bool {
+        limb_bits <= self.max_limb_bits && bits <= self.max_value_bits
+    }
+
+    // Reduces `a` until the output bounds `bounds(a)` fit
+    fn carry_until_fits<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        bounds: impl Fn(&LazyCRTInteger<'v, F>) -> (usize, usize),
+    ) -> LazyCRTInteger<'v, F> {
+        if self.fits(bounds(a)) {
+            return a.clone();
+        }
+        let a = self.carry_mod(ctx, a);
+        assert!(self.fits(bounds(&a)), "operation overflows even on reduced inputs");
+        a
+    }
+
+    // Reduces `a` and `b`, the one with the larger value bound first, until the output bounds `bounds(a, b)` fit
+    fn carry_until_fits_pair<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        b: &LazyCRTInteger<'v, F>,
+        bounds: impl Fn(&LazyCRTInteger<'v, F>, &LazyCRTInteger<'v, F>) -> (usize, usize),
+    ) -> (LazyCRTInteger<'v, F>, LazyCRTInteger<'v, F>) {
+        let (mut a, mut b) = (a.clone(), b.clone());
+        while !self.fits(bounds(&a, &b)) {
+            if !a.is_reduced && (b.is_reduced || a.max_bits >= b.max_bits) {
+                a = self.carry_mod(ctx, &a);
+            } else if !b.is_reduced {
+                b = self.carry_mod(ctx, &b);
+            } else {
+                panic!("operation overflows even on reduced inputs");
+            }
+        }
+        (a, b)
+    }
+
+    pub fn get_assigned_value(&self, a: &LazyCRTInteger<F>) -> Value<Fp> {
+        self.fp_chip.get_assigned_value(&a.crt)
+    }
+
+    pub fn load_private<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        a: Value<BigInt>,
+    ) -> LazyCRTInteger<'v, F> {
+        self.from_reduced(self.fp_chip.load_private(ctx, a))
+    }
+
+    pub fn load_constant<'v>(&self, ctx: &mut Context<'_, F>, a: BigUint) -> LazyCRTInteger<'v, F> {
+        debug_assert!(a.bits() as usize <= self.p_bits());
+        self.from_reduced(self.fp_chip.load_constant(ctx, a))
+    }
+
+    /// Returns `a` reduced by `carry_mod`, without any constraints if `a` is already reduced.
+    pub fn carry_mod<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+    ) -> LazyCRTInteger<'v, F> {
+        if a.is_reduced {
+            return a.clone();
+        }
+        self.from_reduced(self.fp_chip.carry_mod(ctx, &a.crt))
+    }
+
+    pub fn add<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        b: &LazyCRTInteger<'v, F>,
+    ) -> LazyCRTInteger<'v, F> {
+        let bounds = |a: &LazyCRTInteger<F>, b: &LazyCRTInteger<F>| {
+            (max(a.max_limb_bits(), b.max_limb_bits()) + 1, max(a.max_bits, b.max_bits) + 1)
+        };
+        let (a, b) = self.carry_until_fits_pair(ctx, a, b, bounds);
+        let out = self.fp_chip.add_no_carry(ctx, &a.crt, &b.crt);
+        self.from_unreduced(out, bounds(&a, &b).1)
+    }
+
+    /// output: `a + c`
+    pub fn add_constant<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        c: BigUint,
+    ) -> LazyCRTInteger<'v, F> {
+        let c_bits = c.bits() as usize;
+        let limb_bits = self.fp_chip.limb_bits;
+        let bounds = |a: &LazyCRTInteger<F>| {
+            (max(a.max_limb_bits(), limb_bits) + 1, max(a.max_bits, c_bits) + 1)
+        };
+        let a = self.carry_until_fits(ctx, a, bounds);
+        let out = self.fp_chip.add_constant_no_carry(ctx, &a.crt, c);
+        self.from_unreduced(out, bounds(&a).1)
+    }
+
+    pub fn sub<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        b: &LazyCRTInteger<'v, F>,
+    ) -> LazyCRTInteger<'v, F> {
+        let bounds = |a: &LazyCRTInteger<F>, b: &LazyCRTInteger<F>| {
+            (max(a.max_limb_bits(), b.max_limb_bits()) + 1, max(a.max_bits, b.max_bits) + 1)
+        };
+        let (a, b) = self.carry_until_fits_pair(ctx, a, b, bounds);
+        let out = self.fp_chip.sub_no_carry(ctx, &a.crt, &b.crt);
+        self.from_unreduced(out, bounds(&a, &b).1)
+    }
+
+    // unlike `FpConfig::negate`, this does not need `a` to be reduced
+    pub fn negate<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+    ) -> LazyCRTInteger<'v, F> {
+        self.scalar_mul(ctx, a, -1)
+    }
+
+    /// a * c
+    pub fn scalar_mul<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        c: i64,
+    ) -> LazyCRTInteger<'v, F> {
+        // `log2_ceil` is not defined at 0
+        if c == 0 {
+            return self.load_constant(ctx, BigUint::from(0u64));
+        }
+        let c_log2_ceil = log2_ceil(c.unsigned_abs());
+        let bounds =
+            |a: &LazyCRTInteger<F>| (a.max_limb_bits() + c_log2_ceil, a.max_bits + c_log2_ceil);
+        let a = self.carry_until_fits(ctx, a, bounds);
+        let out = self.fp_chip.scalar_mul_no_carry(ctx, &a.crt, c);
+        self.from_unreduced(out, bounds(&a).1)
+    }
+
+    /// a * c + b
+    pub fn scalar_mul_and_add<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        b: &LazyCRTInteger<'v, F>,
+        c: i64,
+    ) -> LazyCRTInteger<'v, F> {
+        if c == 0 {
+            return b.clone();
+        }
+        let c_log2_ceil = log2_ceil(c.unsigned_abs());
+        let bounds = |a: &LazyCRTInteger<F>, b: &LazyCRTInteger<F>| {
+            (
+                max(a.max_limb_bits() + c_log2_ceil, b.max_limb_bits()) + 1,
+                max(a.max_bits + c_log2_ceil, b.max_bits) + 1,
+            )
+        };
+        let (a, b) = self.carry_until_fits_pair(ctx, a, b, bounds);
+        let out = self.fp_chip.scalar_mul_and_add_no_carry(ctx, &a.crt, &b.crt, c);
+        self.from_unreduced(out, bounds(&a, &b).1)
+    }
+
+    pub fn mul<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        b: &LazyCRTInteger<'v, F>,
+    ) -> LazyCRTInteger<'v, F> {
+        let num_limbs_log2_ceil = self.fp_chip.num_limbs_log2_ceil;
+        let bounds = |a: &LazyCRTInteger<F>, b: &LazyCRTInteger<F>| {
+            (a.max_limb_bits() + b.max_limb_bits() + num_limbs_log2_ceil, a.max_bits + b.max_bits)
+        };
+        let (a, b) = self.carry_until_fits_pair(ctx, a, b, bounds);
+        let out = self.fp_chip.mul_no_carry(ctx, &a.crt, &b.crt);
+        self.from_unreduced(out, bounds(&a, &b).1)
+    }
+
+    /// Constrains `a = 0 mod p`.
+    pub fn check_carry_mod_to_zero<'v>(&self, ctx: &mut Context<'v, F>, a: &LazyCRTInteger<'v, F>) {
+        self.fp_chip.check_carry_mod_to_zero(ctx, &a.crt)
+    }
+
+    /// Constrains `a = b mod p`, which is cheaper than reducing `a` and `b` and comparing them.
+    pub fn assert_equal<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+        b: &LazyCRTInteger<'v, F>,
+    ) {
+        let diff = self.sub(ctx, a, b);
+        self.check_carry_mod_to_zero(ctx, &diff)
+    }
+
+    pub fn is_zero<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &LazyCRTInteger<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        let a = self.carry_mod(ctx, a);
+        self.fp_chip.is_zero(ctx, &a.crt)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ecc::msm_cost::FieldOpCosts;
 use crate::halo2_proofs::{arithmetic::Field, circuit::Value};
 use halo2_base::{gates::RangeInstructions, utils::PrimeField, AssignedValue, Context};
 use num_bigint::BigUint;
@@ -6,6 +7,8 @@ use std::fmt::Debug;
 pub mod fp;
 pub mod fp12;
 pub mod fp2;
+pub mod lazy;
+pub mod native;
 
 #[cfg(test)]
 mod tests;
@@ -256,11 +259,19 @@ pub trait Selectable<F: PrimeField> {
 // Common functionality for prime field chips
 pub trait PrimeFieldChip<F: PrimeField>: FieldChip<F>
 where
-    Self::FieldType: PrimeField,
+    Self::FieldType: ff::PrimeField,
 {
     fn num_limbs(&self) -> usize;
     fn limb_mask(&self) -> &BigUint;
     fn limb_bases(&self) -> &[F];
+
+    /// Estimated advice cells of the field operations of this chip, used to choose the algorithm of
+    /// [`EccChip::variable_base_msm`](crate::ecc::EccChip::variable_base_msm).
+    ///
+    /// Defaults to the costs of the CRT representation with `num_limbs` limbs of `limb_bits` bits.
+    fn op_costs(&self) -> FieldOpCosts {
+        FieldOpCosts::new(self.limb_bits(), self.num_limbs(), self.range().lookup_bits())
+    }
 }
 
 // helper trait so we can actually construct and read the Fp2 struct
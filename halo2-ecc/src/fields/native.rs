@@ -0,0 +1,313 @@
+use super::{fp::FpStrategy, FieldChip, PrimeFieldChip, Selectable};
+use crate::ecc::msm_cost::FieldOpCosts;
+use crate::halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{ConstraintSystem, Error},
+};
+use halo2_base::{
+    gates::{
+        range::{RangeConfig, RangeStrategy},
+        GateInstructions, RangeInstructions,
+    },
+    utils::{modulus, PrimeField},
+    AssignedValue, Context, ContextParams,
+    QuantumCell::{Constant, Existing},
+};
+use num_bigint::BigUint;
+
+/// Field chip for the native field `F` of the circuit, where a field element is a single `AssignedValue`.
+///
+/// Every operation is a single gate and every value is always reduced, so there is no carrying and `enforce_less_than` is free.
+/// This is used for curves whose base field is the native field, such as Grumpkin over the scalar field of BN254.
+#[derive(Clone, Debug)]
+pub struct NativeFieldChip<F: PrimeField> {
+    pub range: RangeConfig<F>,
+    pub native_modulus: BigUint,
+    // a field element is a single limb, see `PrimeFieldChip`
+    pub limb_mask: BigUint,
+    pub limb_bases: Vec<F>,
+}
+
+impl<F: PrimeField> NativeFieldChip<F> {
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        strategy: FpStrategy,
+        num_advice: &[usize],
+        num_lookup_advice: &[usize],
+        num_fixed: usize,
+        lookup_bits: usize,
+        gate_context_id: usize,
+        k: usize,
+    ) -> Self {
+        let range = RangeConfig::<F>::configure(
+            meta,
+            match strategy {
+                FpStrategy::Simple => RangeStrategy::Vertical,
+                FpStrategy::SimplePlus => RangeStrategy::PlonkPlus,
+            },
+            num_advice,
+            num_lookup_advice,
+            num_fixed,
+            lookup_bits,
+            gate_context_id,
+            k,
+        );
+        Self::construct(range)
+    }
+
+    pub fn construct(range: RangeConfig<F>) -> Self {
+        Self {
+            range,
+            native_modulus: modulus::<F>(),
+            limb_mask: (BigUint::from(1u64) << F::NUM_BITS) - 1usize,
+            limb_bases: vec![F::one()],
+        }
+    }
+
+    pub fn new_context<'a, 'b>(&'b self, region: Region<'a, F>) -> Context<'a, F> {
+        Context::new(
+            region,
+            ContextParams::new(self.range.gate.max_rows, 1, self.range.gate.constants.clone()),
+        )
+    }
+
+    pub fn load_lookup_table(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.range.load_lookup_table(layouter)
+    }
+
+    pub fn finalize(&self, ctx: &mut Context<'_, F>) -> usize {
+        self.range.finalize(ctx)
+    }
+}
+
+impl<F: PrimeField> FieldChip<F> for NativeFieldChip<F> {
+    const PRIME_FIELD_NUM_BITS: u32 = F::NUM_BITS;
+    type ConstantType = F;
+    type WitnessType = Value<F>;
+    type FieldPoint<'v> = AssignedValue<'v, F>;
+    type FieldType = F;
+    type RangeChip = RangeConfig<F>;
+
+    fn native_modulus(&self) -> &BigUint {
+        &self.native_modulus
+    }
+    fn range(&self) -> &Self::RangeChip {
+        &self.range
+    }
+    // a field element is a single limb of `F::NUM_BITS` bits
+    fn limb_bits(&self) -> usize {
+        F::NUM_BITS as usize
+    }
+
+    fn get_assigned_value(&self, x: &AssignedValue<F>) -> Value<F> {
+        x.value().copied()
+    }
+
+    fn fe_to_constant(x: F) -> F {
+        x
+    }
+
+    fn fe_to_witness(x: &Value<F>) -> Value<F> {
+        *x
+    }
+
+    fn load_private<'v>(&self, ctx: &mut Context<'_, F>, a: Value<F>) -> AssignedValue<'v, F> {
+        self.range.gate().load_witness(ctx, a)
+    }
+
+    fn load_constant<'v>(&self, ctx: &mut Context<'_, F>, a: F) -> AssignedValue<'v, F> {
+        self.range.gate().load_constant(ctx, a)
+    }
+
+    fn add_no_carry<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().add(ctx, Existing(a), Existing(b))
+    }
+
+    fn add_constant_no_carry<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        c: F,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().add(ctx, Existing(a), Constant(c))
+    }
+
+    fn sub_no_carry<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().sub(ctx, Existing(a), Existing(b))
+    }
+
+    fn negate<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().neg(ctx, Existing(a))
+    }
+
+    fn scalar_mul_no_carry<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        c: i64,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().mul(ctx, Existing(a), Constant(i64_to_fe(c)))
+    }
+
+    fn scalar_mul_and_add_no_carry<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+        c: i64,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().mul_add(ctx, Existing(a), Constant(i64_to_fe(c)), Existing(b))
+    }
+
+    fn mul_no_carry<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().mul(ctx, Existing(a), Existing(b))
+    }
+
+    fn check_carry_mod_to_zero<'v>(&self, ctx: &mut Context<'v, F>, a: &AssignedValue<'v, F>) {
+        self.range.gate().assert_is_const(ctx, a, F::zero())
+    }
+
+    fn carry_mod<'v>(
+        &self,
+        _ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        a.clone()
+    }
+
+    fn range_check<'v>(&self, ctx: &mut Context<'v, F>, a: &AssignedValue<'v, F>, max_bits: usize) {
+        // every element of `F` has at most `F::NUM_BITS` bits
+        if max_bits < F::NUM_BITS as usize {
+            self.range.range_check(ctx, a, max_bits)
+        }
+    }
+
+    fn enforce_less_than<'v>(&self, _ctx: &mut Context<'v, F>, _a: &AssignedValue<'v, F>) {}
+
+    fn is_soft_zero<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().is_zero(ctx, a)
+    }
+
+    fn is_soft_nonzero<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        let is_zero = self.range.gate().is_zero(ctx, a);
+        self.range.gate().not(ctx, Existing(&is_zero))
+    }
+
+    fn is_zero<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().is_zero(ctx, a)
+    }
+
+    fn is_equal_unenforced<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().is_equal(ctx, Existing(a), Existing(b))
+    }
+
+    fn assert_equal<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+    ) {
+        ctx.constrain_equal(a, b);
+    }
+
+    // the quotient is always reduced, so unlike the default there is no need to range check it
+    fn neg_divide<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        let quot_val = a.value().zip(b.value()).map(|(a, b)| -*a * b.invert().unwrap());
+        let quot = self.range.gate().load_witness(ctx, quot_val);
+
+        // constrain quot * b + a = 0
+        let quot_constraint =
+            self.range.gate().mul_add(ctx, Existing(&quot), Existing(b), Existing(a));
+        self.check_carry_mod_to_zero(ctx, &quot_constraint);
+
+        quot
+    }
+}
+
+impl<F: PrimeField> PrimeFieldChip<F> for NativeFieldChip<F> {
+    fn num_limbs(&self) -> usize {
+        1
+    }
+    fn limb_mask(&self) -> &BigUint {
+        &self.limb_mask
+    }
+    fn limb_bases(&self) -> &[F] {
+        &self.limb_bases
+    }
+
+    fn op_costs(&self) -> FieldOpCosts {
+        FieldOpCosts::native()
+    }
+}
+
+impl<F: PrimeField> Selectable<F> for NativeFieldChip<F> {
+    type Point<'v> = AssignedValue<'v, F>;
+
+    fn select<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        a: &AssignedValue<'v, F>,
+        b: &AssignedValue<'v, F>,
+        sel: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().select(ctx, Existing(a), Existing(b), Existing(sel))
+    }
+
+    fn select_by_indicator<'v>(
+        &self,
+        ctx: &mut Context<'_, F>,
+        a: &[AssignedValue<'v, F>],
+        coeffs: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        self.range.gate().select_by_indicator(ctx, a.iter().map(Existing), coeffs)
+    }
+}
+
+fn i64_to_fe<F: PrimeField>(c: i64) -> F {
+    if c >= 0 {
+        F::from(c as u64)
+    } else {
+        -F::from(c.unsigned_abs())
+    }
+}
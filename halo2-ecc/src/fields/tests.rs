@@ -265,3 +265,314 @@ mod fp12 {
         halo2_proofs::dev::CircuitLayout::default().render(k, &circuit, &root).unwrap();
     }
 }
+
+mod fp_large_modulus {
+    use crate::fields::{
+        fp::{num_limbs_for_modulus, FpConfig, FpStrategy},
+        FieldChip,
+    };
+    use crate::halo2_proofs::{
+        circuit::*,
+        dev::MockProver,
+        halo2curves::bn256::{Fq, Fr},
+        plonk::*,
+    };
+    use ff::{Field, PrimeField};
+    use halo2_base::{
+        utils::{fe_to_biguint, modulus},
+        SKIP_FIRST_PASS,
+    };
+    use rand::rngs::OsRng;
+
+    // base field of BLS12-381, with a 48 byte representation
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559787"]
+    #[PrimeFieldGenerator = "2"]
+    #[PrimeFieldReprEndianness = "little"]
+    struct Fp381([u64; 6]);
+
+    // base field of P-384
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "39402006196394479212279040100143613805079739270465446667948293404245721771496870329047266088258938001861606973112319"]
+    #[PrimeFieldGenerator = "19"]
+    #[PrimeFieldReprEndianness = "little"]
+    struct Fp384([u64; 7]);
+
+    struct LargeFpCircuit<Fp: PrimeField, const LIMB_BITS: usize, const NUM_LIMBS: usize> {
+        a: Value<Fp>,
+        b: Value<Fp>,
+    }
+
+    const NUM_ADVICE: usize = 1;
+    const NUM_FIXED: usize = 1;
+    const K: usize = 14;
+
+    impl<Fp: PrimeField, const LIMB_BITS: usize, const NUM_LIMBS: usize> Circuit<Fr>
+        for LargeFpCircuit<Fp, LIMB_BITS, NUM_LIMBS>
+    {
+        type Config = FpConfig<Fr, Fp>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { a: Value::unknown(), b: Value::unknown() }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FpConfig::<Fr, Fp>::configure(
+                meta,
+                FpStrategy::Simple,
+                &[NUM_ADVICE],
+                &[1],
+                NUM_FIXED,
+                K - 1,
+                LIMB_BITS,
+                NUM_LIMBS,
+                modulus::<Fp>(),
+                0,
+                K,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            chip: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            chip.load_lookup_table(&mut layouter)?;
+
+            let mut first_pass = SKIP_FIRST_PASS;
+
+            layouter.assign_region(
+                || "fp_large_modulus",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let mut aux = chip.new_context(region);
+                    let ctx = &mut aux;
+
+                    let a = chip.load_private(ctx, FpConfig::<Fr, Fp>::fe_to_witness(&self.a));
+                    let b = chip.load_private(ctx, FpConfig::<Fr, Fp>::fe_to_witness(&self.b));
+
+                    // 3a + b has a few more bits than the modulus, so it can be reduced with any number of limbs
+                    // representing the modulus
+                    let sum = chip.scalar_mul_and_add_no_carry(ctx, &a, &b, 3);
+                    let sum_mod = chip.carry_mod(ctx, &sum);
+                    chip.get_assigned_value(&sum_mod)
+                        .zip(self.a.zip(self.b))
+                        .assert_if_known(|(sum, (a, b))| *sum == *a + *a + *a + *b);
+                    let diff = chip.sub_no_carry(ctx, &sum, &sum_mod);
+                    chip.check_carry_mod_to_zero(ctx, &diff);
+
+                    // the product of two reduced elements needs more limbs than the modulus for large moduli
+                    if NUM_LIMBS >= num_limbs_for_modulus::<Fr>(&modulus::<Fp>(), LIMB_BITS) {
+                        let prod = chip.mul_no_carry(ctx, &a, &b);
+                        let prod_mod = chip.carry_mod(ctx, &prod);
+                        chip.get_assigned_value(&prod_mod)
+                            .zip(self.a.zip(self.b))
+                            .assert_if_known(|(prod, (a, b))| *prod == *a * *b);
+                        let diff = chip.sub_no_carry(ctx, &prod, &prod_mod);
+                        chip.check_carry_mod_to_zero(ctx, &diff);
+                    }
+
+                    // IMPORTANT: this copies advice cells to enable lookup
+                    // This is not optional.
+                    chip.finalize(ctx);
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn run<Fp: PrimeField, const LIMB_BITS: usize, const NUM_LIMBS: usize>() {
+        let circuit = LargeFpCircuit::<Fp, LIMB_BITS, NUM_LIMBS> {
+            a: Value::known(Fp::random(OsRng)),
+            b: Value::known(Fp::random(OsRng)),
+        };
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_num_limbs_for_modulus() {
+        assert_eq!(num_limbs_for_modulus::<Fr>(&modulus::<Fq>(), 88), 3);
+        assert_eq!(num_limbs_for_modulus::<Fr>(&modulus::<Fp381>(), 104), 5);
+        assert_eq!(num_limbs_for_modulus::<Fr>(&modulus::<Fp384>(), 104), 5);
+        assert_eq!(num_limbs_for_modulus::<Fr>(&modulus::<Fp384>(), 96), 6);
+    }
+
+    #[test]
+    fn test_fp381_repr() {
+        assert_eq!(<Fp381 as PrimeField>::Repr::default().as_ref().len(), 48);
+        assert_eq!(modulus::<Fp381>().bits(), 381);
+        let a = Fp381::random(OsRng);
+        assert_eq!(fe_to_biguint(&(a * a)), fe_to_biguint(&a).pow(2) % modulus::<Fp381>());
+    }
+
+    #[test]
+    fn test_fp381_mul() {
+        run::<Fp381, 104, 5>();
+    }
+
+    #[test]
+    fn test_fp384_mul() {
+        run::<Fp384, 104, 5>();
+    }
+
+    #[test]
+    fn test_fp381_linear() {
+        run::<Fp381, 96, 4>();
+    }
+
+    #[test]
+    fn test_fp384_linear() {
+        run::<Fp384, 96, 4>();
+    }
+}
+
+mod lazy {
+    use crate::fields::{
+        fp::{FpConfig, FpStrategy},
+        lazy::LazyFpChip,
+        FieldChip,
+    };
+    use crate::halo2_proofs::{
+        circuit::*,
+        dev::MockProver,
+        halo2curves::bn256::{Fq, Fr},
+        plonk::*,
+    };
+    use group::ff::Field;
+    use halo2_base::{utils::modulus, SKIP_FIRST_PASS};
+    use rand::rngs::OsRng;
+
+    const NUM_PAIRS: usize = 4;
+    const K: usize = 13;
+
+    #[derive(Default)]
+    struct LazyCircuit {
+        a: Vec<Value<Fq>>,
+        b: Vec<Value<Fq>>,
+    }
+
+    impl Circuit<Fr> for LazyCircuit {
+        type Config = FpConfig<Fr, Fq>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { a: vec![Value::unknown(); NUM_PAIRS], b: vec![Value::unknown(); NUM_PAIRS] }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FpConfig::<Fr, Fq>::configure(
+                meta,
+                FpStrategy::Simple,
+                &[2],
+                &[1],
+                1,
+                K - 1,
+                88,
+                3,
+                modulus::<Fq>(),
+                0,
+                K,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            chip: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            chip.load_lookup_table(&mut layouter)?;
+            let lazy_chip = LazyFpChip::construct(&chip);
+
+            let mut first_pass = SKIP_FIRST_PASS;
+
+            layouter.assign_region(
+                || "lazy",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let mut aux = chip.new_context(region);
+                    let ctx = &mut aux;
+
+                    let mut load = |a: &Value<Fq>| {
+                        lazy_chip.load_private(ctx, FpConfig::<Fr, Fq>::fe_to_witness(a))
+                    };
+                    let a = self.a.iter().map(&mut load).collect::<Vec<_>>();
+                    let b = self.b.iter().map(&mut load).collect::<Vec<_>>();
+
+                    // the inner product fits without any reduction
+                    let mut inner_product = lazy_chip.mul(ctx, &a[0], &b[0]);
+                    for (a, b) in a.iter().zip(b.iter()).skip(1) {
+                        let prod = lazy_chip.mul(ctx, a, b);
+                        inner_product = lazy_chip.add(ctx, &inner_product, &prod);
+                    }
+                    assert!(!inner_product.is_reduced);
+                    assert_eq!(inner_product.max_bits, 2 * 254 + NUM_PAIRS - 1);
+
+                    // repeated squaring reduces the previous square before each multiplication
+                    let mut pow = a[0].clone();
+                    for _ in 0..4 {
+                        pow = lazy_chip.mul(ctx, &pow, &pow);
+                    }
+                    assert!(!pow.is_reduced);
+
+                    // the difference still fits, so it is only reduced before multiplying by 5
+                    let diff = lazy_chip.sub(ctx, &inner_product, &pow);
+                    assert!(!diff.is_reduced);
+                    let out = lazy_chip.scalar_mul_and_add(ctx, &diff, &a[1], 5);
+                    let out = lazy_chip.negate(ctx, &out);
+
+                    // multiplying by zero needs no constraints on `diff`
+                    let zero = lazy_chip.scalar_mul(ctx, &diff, 0);
+                    let is_zero = lazy_chip.is_zero(ctx, &zero);
+                    is_zero.value().assert_if_known(|v| **v == Fr::one());
+                    let a1 = lazy_chip.scalar_mul_and_add(ctx, &diff, &a[1], 0);
+                    lazy_chip.assert_equal(ctx, &a1, &a[1]);
+
+                    let expected = self
+                        .a
+                        .iter()
+                        .zip(self.b.iter())
+                        .fold(Value::known(Fq::zero()), |acc, (a, b)| acc + *a * *b);
+                    let expected = expected.zip(self.a[0]).zip(self.a[1]).map(|((ip, a0), a1)| {
+                        -((ip - a0.square().square().square().square()) * Fq::from(5) + a1)
+                    });
+                    lazy_chip
+                        .get_assigned_value(&out)
+                        .zip(expected)
+                        .assert_if_known(|(out, expected)| out == expected);
+
+                    let expected =
+                        lazy_chip.load_private(ctx, FpConfig::<Fr, Fq>::fe_to_witness(&expected));
+                    lazy_chip.assert_equal(ctx, &out, &expected);
+                    let out_minus_expected = lazy_chip.sub(ctx, &out, &expected);
+                    let is_zero = lazy_chip.is_zero(ctx, &out_minus_expected);
+                    is_zero.value().assert_if_known(|v| **v == Fr::one());
+
+                    // IMPORTANT: this copies advice cells to enable lookup
+                    // This is not optional.
+                    chip.finalize(ctx);
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_lazy() {
+        let circuit = LazyCircuit {
+            a: (0..NUM_PAIRS).map(|_| Value::known(Fq::random(OsRng))).collect(),
+            b: (0..NUM_PAIRS).map(|_| Value::known(Fq::random(OsRng))).collect(),
+        };
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
@@ -0,0 +1,37 @@
+use crate::ecc::{CurveParams, EccChip};
+use crate::fields::native::NativeFieldChip;
+use crate::halo2_proofs::halo2curves::bn256::{Fq, Fr};
+
+/// The Grumpkin curve `y^2 = x^3 - 17` over the scalar field of BN254. Its group of points has prime order the modulus of
+/// the base field of BN254, so BN254 and Grumpkin form a cycle of curves.
+///
+/// Since the base field is `bn256::Fr`, the elliptic curve operations in a circuit over `bn256::Fr` use [`NativeFieldChip`],
+/// where each coordinate is a single cell.
+#[derive(Clone, Copy, Debug)]
+pub struct Grumpkin;
+
+impl Grumpkin {
+    /// The generator `(1, sqrt(-16))`
+    pub const GENERATOR: (Fr, Fr) = (
+        Fr::from_raw([1, 0, 0, 0]),
+        Fr::from_raw([0x833fc48d823f272c, 0x2d270d45f1181294, 0xcf135e7506a45d63, 0x2]),
+    );
+}
+
+impl CurveParams for Grumpkin {
+    type Base = Fr;
+    type ScalarField = Fq;
+
+    fn coeff_a() -> Fr {
+        Fr::from(0)
+    }
+
+    fn coeff_b() -> Fr {
+        -Fr::from(17)
+    }
+}
+
+pub type GrumpkinChip = EccChip<Fr, NativeFieldChip<Fr>>;
+
+#[cfg(test)]
+mod tests;
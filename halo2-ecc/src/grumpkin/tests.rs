@@ -0,0 +1,151 @@
+#![allow(non_snake_case)]
+use super::*;
+use crate::ecc::EcPoint;
+use crate::fields::{fp::FpStrategy, FieldChip};
+use crate::halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use ff::Field;
+use halo2_base::{utils::fe_to_biguint, AssignedValue, SKIP_FIRST_PASS};
+use rand::rngs::OsRng;
+
+// scalars are loaded as two limbs, each of which is compared to the curve order with `is_less_than` on `SCALAR_LIMB_BITS + 1` bits
+const SCALAR_LIMB_BITS: usize = 128;
+const WINDOW_BITS: usize = 4;
+
+// affine point arithmetic outside of the circuit, with `None` the point at infinity
+type NativePoint = Option<(Fr, Fr)>;
+
+fn add_native(P: NativePoint, Q: NativePoint) -> NativePoint {
+    let ((x1, y1), (x2, y2)) = match (P, Q) {
+        (None, _) => return Q,
+        (_, None) => return P,
+        (Some(P), Some(Q)) => (P, Q),
+    };
+    let lambda = if x1 == x2 {
+        if y1 != y2 || y1 == Fr::zero() {
+            return None;
+        }
+        x1.square() * Fr::from(3) * (y1 + y1).invert().unwrap()
+    } else {
+        (y2 - y1) * (x2 - x1).invert().unwrap()
+    };
+    let x3 = lambda.square() - x1 - x2;
+    Some((x3, lambda * (x1 - x3) - y1))
+}
+
+fn scalar_mul_native(P: NativePoint, scalar: &Fq) -> NativePoint {
+    let scalar = fe_to_biguint(scalar);
+    (0..scalar.bits()).rev().fold(None, |acc, i| {
+        let acc = add_native(acc, acc);
+        if scalar.bit(i) {
+            add_native(acc, P)
+        } else {
+            acc
+        }
+    })
+}
+
+#[test]
+fn test_grumpkin_native() {
+    let (x, y) = Grumpkin::GENERATOR;
+    assert_eq!(y.square(), x.square() * x + Grumpkin::coeff_b());
+    // the order of the group is the modulus of `Fq`
+    let G = Some(Grumpkin::GENERATOR);
+    assert_eq!(scalar_mul_native(G, &-Fq::one()), Some((x, -y)));
+    let a = Fq::random(OsRng);
+    let b = Fq::random(OsRng);
+    assert_eq!(
+        add_native(scalar_mul_native(G, &a), scalar_mul_native(G, &b)),
+        scalar_mul_native(G, &(a + b))
+    );
+}
+
+#[derive(Default)]
+struct GrumpkinMsmCircuit {
+    bases: Vec<(Fr, Fr)>,
+    scalars: Vec<Fq>,
+}
+
+impl Circuit<Fr> for GrumpkinMsmCircuit {
+    type Config = NativeFieldChip<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        NativeFieldChip::configure(meta, FpStrategy::Simple, &[2], &[1], 1, 17, 0, 18)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        config.load_lookup_table(&mut layouter)?;
+        let chip = GrumpkinChip::construct(config.clone());
+
+        let mut first_pass = SKIP_FIRST_PASS;
+        layouter.assign_region(
+            || "grumpkin msm",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = config.new_context(region);
+                let ctx = &mut aux;
+
+                let bases = self
+                    .bases
+                    .iter()
+                    .map(|&(x, y)| {
+                        let P = chip.load_private(ctx, (Value::known(x), Value::known(y)));
+                        chip.assert_is_on_curve::<Grumpkin>(ctx, &P);
+                        P
+                    })
+                    .collect::<Vec<_>>();
+                let scalars = self
+                    .scalars
+                    .iter()
+                    .map(|s| chip.load_scalar(ctx, Value::known(*s), SCALAR_LIMB_BITS))
+                    .collect::<Vec<_>>();
+
+                let check = |P: &EcPoint<Fr, AssignedValue<Fr>>, expected: NativePoint| {
+                    let (x, y) = expected.unwrap();
+                    P.x.value().assert_if_known(|v| **v == x);
+                    P.y.value().assert_if_known(|v| **v == y);
+                };
+
+                let P = chip.scalar_mult::<Grumpkin>(ctx, &bases[0], &scalars[0], WINDOW_BITS);
+                check(&P, scalar_mul_native(Some(self.bases[0]), &self.scalars[0]));
+
+                let msm = chip.variable_base_msm::<Grumpkin>(ctx, &bases, &scalars, WINDOW_BITS);
+                let expected = self
+                    .bases
+                    .iter()
+                    .zip(self.scalars.iter())
+                    .fold(None, |acc, (P, s)| add_native(acc, scalar_mul_native(Some(*P), s)));
+                check(&msm, expected);
+
+                chip.field_chip().finalize(ctx);
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn test_grumpkin_msm() {
+    let k = 18;
+    let bases = (0..4).map(|_| Grumpkin::random_point(OsRng)).collect();
+    let scalars = (0..4).map(|_| Fq::random(OsRng)).collect();
+    let circuit = GrumpkinMsmCircuit { bases, scalars };
+    let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+    prover.assert_satisfied();
+}
@@ -8,8 +8,12 @@ pub mod ecc;
 pub mod fields;
 
 pub mod bn254;
+pub mod grumpkin;
 pub mod secp256k1;
 
+#[cfg(feature = "display")]
+pub mod tuner;
+
 pub use halo2_base;
 pub(crate) use halo2_base::halo2_proofs;
 
@@ -8,16 +8,23 @@ use crate::halo2_proofs::{
 };
 use crate::secp256k1::FpChip;
 use crate::{
-    ecc::{ecdsa::ecdsa_verify_no_pubkey_check, EccChip},
+    ecc::{
+        ecdsa::{ecdsa_verify_no_pubkey_check, ecdsa_verify_no_pubkey_check_glv},
+        EccChip,
+    },
     fields::{fp::FpStrategy, FieldChip},
 };
+#[cfg(feature = "display")]
+use crate::{halo2_proofs::halo2curves::bn256::Fr, tuner::TunableCircuit};
 use halo2_base::utils::{biguint_to_fe, fe_to_biguint, modulus};
-use halo2_base::{utils::PrimeField, SKIP_FIRST_PASS};
+#[cfg(feature = "display")]
+use halo2_base::ContextStats;
+use halo2_base::{utils::PrimeField, Context, SKIP_FIRST_PASS};
 use rand_core::OsRng;
 use serde::{Deserialize, Serialize};
-use std::marker::PhantomData;
+use std::{cell::RefCell, marker::PhantomData};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CircuitParams {
     pub strategy: FpStrategy,
     pub degree: u32,
@@ -29,6 +36,34 @@ pub struct CircuitParams {
     pub num_limbs: usize,
 }
 
+thread_local! {
+    // the params set by `with_params` for the circuits configured in this thread
+    static CIRCUIT_PARAMS: RefCell<Option<CircuitParams>> = RefCell::new(None);
+}
+
+/// Runs `f` with `params` as the parameters of the circuits configured in this thread, e.g., by `MockProver::run` or `keygen_vk`,
+/// instead of the default [`PARAMS`](super::params::PARAMS). The previous parameters are restored afterwards, also if `f` panics.
+///
+/// [`ECDSACircuit`] reads them in [`Circuit::configure`], which has no other way to receive parameters.
+pub fn with_params<T>(params: &CircuitParams, f: impl FnOnce() -> T) -> T {
+    // restores the previous params when dropped
+    struct Restore(Option<CircuitParams>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CIRCUIT_PARAMS.with(|params| params.replace(self.0.take()));
+        }
+    }
+
+    let _restore = Restore(CIRCUIT_PARAMS.with(|prev| prev.replace(Some(params.clone()))));
+    f()
+}
+
+// The parameters of `ECDSACircuit`: the ones set by `with_params` in this thread, otherwise the default `PARAMS`
+fn circuit_params() -> CircuitParams {
+    CIRCUIT_PARAMS.with(|params| params.borrow().clone()).unwrap_or(super::params::PARAMS)
+}
+
+#[derive(Clone)]
 pub struct ECDSACircuit<F> {
     pub r: Option<Fq>,
     pub s: Option<Fq>,
@@ -59,37 +94,73 @@ impl<F: PrimeField> Circuit<F> for ECDSACircuit<F> {
     }
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-        // use std::env::var;
-        // use std::fs::File;
-        // let path = var("ECDSA_CONFIG")
-        //     .unwrap_or_else(|_| "./src/secp256k1/configs/ecdsa_circuit.tmp.config".to_string());
-        // let PARAMS: CircuitParams = serde_json::from_reader(
-        //     File::open(&path).unwrap_or_else(|_| panic!("{path:?} file should exist")),
-        // )
-        // .unwrap();
-
-        use super::params::PARAMS;
+        let params = circuit_params();
 
         FpChip::<F>::configure(
             meta,
-            PARAMS.strategy,
-            &[PARAMS.num_advice],
-            &[PARAMS.num_lookup_advice],
-            PARAMS.num_fixed,
-            PARAMS.lookup_bits,
-            PARAMS.limb_bits,
-            PARAMS.num_limbs,
+            params.strategy,
+            &[params.num_advice],
+            &[params.num_lookup_advice],
+            params.num_fixed,
+            params.lookup_bits,
+            params.limb_bits,
+            params.num_limbs,
             modulus::<Fp>(),
             0,
-            PARAMS.degree as usize,
+            params.degree as usize,
         )
     }
 
-    fn synthesize(
+    fn synthesize(&self, fp_chip: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.synthesize_then(fp_chip, layouter, false, |_| ()).map(|_| ())
+    }
+}
+
+/// Same as [`ECDSACircuit`], but verifies the signature with [`ecdsa_verify_no_pubkey_check_glv`], which takes fewer rows.
+///
+/// The layout differs from `ECDSACircuit`, so the parameters in `configs` do not apply to it.
+#[derive(Clone, Default)]
+pub struct ECDSAGlvCircuit<F>(pub ECDSACircuit<F>);
+
+impl<F: PrimeField> Circuit<F> for ECDSAGlvCircuit<F> {
+    type Config = FpChip<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        ECDSACircuit::configure(meta)
+    }
+
+    fn synthesize(&self, fp_chip: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.0.synthesize_then(fp_chip, layouter, true, |_| ()).map(|_| ())
+    }
+}
+
+#[cfg(feature = "display")]
+impl TunableCircuit for ECDSACircuit<Fr> {
+    fn synthesize_with_stats(
         &self,
         fp_chip: Self::Config,
+        layouter: impl Layouter<Fr>,
+    ) -> Result<ContextStats, Error> {
+        self.synthesize_then(fp_chip, layouter, false, |ctx| ctx.stats())
+            .map(|stats| stats.expect("the circuit is assigned in the last pass of the layouter"))
+    }
+}
+
+impl<F: PrimeField> ECDSACircuit<F> {
+    // Assigns the circuit, verifying the signature with the GLV endomorphism if `glv`, and returns `f` of its `Context` after
+    // `finalize`, or `None` if the assignment is skipped in the first pass of the layouter
+    fn synthesize_then<T>(
+        &self,
+        fp_chip: FpChip<F>,
         mut layouter: impl Layouter<F>,
-    ) -> Result<(), Error> {
+        glv: bool,
+        mut f: impl FnMut(&mut Context<F>) -> T,
+    ) -> Result<Option<T>, Error> {
         fp_chip.range.load_lookup_table(&mut layouter)?;
 
         let limb_bits = fp_chip.limb_bits;
@@ -105,7 +176,7 @@ impl<F: PrimeField> Circuit<F> for ECDSACircuit<F> {
             |region| {
                 if first_pass {
                     first_pass = false;
-                    return Ok(());
+                    return Ok(None);
                 }
 
                 let mut aux = fp_chip.new_context(region);
@@ -150,16 +221,29 @@ impl<F: PrimeField> Circuit<F> for ECDSACircuit<F> {
                     ),
                 );
                 // test ECDSA
-                let ecdsa = ecdsa_verify_no_pubkey_check::<F, Fp, Fq, Secp256k1Affine>(
-                    &ecc_chip.field_chip,
-                    ctx,
-                    &pk_assigned,
-                    &r_assigned,
-                    &s_assigned,
-                    &m_assigned,
-                    4,
-                    4,
-                );
+                let ecdsa = if glv {
+                    ecdsa_verify_no_pubkey_check_glv::<F, Fp, Fq, Secp256k1Affine>(
+                        &ecc_chip.field_chip,
+                        ctx,
+                        &pk_assigned,
+                        &r_assigned,
+                        &s_assigned,
+                        &m_assigned,
+                        4,
+                        4,
+                    )
+                } else {
+                    ecdsa_verify_no_pubkey_check::<F, Fp, Fq, Secp256k1Affine>(
+                        &ecc_chip.field_chip,
+                        ctx,
+                        &pk_assigned,
+                        &r_assigned,
+                        &s_assigned,
+                        &m_assigned,
+                        4,
+                        4,
+                    )
+                };
 
                 // IMPORTANT: this copies cells to the lookup advice column to perform range check lookups
                 // This is not optional.
@@ -171,7 +255,7 @@ impl<F: PrimeField> Circuit<F> for ECDSACircuit<F> {
 
                     ctx.print_stats(&["Range"]);
                 }
-                Ok(())
+                Ok(Some(f(ctx)))
             },
         )
     }
@@ -1,22 +1,35 @@
 #![allow(non_snake_case)]
 use ark_std::{end_timer, start_timer};
 use halo2_base::utils::PrimeField;
+use std::io::Write;
 use std::marker::PhantomData;
-use std::{env::var, io::Write};
 
 use crate::halo2_proofs::{
     arithmetic::CurveAffine,
+    circuit::{Layouter, SimpleFloorPlanner, Value},
     dev::MockProver,
     halo2curves::bn256::{Bn256, Fr, G1Affine},
-    halo2curves::secp256k1::{Fq, Secp256k1Affine},
+    halo2curves::secp256k1::{Fp, Fq, Secp256k1Affine},
     plonk::*,
     transcript::{Blake2bRead, Blake2bWrite, Challenge255},
 };
 use rand_core::OsRng;
 
+use halo2_base::gates::GateInstructions;
 use halo2_base::utils::{biguint_to_fe, fe_to_biguint, modulus};
+use halo2_base::virtual_context::assign_virtual_contexts;
+use halo2_base::{Context, SKIP_FIRST_PASS};
+use rayon::prelude::*;
 
-use crate::secp256k1::ecdsa::{CircuitParams, ECDSACircuit};
+use crate::bigint::{CRTInteger, OverflowInteger};
+use crate::ecc::{
+    ecdsa::{ecdsa_verify_no_pubkey_check, ecdsa_verify_no_pubkey_check_glv_lookup},
+    fixed_base_lookup::FixedBaseLookupConfig,
+    EccChip,
+};
+use crate::fields::{fp::FpConfig, FieldChip};
+use crate::secp256k1::ecdsa::{generate_ecdsa_input, with_params, CircuitParams, ECDSACircuit};
+use crate::secp256k1::FpChip;
 
 #[cfg(test)]
 #[test]
@@ -53,11 +66,29 @@ fn test_secp256k1_ecdsa() {
         _marker: PhantomData,
     };
 
-    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    let prover = with_params(&params, || MockProver::run(K, &circuit, vec![])).unwrap();
     //prover.assert_satisfied();
     assert_eq!(prover.verify(), Ok(()));
 }
 
+#[test]
+fn test_secp256k1_ecdsa_glv() {
+    use crate::secp256k1::{ecdsa::ECDSAGlvCircuit, params::PARAMS};
+
+    let (r, s, msghash, pk, G) = generate_ecdsa_input();
+    let circuit = ECDSAGlvCircuit(ECDSACircuit::<Fr> {
+        r: Some(r),
+        s: Some(s),
+        msghash: Some(msghash),
+        pk: Some(pk),
+        G,
+        _marker: PhantomData,
+    });
+
+    let prover = MockProver::run(PARAMS.degree, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
 #[cfg(test)]
 #[test]
 fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
@@ -72,7 +103,6 @@ fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
     };
     use std::fs::File;
     use std::io::{BufRead, BufReader};
-    use std::{env::set_var, fs};
 
     let _rng = OsRng;
 
@@ -101,12 +131,6 @@ fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
         println!("---------------------- degree = {} ------------------------------", k);
 
         {
-            folder.pop();
-            folder.push("configs/ecdsa_circuit.tmp.config");
-            set_var("ECDSA_CONFIG", &folder);
-            let mut f = std::fs::File::create(folder.as_path())?;
-            write!(f, "{}", serde_json::to_string(&bench_params).unwrap())?;
-            folder.pop();
             folder.pop();
             folder.push("keys")
         }
@@ -121,7 +145,7 @@ fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
         end_timer!(params_time);
 
         let vk_time = start_timer!(|| "Time elapsed in generating vkey");
-        let vk = keygen_vk(&params, &circuit)?;
+        let vk = with_params(&bench_params, || keygen_vk(&params, &circuit))?;
         end_timer!(vk_time);
 
         // // write the verifying key to a file
@@ -137,7 +161,7 @@ fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
         folder.push("data");
 
         let pk_time = start_timer!(|| "Time elapsed in generating pkey");
-        let pk = keygen_pk(&params, vk, &circuit)?;
+        let pk = with_params(&bench_params, || keygen_pk(&params, vk, &circuit))?;
         end_timer!(pk_time);
 
         // write the proving key to a file
@@ -178,14 +202,16 @@ fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
         // create a proof
         let proof_time = start_timer!(|| "Proving time");
         let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
-        create_proof::<
-            KZGCommitmentScheme<Bn256>,
-            ProverSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            _,
-            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-            ECDSACircuit<Fr>,
-        >(&params, &pk, &[proof_circuit], &[&[]], &mut rng, &mut transcript)?;
+        with_params(&bench_params, || {
+            create_proof::<
+                KZGCommitmentScheme<Bn256>,
+                ProverSHPLONK<'_, Bn256>,
+                Challenge255<G1Affine>,
+                _,
+                Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+                ECDSACircuit<Fr>,
+            >(&params, &pk, &[proof_circuit], &[&[]], &mut rng, &mut transcript)
+        })?;
         let proof = transcript.finalize();
         end_timer!(proof_time);
 
@@ -219,7 +245,6 @@ fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
         >(verifier_params, pk.get_vk(), strategy, &[&[]], &mut transcript)
         .is_ok());
         end_timer!(verify_time);
-        fs::remove_file(var("ECDSA_CONFIG").unwrap())?;
 
         writeln!(
             fs_results,
@@ -238,3 +263,313 @@ fn bench_secp256k1_ecdsa() -> Result<(), Box<dyn std::error::Error>> {
     }
     Ok(())
 }
+
+#[cfg(feature = "display")]
+#[test]
+fn test_secp256k1_ecdsa_tuner() {
+    use crate::secp256k1::params::PARAMS;
+    use crate::tuner::{dry_run, recommend_params, write_params};
+
+    let G = Secp256k1Affine::generator();
+    let sk = <Secp256k1Affine as CurveAffine>::ScalarExt::random(OsRng);
+    let pubkey = Secp256k1Affine::from(G * sk);
+    let msg_hash = <Secp256k1Affine as CurveAffine>::ScalarExt::random(OsRng);
+
+    let k = <Secp256k1Affine as CurveAffine>::ScalarExt::random(OsRng);
+    let k_inv = k.invert().unwrap();
+
+    let r_point = Secp256k1Affine::from(G * k).coordinates().unwrap();
+    let x_bigint = fe_to_biguint(r_point.x());
+    let r = biguint_to_fe::<Fq>(&(x_bigint % modulus::<Fq>()));
+    let s = k_inv * (msg_hash + (r * sk));
+
+    let circuit = ECDSACircuit::<Fr> {
+        r: Some(r),
+        s: Some(s),
+        msghash: Some(msg_hash),
+        pk: Some(pubkey),
+        G,
+        _marker: PhantomData,
+    };
+    // oversize layout: a single advice column with many rows
+    let dry_run_params = CircuitParams { degree: 20, lookup_bits: 16, ..PARAMS };
+    let stats = dry_run(&dry_run_params, &circuit);
+
+    let k = 18;
+    let params = recommend_params(&circuit, &dry_run_params, &stats, k);
+    assert_eq!(params.degree, k);
+    assert!(params.num_advice > 1);
+    // `recommend_params` already checks them, but make sure the recommended params pass
+    let prover = with_params(&params, || MockProver::run(k, &circuit, vec![])).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let path =
+        std::env::temp_dir().join(format!("ecdsa_circuit_tuned_{}.config", std::process::id()));
+    write_params(&params, &path).unwrap();
+    let written: CircuitParams =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written.degree, params.degree);
+    assert_eq!(written.num_advice, params.num_advice);
+    assert_eq!(written.num_lookup_advice, params.num_lookup_advice);
+    assert_eq!(written.num_fixed, params.num_fixed);
+}
+
+// Same as `ECDSACircuit`, but computes `u1 * G` with a fixed base lookup table of `G`
+#[derive(Default)]
+struct ECDSALookupCircuit {
+    r: Option<Fq>,
+    s: Option<Fq>,
+    msghash: Option<Fq>,
+    pk: Option<Secp256k1Affine>,
+}
+
+impl Circuit<Fr> for ECDSALookupCircuit {
+    type Config = (FpChip<Fr>, FixedBaseLookupConfig<Secp256k1Affine>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        use crate::secp256k1::params::PARAMS;
+
+        let fp_chip = FpChip::<Fr>::configure(
+            meta,
+            PARAMS.strategy,
+            &[PARAMS.num_advice],
+            &[PARAMS.num_lookup_advice],
+            PARAMS.num_fixed,
+            PARAMS.lookup_bits,
+            PARAMS.limb_bits,
+            PARAMS.num_limbs,
+            modulus::<Fp>(),
+            0,
+            PARAMS.degree as usize,
+        );
+        let table = FixedBaseLookupConfig::configure(
+            meta,
+            &[Secp256k1Affine::generator()],
+            PARAMS.limb_bits * PARAMS.num_limbs,
+            8,
+            PARAMS.limb_bits,
+            PARAMS.num_limbs,
+            1,
+            0,
+            PARAMS.degree as usize,
+        );
+        (fp_chip, table)
+    }
+
+    fn synthesize(
+        &self,
+        (fp_chip, table): Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        fp_chip.range.load_lookup_table(&mut layouter)?;
+        table.load_lookup_table(&mut layouter)?;
+
+        let mut first_pass = SKIP_FIRST_PASS;
+        layouter.assign_region(
+            || "ECDSA lookup",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = fp_chip.new_context(region);
+                let ctx = &mut aux;
+
+                let fq_chip = FpConfig::<Fr, Fq>::construct(
+                    fp_chip.range.clone(),
+                    fp_chip.limb_bits,
+                    fp_chip.num_limbs,
+                    modulus::<Fq>(),
+                );
+                let [m, r, s] = [self.msghash, self.r, self.s].map(|x| {
+                    fq_chip.load_private(
+                        ctx,
+                        FpConfig::<Fr, Fq>::fe_to_witness(
+                            &x.map_or(Value::unknown(), Value::known),
+                        ),
+                    )
+                });
+                let ecc_chip = EccChip::<Fr, FpChip<Fr>>::construct(fp_chip.clone());
+                let pk = ecc_chip.load_private(
+                    ctx,
+                    (
+                        self.pk.map_or(Value::unknown(), |pt| Value::known(pt.x)),
+                        self.pk.map_or(Value::unknown(), |pt| Value::known(pt.y)),
+                    ),
+                );
+                let ecdsa = ecdsa_verify_no_pubkey_check_glv_lookup::<Fr, Fp, Fq, Secp256k1Affine>(
+                    &fp_chip, &table, ctx, &pk, &r, &s, &m, 4,
+                );
+                fp_chip.range.gate.assert_is_const(ctx, &ecdsa, Fr::from(1));
+
+                fp_chip.finalize(ctx);
+                table.finalize(ctx);
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_secp256k1_ecdsa_lookup() {
+    use crate::secp256k1::params::PARAMS;
+
+    let (r, s, msghash, pk, _) = generate_ecdsa_input();
+    let circuit =
+        ECDSALookupCircuit { r: Some(r), s: Some(s), msghash: Some(msghash), pk: Some(pk) };
+    let prover = MockProver::run(PARAMS.degree, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // a wrong signature is rejected
+    let circuit = ECDSALookupCircuit {
+        r: Some(r),
+        s: Some(s),
+        msghash: Some(msghash + Fq::from(1)),
+        pk: Some(pk),
+    };
+    let prover = MockProver::run(PARAMS.degree, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+// Verifies a batch of signatures, each in its own virtual context, witnessed in parallel
+#[derive(Default)]
+struct ECDSABatchCircuit {
+    // `(r, s, msghash, pk)` of each signature
+    signatures: Vec<(Fq, Fq, Fq, Secp256k1Affine)>,
+}
+
+const BATCH_PARAMS: CircuitParams =
+    CircuitParams { num_advice: 4, ..crate::secp256k1::params::PARAMS };
+
+// Returns `a`, a `CRTInteger` of the real context, as a `CRTInteger` of the virtual context `ctx`
+fn load_external_crt<'v>(ctx: &mut Context<'v, Fr>, a: &CRTInteger<'v, Fr>) -> CRTInteger<'v, Fr> {
+    let limbs = a.truncation.limbs.iter().map(|limb| ctx.load_external(limb)).collect();
+    CRTInteger::construct(
+        OverflowInteger::construct(limbs, a.truncation.max_limb_bits),
+        ctx.load_external(&a.native),
+        a.value.clone(),
+    )
+}
+
+impl Circuit<Fr> for ECDSABatchCircuit {
+    type Config = FpChip<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        FpChip::<Fr>::configure(
+            meta,
+            BATCH_PARAMS.strategy,
+            &[BATCH_PARAMS.num_advice],
+            &[BATCH_PARAMS.num_lookup_advice],
+            BATCH_PARAMS.num_fixed,
+            BATCH_PARAMS.lookup_bits,
+            BATCH_PARAMS.limb_bits,
+            BATCH_PARAMS.num_limbs,
+            modulus::<Fp>(),
+            0,
+            BATCH_PARAMS.degree as usize,
+        )
+    }
+
+    fn synthesize(
+        &self,
+        fp_chip: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        fp_chip.range.load_lookup_table(&mut layouter)?;
+
+        let mut first_pass = SKIP_FIRST_PASS;
+        layouter.assign_region(
+            || "ECDSA batch",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = fp_chip.new_context(region);
+                let ctx = &mut aux;
+
+                let fq_chip = FpConfig::<Fr, Fq>::construct(
+                    fp_chip.range.clone(),
+                    fp_chip.limb_bits,
+                    fp_chip.num_limbs,
+                    modulus::<Fq>(),
+                );
+                let ecc_chip = EccChip::<Fr, FpChip<Fr>>::construct(fp_chip.clone());
+                // the message hashes are loaded in the real context and shared with the virtual contexts
+                let msghashes = self
+                    .signatures
+                    .iter()
+                    .map(|(_, _, m, _)| {
+                        fq_chip
+                            .load_private(ctx, FpConfig::<Fr, Fq>::fe_to_witness(&Value::known(*m)))
+                    })
+                    .collect::<Vec<_>>();
+
+                let (virtual_ctxs, results): (Vec<_>, Vec<_>) = self
+                    .signatures
+                    .par_iter()
+                    .zip(msghashes.par_iter())
+                    .map(|(&(r, s, _, pk), m)| {
+                        let mut vctx = fp_chip.new_virtual_context();
+                        let ctx = &mut vctx;
+                        let m = load_external_crt(ctx, m);
+                        let [r, s] = [r, s].map(|x| {
+                            fq_chip.load_private(
+                                ctx,
+                                FpConfig::<Fr, Fq>::fe_to_witness(&Value::known(x)),
+                            )
+                        });
+                        let pk =
+                            ecc_chip.load_private(ctx, (Value::known(pk.x), Value::known(pk.y)));
+                        let res = ecdsa_verify_no_pubkey_check::<Fr, Fp, Fq, Secp256k1Affine>(
+                            &fp_chip, ctx, &pk, &r, &s, &m, 4, 4,
+                        );
+                        (vctx.into_virtual(), res)
+                    })
+                    .unzip();
+
+                let assigned = assign_virtual_contexts(&fp_chip.range.gate, ctx, virtual_ctxs);
+                for (assigned, res) in assigned.iter().zip(results.iter()) {
+                    fp_chip.range.gate.assert_is_const(ctx, assigned.get(res), Fr::from(1));
+                }
+
+                fp_chip.finalize(ctx);
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_secp256k1_ecdsa_virtual_contexts() {
+    let mut signatures = (0..3)
+        .map(|_| {
+            let (r, s, msghash, pk, _) = generate_ecdsa_input();
+            (r, s, msghash, pk)
+        })
+        .collect::<Vec<_>>();
+    let circuit = ECDSABatchCircuit { signatures: signatures.clone() };
+    let prover = MockProver::run(BATCH_PARAMS.degree, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // a wrong signature in one of the virtual contexts is rejected
+    signatures[1].2 += Fq::from(1);
+    let circuit = ECDSABatchCircuit { signatures };
+    let prover = MockProver::run(BATCH_PARAMS.degree, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
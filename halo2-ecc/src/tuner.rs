@@ -0,0 +1,117 @@
+//! Chooses circuit parameters from a dry run of a circuit's synthesis.
+//!
+//! The dry run synthesizes the circuit with an oversize layout, e.g., a large degree with a single advice column, and collects
+//! the cell statistics of the circuit's [`Context`](halo2_base::Context) with [`TunableCircuit::synthesize_with_stats`].
+use crate::halo2_proofs::{
+    circuit::Layouter,
+    dev::MockProver,
+    halo2curves::bn256::Fr,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2_base::ContextStats;
+use std::{cell::RefCell, fs::File, io::BufWriter, path::Path};
+
+pub use crate::secp256k1::ecdsa::{with_params, CircuitParams};
+
+/// A circuit configured with the [`CircuitParams`] set by [`with_params`], whose synthesis can return the statistics of its `Context`
+pub trait TunableCircuit: Circuit<Fr> + Clone {
+    /// Same as [`Circuit::synthesize`], but returns the statistics of the circuit's `Context` after `finalize`
+    fn synthesize_with_stats(
+        &self,
+        config: Self::Config,
+        layouter: impl Layouter<Fr>,
+    ) -> Result<ContextStats, Error>;
+}
+
+// Synthesizes `circuit` and keeps the statistics of its `Context`
+struct StatsCircuit<C> {
+    circuit: C,
+    stats: RefCell<Option<ContextStats>>,
+}
+
+impl<C: TunableCircuit> Circuit<Fr> for StatsCircuit<C> {
+    type Config = C::Config;
+    type FloorPlanner = C::FloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self { circuit: self.circuit.without_witnesses(), stats: RefCell::new(None) }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        let stats = self.circuit.synthesize_with_stats(config, layouter)?;
+        self.stats.replace(Some(stats));
+        Ok(())
+    }
+}
+
+/// Runs the synthesis of `circuit` configured with `dry_run_params` on a [`MockProver`] and returns the statistics of its `Context`.
+///
+/// `dry_run_params` must have enough columns for the synthesis to succeed, for example a large degree with a single advice column.
+/// The constraints are not checked.
+pub fn dry_run<C: TunableCircuit>(dry_run_params: &CircuitParams, circuit: &C) -> ContextStats {
+    let circuit = StatsCircuit { circuit: circuit.clone(), stats: RefCell::new(None) };
+    with_params(dry_run_params, || {
+        MockProver::run(dry_run_params.degree, &circuit, vec![])
+            .expect("dry run synthesis should not fail")
+    });
+    circuit.stats.into_inner().expect("dry run should synthesize the circuit")
+}
+
+/// Returns the minimal parameters to fit `circuit` into `2^k` rows, given the `stats` of a [`dry_run`] of the circuit with
+/// parameters `dry_run_params`.
+///
+/// The number of cells used depends on `lookup_bits`, `limb_bits`, and `num_limbs`, so these are kept from `dry_run_params`.
+/// In particular `dry_run_params.lookup_bits` must be less than `k` so the lookup table fits.
+///
+/// The recommended parameters are checked by running `circuit` with them on a [`MockProver`]. Assigning a region of cells moves
+/// to the next advice column when it does not fit in the current one, so this panics if the circuit's regions are too long
+/// compared to `2^k` to fit in the minimal number of advice columns.
+pub fn recommend_params<C: TunableCircuit>(
+    circuit: &C,
+    dry_run_params: &CircuitParams,
+    stats: &ContextStats,
+    k: u32,
+) -> CircuitParams {
+    assert!(
+        dry_run_params.lookup_bits < k as usize,
+        "lookup table with {} bits does not fit in 2^{k} rows; do the dry run with smaller lookup_bits",
+        dry_run_params.lookup_bits
+    );
+    // the rows reserved for blinding factors do not depend on the degree
+    let unusable_rows = (1usize << dry_run_params.degree) - stats.max_rows;
+    let max_rows = (1usize << k) - unusable_rows;
+    let num_columns = |cells: usize| (cells + max_rows - 1) / max_rows;
+    // `CircuitParams` only describes the first phase
+    debug_assert!(stats.advice_cells[1..].iter().all(|&cells| cells == 0));
+
+    let params = CircuitParams {
+        strategy: dry_run_params.strategy.clone(),
+        degree: k,
+        num_advice: num_columns(stats.advice_cells[0]).max(1),
+        num_lookup_advice: num_columns(stats.lookup_cells[0]),
+        num_fixed: num_columns(stats.fixed_cells).max(1),
+        lookup_bits: dry_run_params.lookup_bits,
+        limb_bits: dry_run_params.limb_bits,
+        num_limbs: dry_run_params.num_limbs,
+    };
+
+    let prover =
+        with_params(&params, || MockProver::run(k, circuit, vec![])).unwrap_or_else(|err| {
+            panic!("circuit does not fit in the recommended {params:?}: {err:?}")
+        });
+    if let Err(failures) = prover.verify() {
+        panic!("circuit is not satisfied with the recommended {params:?}: {failures:?}");
+    }
+    params
+}
+
+/// Writes `params` as JSON to `path`, in the format of the `.config` files in this crate
+pub fn write_params(params: &CircuitParams, path: impl AsRef<Path>) -> std::io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, params)?;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use super::util::{
     constraint_builder::BaseConstraintBuilder,
     eth_types::Field,
-    expression::{and, not, select, Expr},
+    expression::{and, not, select, sum, Expr},
     field_xor, get_absorb_positions, get_num_bits_per_lookup, into_bits, load_lookup_table,
     load_normalize_table, load_pack_table, pack, pack_u64, pack_with_base, rotate, scatter,
     target_part_sizes, to_bytes, unpack, CHI_BASE_LOOKUP_TABLE, NUM_BYTES_PER_WORD, NUM_ROUNDS,
@@ -20,9 +20,9 @@ use halo2_base::AssignedValue;
 use itertools::Itertools;
 use log::{debug, info};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use std::env::var;
 use std::marker::PhantomData;
 
+pub mod circuit;
 #[cfg(test)]
 mod tests;
 
@@ -32,36 +32,44 @@ const THETA_C_LOOKUP_RANGE: usize = 6;
 const RHO_PI_LOOKUP_RANGE: usize = 4;
 const CHI_BASE_LOOKUP_RANGE: usize = 5;
 
-pub fn get_num_rows_per_round() -> usize {
-    var("KECCAK_ROWS")
-        .unwrap_or_else(|_| "25".to_string())
-        .parse()
-        .expect("Cannot parse KECCAK_ROWS env var as usize")
+/// Configuration parameters to define [`KeccakCircuitConfig`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeccakConfigParams {
+    /// The circuit degree, i.e., circuit has 2<sup>k</sup> rows
+    pub k: u32,
+    /// The number of rows to use for each round in the keccak_f permutation
+    pub rows_per_round: usize,
 }
 
-fn get_num_bits_per_absorb_lookup() -> usize {
-    get_num_bits_per_lookup(ABSORB_LOOKUP_RANGE)
+impl KeccakConfigParams {
+    pub fn new(k: u32, rows_per_round: usize) -> Self {
+        Self { k, rows_per_round }
+    }
+}
+
+fn get_num_bits_per_absorb_lookup(k: u32) -> usize {
+    get_num_bits_per_lookup(ABSORB_LOOKUP_RANGE, k)
 }
 
-fn get_num_bits_per_theta_c_lookup() -> usize {
-    get_num_bits_per_lookup(THETA_C_LOOKUP_RANGE)
+fn get_num_bits_per_theta_c_lookup(k: u32) -> usize {
+    get_num_bits_per_lookup(THETA_C_LOOKUP_RANGE, k)
 }
 
-fn get_num_bits_per_rho_pi_lookup() -> usize {
-    get_num_bits_per_lookup(CHI_BASE_LOOKUP_RANGE.max(RHO_PI_LOOKUP_RANGE))
+fn get_num_bits_per_rho_pi_lookup(k: u32) -> usize {
+    get_num_bits_per_lookup(CHI_BASE_LOOKUP_RANGE.max(RHO_PI_LOOKUP_RANGE), k)
 }
 
-fn get_num_bits_per_base_chi_lookup() -> usize {
-    get_num_bits_per_lookup(CHI_BASE_LOOKUP_RANGE.max(RHO_PI_LOOKUP_RANGE))
+fn get_num_bits_per_base_chi_lookup(k: u32) -> usize {
+    get_num_bits_per_lookup(CHI_BASE_LOOKUP_RANGE.max(RHO_PI_LOOKUP_RANGE), k)
 }
 
 /// The number of keccak_f's that can be done in this circuit
 ///
 /// `num_rows` should be number of usable rows without blinding factors
-pub fn get_keccak_capacity(num_rows: usize) -> usize {
+pub fn get_keccak_capacity(num_rows: usize, rows_per_round: usize) -> usize {
     // - 1 because we have a dummy round at the very beginning of multi_keccak
-    // - NUM_WORDS_TO_ABSORB because `absorb_data_next` and `absorb_result_next` query `NUM_WORDS_TO_ABSORB * get_num_rows_per_round()` beyond any row where `q_absorb == 1`
-    (num_rows / get_num_rows_per_round() - 1 - NUM_WORDS_TO_ABSORB) / (NUM_ROUNDS + 1)
+    // - NUM_WORDS_TO_ABSORB because `absorb_data_next` and `absorb_result_next` query `NUM_WORDS_TO_ABSORB * rows_per_round` beyond any row where `q_absorb == 1`
+    (num_rows / rows_per_round - 1 - NUM_WORDS_TO_ABSORB) / (NUM_ROUNDS + 1)
 }
 
 pub fn get_num_keccak_f(byte_length: usize) -> usize {
@@ -96,8 +104,7 @@ pub struct KeccakRow<F: FieldExt> {
     round_cst: F,
     is_final: bool,
     cell_values: Vec<F>,
-    // We have no need for length as RLC equality checks length implicitly
-    // length: usize,
+    length: usize,
     // SecondPhase values will be assigned separately
     // data_rlc: Value<F>,
     // hash_rlc: Value<F>,
@@ -117,6 +124,7 @@ impl<F: FieldExt> KeccakRow<F> {
                 round_cst: F::zero(),
                 is_final: false,
                 cell_values: Vec::new(),
+                length: 0,
             })
             .collect()
     }
@@ -360,8 +368,8 @@ pub struct KeccakTable {
     pub is_enabled: Column<Advice>,
     /// Byte array input as `RLC(reversed(input))`
     pub input_rlc: Column<Advice>, // RLC of input bytes
-    // Byte array input length
-    // pub input_len: Column<Advice>,
+    /// Byte array input length
+    pub input_len: Column<Advice>,
     /// RLC of the hash result
     pub output_rlc: Column<Advice>, // RLC of hash of input bytes
 }
@@ -371,14 +379,11 @@ impl KeccakTable {
     pub fn construct<F: Field>(meta: &mut ConstraintSystem<F>) -> Self {
         let input_rlc = meta.advice_column_in(SecondPhase);
         let output_rlc = meta.advice_column_in(SecondPhase);
+        let input_len = meta.advice_column();
         meta.enable_equality(input_rlc);
+        meta.enable_equality(input_len);
         meta.enable_equality(output_rlc);
-        Self {
-            is_enabled: meta.advice_column(),
-            input_rlc,
-            // input_len: meta.advice_column(),
-            output_rlc,
-        }
+        Self { is_enabled: meta.advice_column(), input_rlc, input_len, output_rlc }
     }
 }
 
@@ -390,25 +395,24 @@ pub fn assign_advice_custom<'v, F: Field>(
 ) -> AssignedValue<'v, F> {
     #[cfg(feature = "halo2-axiom")]
     {
-        AssignedValue {
-            cell: region.assign_advice(column, offset, value).unwrap(),
+        AssignedValue::new(
+            region.assign_advice(column, offset, value).unwrap(),
             #[cfg(feature = "display")]
-            context_id: usize::MAX,
-        }
+            usize::MAX,
+        )
     }
     #[cfg(feature = "halo2-pse")]
     {
-        AssignedValue {
-            cell: region
+        AssignedValue::new(
+            region
                 .assign_advice(|| format!("assign advice {}", offset), column, offset, || value)
                 .unwrap()
                 .cell(),
             value,
-            row_offset: offset,
-            _marker: PhantomData,
+            offset,
             #[cfg(feature = "display")]
-            context_id: usize::MAX,
-        }
+            usize::MAX,
+        )
     }
 }
 
@@ -836,12 +840,15 @@ pub struct KeccakCircuitConfig<F> {
     pub keccak_table: KeccakTable,
 
     cell_manager: CellManager<F>,
+    // the cell holding the packed hash word that is squeezed in a round, used to expose the digest
+    squeeze_from: Cell<F>,
     round_cst: Column<Fixed>,
     normalize_3: [TableColumn; 2],
     normalize_4: [TableColumn; 2],
     normalize_6: [TableColumn; 2],
     chi_base_table: [TableColumn; 2],
     pack_table: [TableColumn; 2],
+    parameters: KeccakConfigParams,
     _marker: PhantomData<F>,
 }
 
@@ -849,8 +856,18 @@ impl<F: Field> KeccakCircuitConfig<F> {
     pub fn challenge(&self) -> Challenge {
         self.challenge
     }
+    pub fn parameters(&self) -> KeccakConfigParams {
+        self.parameters
+    }
     /// Return a new KeccakCircuitConfig
-    pub fn new(meta: &mut ConstraintSystem<F>, challenge: Challenge) -> Self {
+    pub fn new(
+        meta: &mut ConstraintSystem<F>,
+        challenge: Challenge,
+        parameters: KeccakConfigParams,
+    ) -> Self {
+        let k = parameters.k;
+        let num_rows_per_round = parameters.rows_per_round;
+
         let q_enable = meta.fixed_column();
         // let q_enable_row = meta.fixed_column();
         let q_first = meta.fixed_column();
@@ -863,7 +880,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
         let keccak_table = KeccakTable::construct(meta);
 
         let is_final = keccak_table.is_enabled;
-        // let length = keccak_table.input_len;
+        let length = keccak_table.input_len;
         let data_rlc = keccak_table.input_rlc;
         let hash_rlc = keccak_table.output_rlc;
 
@@ -873,8 +890,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
         let chi_base_table = array_init::array_init(|_| meta.lookup_table_column());
         let pack_table = array_init::array_init(|_| meta.lookup_table_column());
 
-        let num_rows_per_round = get_num_rows_per_round();
-        let mut cell_manager = CellManager::new(get_num_rows_per_round());
+        let mut cell_manager = CellManager::new(num_rows_per_round);
         let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
         let mut total_lookup_counter = 0;
 
@@ -925,7 +941,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
         // rlc.
         cell_manager.start_region();
         let mut lookup_counter = 0;
-        let part_size = get_num_bits_per_absorb_lookup();
+        let part_size = get_num_bits_per_absorb_lookup(k);
         let input = absorb_from.expr() + absorb_data.expr();
         let absorb_fat =
             split::expr(meta, &mut cell_manager, &mut cb, input, 0, part_size, false, None);
@@ -984,7 +1000,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
         // that allows us to also calculate the rotated value "for free".
         cell_manager.start_region();
         let mut lookup_counter = 0;
-        let part_size_c = get_num_bits_per_theta_c_lookup();
+        let part_size_c = get_num_bits_per_theta_c_lookup(k);
         let mut c_parts = Vec::new();
         for s in s.iter() {
             // Calculate c and split into parts
@@ -1044,7 +1060,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
         // `s[j][2 * i + 3 * j) % 5] = normalize(rot(s[i][j], RHOM[i][j]))`.
         cell_manager.start_region();
         let mut lookup_counter = 0;
-        let part_size = get_num_bits_per_base_chi_lookup();
+        let part_size = get_num_bits_per_base_chi_lookup(k);
         // To combine the rho/pi/chi steps we have to ensure a specific layout so
         // query those cells here first.
         // For chi we have to do `s[i][j] ^ ((~s[(i+1)%5][j]) & s[(i+2)%5][j])`. `j`
@@ -1129,7 +1145,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
         // s[(i+2)%5][j])` five times, on each row (no selector needed).
         // This is calculated by making use of `CHI_BASE_LOOKUP_TABLE`.
         let mut lookup_counter = 0;
-        let part_size_base = get_num_bits_per_base_chi_lookup();
+        let part_size_base = get_num_bits_per_base_chi_lookup(k);
         for idx in 0..num_columns {
             // First fetch the cells we wan to use
             let mut input: [Expression<F>; 5] = array_init::array_init(|_| 0.expr());
@@ -1171,7 +1187,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
         // iota
         // Simply do the single xor on state [0][0].
         cell_manager.start_region();
-        let part_size = get_num_bits_per_absorb_lookup();
+        let part_size = get_num_bits_per_absorb_lookup(k);
         let input = s[0][0].clone() + round_cst_expr.clone();
         let iota_parts =
             split::expr(meta, &mut cell_manager, &mut cb, input, 0, part_size, false, None);
@@ -1203,6 +1219,8 @@ impl<F: Field> KeccakCircuitConfig<F> {
 
         // Squeeze data
         let squeeze_from = cell_manager.query_cell(meta);
+        // allow the squeezed words to be copied elsewhere, e.g., to instances
+        meta.enable_equality(squeeze_from.column.unwrap());
         let mut squeeze_from_prev = vec![0u64.expr(); NUM_WORDS_TO_SQUEEZE];
         for (idx, squeeze_from_prev) in squeeze_from_prev.iter_mut().enumerate() {
             let rot = (-(idx as i32) - 1) * num_rows_per_round as i32;
@@ -1446,15 +1464,26 @@ impl<F: Field> KeccakCircuitConfig<F> {
         // TODO: there is probably a way to only require NUM_BYTES_PER_WORD instead of
         // NUM_BYTES_PER_WORD + 1 rows per round, but for simplicity and to keep the
         // gate degree at 3, we just do the obvious thing for now Input data rlc
-        meta.create_gate("data rlc", |meta| {
+        meta.create_gate("length and data rlc", |meta| {
             let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
 
             let q_padding = meta.query_fixed(q_padding, Rotation::cur());
             let start_new_hash_prev = start_new_hash(meta, Rotation(-(num_rows_per_round as i32)));
+            let length_prev = meta.query_advice(length, Rotation(-(num_rows_per_round as i32)));
+            let length = meta.query_advice(length, Rotation::cur());
             let data_rlc_prev = meta.query_advice(data_rlc, Rotation(-(num_rows_per_round as i32)));
 
             // Update the length/data_rlc on rows where we absorb data
             cb.condition(q_padding.expr(), |cb| {
+                // Length increases by the number of bytes that aren't padding
+                cb.require_equal(
+                    "update length",
+                    length.clone(),
+                    length_prev.clone() * not::expr(start_new_hash_prev.expr())
+                        + sum::expr(
+                            is_paddings.iter().map(|is_padding| not::expr(is_padding.expr())),
+                        ),
+                );
                 let challenge_expr = meta.query_challenge(challenge);
                 // Use intermediate cells to keep the degree low
                 let mut new_data_rlc =
@@ -1493,6 +1522,7 @@ impl<F: Field> KeccakCircuitConfig<F> {
                     not::expr(q_padding),
                 ]),
                 |cb| {
+                    cb.require_equal("length equality check", length.clone(), length_prev.clone());
                     cb.require_equal(
                         "data_rlc equality check",
                         meta.query_advice(data_rlc, Rotation::cur()),
@@ -1509,18 +1539,17 @@ impl<F: Field> KeccakCircuitConfig<F> {
         #[cfg(feature = "display")]
         {
             println!("Total Keccak Columns: {}", cell_manager.get_width());
-            std::env::set_var("KECCAK_ADVICE_COLUMNS", cell_manager.get_width().to_string());
         }
         #[cfg(not(feature = "display"))]
         info!("Total Keccak Columns: {}", cell_manager.get_width());
         info!("num unused cells: {}", cell_manager.get_num_unused_cells());
-        info!("part_size absorb: {}", get_num_bits_per_absorb_lookup());
-        info!("part_size theta: {}", get_num_bits_per_theta_c_lookup());
-        info!("part_size theta c: {}", get_num_bits_per_lookup(THETA_C_LOOKUP_RANGE));
-        info!("part_size theta t: {}", get_num_bits_per_lookup(4));
-        info!("part_size rho/pi: {}", get_num_bits_per_rho_pi_lookup());
-        info!("part_size chi base: {}", get_num_bits_per_base_chi_lookup());
-        info!("uniform part sizes: {:?}", target_part_sizes(get_num_bits_per_theta_c_lookup()));
+        info!("part_size absorb: {}", get_num_bits_per_absorb_lookup(k));
+        info!("part_size theta: {}", get_num_bits_per_theta_c_lookup(k));
+        info!("part_size theta c: {}", get_num_bits_per_lookup(THETA_C_LOOKUP_RANGE, k));
+        info!("part_size theta t: {}", get_num_bits_per_lookup(4, k));
+        info!("part_size rho/pi: {}", get_num_bits_per_rho_pi_lookup(k));
+        info!("part_size chi base: {}", get_num_bits_per_base_chi_lookup(k));
+        info!("uniform part sizes: {:?}", target_part_sizes(get_num_bits_per_theta_c_lookup(k)));
 
         KeccakCircuitConfig {
             challenge,
@@ -1534,25 +1563,52 @@ impl<F: Field> KeccakCircuitConfig<F> {
             q_padding_last,
             keccak_table,
             cell_manager,
+            squeeze_from,
             round_cst,
             normalize_3,
             normalize_4,
             normalize_6,
             chi_base_table,
             pack_table,
+            parameters,
             _marker: PhantomData,
         }
     }
 }
 
+/// The cells of a [`KeccakRow`] that may be copied elsewhere after assignment
+#[derive(Clone, Debug)]
+pub struct KeccakAssignedRow<'v, F: Field> {
+    pub is_final: AssignedValue<'v, F>,
+    pub length: AssignedValue<'v, F>,
+    /// The packed hash word squeezed in this row, if this row holds one
+    pub squeeze_packed: Option<AssignedValue<'v, F>>,
+}
+
 impl<F: Field> KeccakCircuitConfig<F> {
-    pub fn assign(&self, region: &mut Region<'_, F>, witness: &[KeccakRow<F>]) {
-        for (offset, keccak_row) in witness.iter().enumerate() {
-            self.set_row(region, offset, keccak_row);
-        }
+    pub fn assign<'v>(
+        &self,
+        region: &mut Region<'_, F>,
+        witness: &[KeccakRow<F>],
+    ) -> Vec<KeccakAssignedRow<'v, F>> {
+        witness
+            .iter()
+            .enumerate()
+            .map(|(offset, keccak_row)| self.set_row(region, offset, keccak_row))
+            .collect()
+    }
+
+    /// The offset, relative to the first row of a round, of the row holding the squeezed hash word
+    pub fn squeeze_rotation(&self) -> usize {
+        self.squeeze_from.rotation as usize
     }
 
-    pub fn set_row(&self, region: &mut Region<'_, F>, offset: usize, row: &KeccakRow<F>) {
+    pub fn set_row<'v>(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        row: &KeccakRow<F>,
+    ) -> KeccakAssignedRow<'v, F> {
         // Fixed selectors
         for (_, column, value) in &[
             ("q_enable", self.q_enable, F::from(row.q_enable)),
@@ -1566,31 +1622,46 @@ impl<F: Field> KeccakCircuitConfig<F> {
             assign_fixed_custom(region, *column, offset, *value);
         }
 
-        assign_advice_custom(
+        let is_final = assign_advice_custom(
             region,
             self.keccak_table.is_enabled,
             offset,
             Value::known(F::from(row.is_final)),
         );
+        let length = assign_advice_custom(
+            region,
+            self.keccak_table.input_len,
+            offset,
+            Value::known(F::from(row.length as u64)),
+        );
 
         // Cell values
-        row.cell_values.iter().zip(self.cell_manager.columns()).for_each(|(bit, column)| {
-            assign_advice_custom(region, column.advice, offset, Value::known(*bit));
-        });
+        let mut squeeze_packed = None;
+        for (idx, (bit, column)) in
+            row.cell_values.iter().zip(self.cell_manager.columns()).enumerate()
+        {
+            let cell = assign_advice_custom(region, column.advice, offset, Value::known(*bit));
+            if idx == self.squeeze_from.column_idx {
+                squeeze_packed = Some(cell);
+            }
+        }
 
         // Round constant
         assign_fixed_custom(region, self.round_cst, offset, row.round_cst);
+
+        KeccakAssignedRow { is_final, length, squeeze_packed }
     }
 
     pub fn load_aux_tables(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
-        load_normalize_table(layouter, "normalize_6", &self.normalize_6, 6u64)?;
-        load_normalize_table(layouter, "normalize_4", &self.normalize_4, 4u64)?;
-        load_normalize_table(layouter, "normalize_3", &self.normalize_3, 3u64)?;
+        let k = self.parameters.k;
+        load_normalize_table(layouter, "normalize_6", &self.normalize_6, 6u64, k)?;
+        load_normalize_table(layouter, "normalize_4", &self.normalize_4, 4u64, k)?;
+        load_normalize_table(layouter, "normalize_3", &self.normalize_3, 3u64, k)?;
         load_lookup_table(
             layouter,
             "chi base",
             &self.chi_base_table,
-            get_num_bits_per_base_chi_lookup(),
+            get_num_bits_per_base_chi_lookup(k),
             &CHI_BASE_LOOKUP_TABLE,
         )?;
         load_pack_table(layouter, &self.pack_table)
@@ -1606,9 +1677,10 @@ pub fn keccak_phase1<'v, F: Field>(
     challenge: Value<F>,
     input_rlcs: &mut Vec<AssignedValue<'v, F>>,
     offset: &mut usize,
+    rows_per_round: usize,
 ) {
     let num_chunks = get_num_keccak_f(bytes.len());
-    let num_rows_per_round = get_num_rows_per_round();
+    let num_rows_per_round = rows_per_round;
 
     let mut byte_idx = 0;
     let mut data_rlc = Value::known(F::zero());
@@ -1646,12 +1718,15 @@ pub fn keccak_phase0<F: Field>(
     rows: &mut Vec<KeccakRow<F>>,
     squeeze_digests: &mut Vec<[F; NUM_WORDS_TO_SQUEEZE]>,
     bytes: &[u8],
+    parameters: KeccakConfigParams,
 ) {
+    let k = parameters.k;
+    let num_rows_per_round = parameters.rows_per_round;
+
     let mut bits = into_bits(bytes);
     let mut s = [[F::zero(); 5]; 5];
     let absorb_positions = get_absorb_positions();
     let num_bytes_in_last_block = bytes.len() % RATE;
-    let num_rows_per_round = get_num_rows_per_round();
     let two = F::from(2u64);
 
     // Padding
@@ -1667,9 +1742,11 @@ pub fn keccak_phase0<F: Field>(
     let mut cell_managers = Vec::with_capacity(NUM_ROUNDS + 1);
     let mut regions = Vec::with_capacity(NUM_ROUNDS + 1);
     let mut hash_words = [F::zero(); NUM_WORDS_TO_SQUEEZE];
+    let mut length = 0;
 
     for (idx, chunk) in chunks.enumerate() {
         let is_final_block = idx == num_chunks - 1;
+        let mut round_lengths = Vec::with_capacity(NUM_ROUNDS + 1);
 
         let mut absorb_rows = Vec::new();
         // Absorb
@@ -1711,7 +1788,7 @@ pub fn keccak_phase0<F: Field>(
 
             // Absorb
             cell_manager.start_region();
-            let part_size = get_num_bits_per_absorb_lookup();
+            let part_size = get_num_bits_per_absorb_lookup(k);
             let input = absorb_row.from + absorb_row.absorb;
             let absorb_fat =
                 split::value(&mut cell_manager, &mut region, input, 0, part_size, false, None);
@@ -1743,13 +1820,17 @@ pub fn keccak_phase0<F: Field>(
                     let byte_idx = round * NUM_BYTES_PER_WORD + padding_idx;
                     let padding = is_final_block && byte_idx >= num_bytes_in_last_block;
                     is_padding.assign(&mut region, 0, F::from(padding));
+                    if !padding {
+                        length += 1;
+                    }
                 }
             }
+            round_lengths.push(length);
             cell_manager.start_region();
 
             if round != NUM_ROUNDS {
                 // Theta
-                let part_size = get_num_bits_per_theta_c_lookup();
+                let part_size = get_num_bits_per_theta_c_lookup(k);
                 let mut bcf = Vec::new();
                 for s in &s {
                     let c = s[0] + s[1] + s[2] + s[3] + s[4];
@@ -1783,7 +1864,7 @@ pub fn keccak_phase0<F: Field>(
                 cell_manager.start_region();
 
                 // Rho/Pi
-                let part_size = get_num_bits_per_base_chi_lookup();
+                let part_size = get_num_bits_per_base_chi_lookup(k);
                 let target_word_sizes = target_part_sizes(part_size);
                 let num_word_parts = target_word_sizes.len();
                 let mut rho_pi_chi_cells: [[[Vec<Cell<F>>; 5]; 5]; 3] =
@@ -1832,7 +1913,7 @@ pub fn keccak_phase0<F: Field>(
                 cell_manager.start_region();
 
                 // Chi
-                let part_size_base = get_num_bits_per_base_chi_lookup();
+                let part_size_base = get_num_bits_per_base_chi_lookup(k);
                 let three_packed = pack::<F>(&vec![3u8; part_size_base]);
                 let mut os = [[F::zero(); 5]; 5];
                 for j in 0..5 {
@@ -1864,7 +1945,7 @@ pub fn keccak_phase0<F: Field>(
                 cell_manager.start_region();
 
                 // iota
-                let part_size = get_num_bits_per_absorb_lookup();
+                let part_size = get_num_bits_per_absorb_lookup(k);
                 let input = s[0][0] + pack_u64::<F>(ROUND_CST[round]);
                 let iota_parts = split::value::<F>(
                     &mut cell_manager,
@@ -1928,6 +2009,7 @@ pub fn keccak_phase0<F: Field>(
                     round_cst,
                     is_final: is_final_block && round == NUM_ROUNDS && row_idx == 0,
                     cell_values: regions[round].rows.get(row_idx).unwrap_or(&vec![]).clone(),
+                    length: round_lengths[round],
                 });
                 #[cfg(debug_assertions)]
                 {
@@ -1967,11 +2049,12 @@ pub fn multi_keccak_phase1<'a, 'v, F: Field>(
     bytes: impl IntoIterator<Item = &'a [u8]>,
     challenge: Value<F>,
     squeeze_digests: Vec<[F; NUM_WORDS_TO_SQUEEZE]>,
+    parameters: KeccakConfigParams,
 ) -> (Vec<AssignedValue<'v, F>>, Vec<AssignedValue<'v, F>>) {
     let mut input_rlcs = Vec::with_capacity(squeeze_digests.len());
     let mut output_rlcs = Vec::with_capacity(squeeze_digests.len());
 
-    let num_rows_per_round = get_num_rows_per_round();
+    let num_rows_per_round = parameters.rows_per_round;
     for idx in 0..num_rows_per_round {
         [keccak_table.input_rlc, keccak_table.output_rlc]
             .map(|column| assign_advice_custom(region, column, idx, Value::known(F::zero())));
@@ -1979,11 +2062,27 @@ pub fn multi_keccak_phase1<'a, 'v, F: Field>(
 
     let mut offset = num_rows_per_round;
     for bytes in bytes {
-        keccak_phase1(region, keccak_table, bytes, challenge, &mut input_rlcs, &mut offset);
+        keccak_phase1(
+            region,
+            keccak_table,
+            bytes,
+            challenge,
+            &mut input_rlcs,
+            &mut offset,
+            num_rows_per_round,
+        );
     }
     debug_assert!(input_rlcs.len() <= squeeze_digests.len());
     while input_rlcs.len() < squeeze_digests.len() {
-        keccak_phase1(region, keccak_table, &[], challenge, &mut input_rlcs, &mut offset);
+        keccak_phase1(
+            region,
+            keccak_table,
+            &[],
+            challenge,
+            &mut input_rlcs,
+            &mut offset,
+            num_rows_per_round,
+        );
     }
 
     offset = num_rows_per_round;
@@ -2007,8 +2106,9 @@ pub fn multi_keccak_phase1<'a, 'v, F: Field>(
 pub fn multi_keccak_phase0<F: Field>(
     bytes: &[Vec<u8>],
     capacity: Option<usize>,
+    parameters: KeccakConfigParams,
 ) -> (Vec<KeccakRow<F>>, Vec<[F; NUM_WORDS_TO_SQUEEZE]>) {
-    let num_rows_per_round = get_num_rows_per_round();
+    let num_rows_per_round = parameters.rows_per_round;
     let mut rows =
         Vec::with_capacity((1 + capacity.unwrap_or(0) * (NUM_ROUNDS + 1)) * num_rows_per_round);
     // Dummy first row so that the initial data is absorbed
@@ -2021,7 +2121,7 @@ pub fn multi_keccak_phase0<F: Field>(
             let num_keccak_f = get_num_keccak_f(bytes.len());
             let mut squeeze_digests = Vec::with_capacity(num_keccak_f);
             let mut rows = Vec::with_capacity(num_keccak_f * (NUM_ROUNDS + 1) * num_rows_per_round);
-            keccak_phase0(&mut rows, &mut squeeze_digests, bytes);
+            keccak_phase0(&mut rows, &mut squeeze_digests, bytes, parameters);
             (rows, squeeze_digests)
         })
         .collect::<Vec<_>>();
@@ -2034,11 +2134,11 @@ pub fn multi_keccak_phase0<F: Field>(
 
     if let Some(capacity) = capacity {
         // Pad with no data hashes to the expected capacity
-        while rows.len() < (1 + capacity * (NUM_ROUNDS + 1)) * get_num_rows_per_round() {
-            keccak_phase0(&mut rows, &mut squeeze_digests, &[]);
+        while rows.len() < (1 + capacity * (NUM_ROUNDS + 1)) * num_rows_per_round {
+            keccak_phase0(&mut rows, &mut squeeze_digests, &[], parameters);
         }
         // Check that we are not over capacity
-        if rows.len() > (1 + capacity * (NUM_ROUNDS + 1)) * get_num_rows_per_round() {
+        if rows.len() > (1 + capacity * (NUM_ROUNDS + 1)) * num_rows_per_round {
             panic!("{:?}", Error::BoundsFailure);
         }
     }
@@ -0,0 +1,237 @@
+//! A standalone keccak circuit that hashes a list of byte arrays and exposes, for each input,
+//! its byte length and its digest as public instances.
+use super::*;
+use crate::halo2_proofs::{
+    circuit::SimpleFloorPlanner,
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, FirstPhase, Instance,
+        ProvingKey, VerifyingKey,
+    },
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use ethers_core::utils::keccak256;
+use rand::rngs::OsRng;
+use std::iter;
+
+/// Number of public instances exposed per input: the byte length followed by the packed digest words
+pub const NUM_INSTANCES_PER_INPUT: usize = 1 + NUM_WORDS_TO_SQUEEZE;
+
+#[derive(Clone, Debug)]
+pub struct KeccakCircuitInstanceConfig<F> {
+    pub keccak: KeccakCircuitConfig<F>,
+    pub instance: Column<Instance>,
+}
+
+/// Keccak circuit with circuit degree `K` and `ROWS_PER_ROUND` rows per keccak_f round.
+///
+/// For input `i`, the instances at rows `NUM_INSTANCES_PER_INPUT * i..NUM_INSTANCES_PER_INPUT * (i + 1)`
+/// are the byte length of the input followed by the 4 little endian 64-bit words of its digest,
+/// each packed as in [`pack_u64`]. Use [`KeccakCircuit::instances`] to compute them natively.
+///
+/// The copy constraints to the instance column depend on how many keccak_f permutations each input takes,
+/// so the verifying key is only valid for inputs with the same number of keccak_f's per input.
+#[derive(Clone, Debug)]
+pub struct KeccakCircuit<F: Field, const K: u32, const ROWS_PER_ROUND: usize> {
+    inputs: Vec<Vec<u8>>,
+    capacity: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field, const K: u32, const ROWS_PER_ROUND: usize> KeccakCircuit<F, K, ROWS_PER_ROUND> {
+    /// Creates a new circuit hashing `inputs`, padded with empty inputs to `capacity` keccak_f's
+    pub fn new(inputs: Vec<Vec<u8>>, capacity: usize) -> Self {
+        let num_keccak_f: usize = inputs.iter().map(|input| get_num_keccak_f(input.len())).sum();
+        assert!(
+            num_keccak_f <= capacity,
+            "inputs require {num_keccak_f} keccak_f's, which exceeds capacity {capacity}"
+        );
+        Self { inputs, capacity, _marker: PhantomData }
+    }
+
+    pub fn parameters() -> KeccakConfigParams {
+        KeccakConfigParams::new(K, ROWS_PER_ROUND)
+    }
+
+    /// The largest capacity that fits in the `2^K` rows, excluding the rows reserved for blinding factors
+    pub fn max_capacity() -> usize {
+        let mut meta = ConstraintSystem::<F>::default();
+        Self::configure(&mut meta);
+        let usable_rows = (1 << K) - (meta.blinding_factors() + 1);
+        get_keccak_capacity(usable_rows, ROWS_PER_ROUND)
+    }
+
+    pub fn inputs(&self) -> &[Vec<u8>] {
+        &self.inputs
+    }
+
+    /// The number of keccak_f's that can be done in this circuit
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Computes the public instances natively
+    pub fn instances(&self) -> Vec<Vec<F>> {
+        vec![self
+            .inputs
+            .iter()
+            .flat_map(|input| {
+                let digest = keccak256(input);
+                iter::once(F::from(input.len() as u64))
+                    .chain(digest.chunks(NUM_BYTES_PER_WORD).map(|word| pack(&into_bits(word))))
+            })
+            .collect()]
+    }
+}
+
+impl<F: Field, const K: u32, const ROWS_PER_ROUND: usize> Circuit<F>
+    for KeccakCircuit<F, K, ROWS_PER_ROUND>
+{
+    type Config = KeccakCircuitInstanceConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // the circuit layout depends on the input lengths, so we keep the inputs
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let challenge = meta.challenge_usable_after(FirstPhase);
+        let keccak = KeccakCircuitConfig::new(meta, challenge, Self::parameters());
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        KeccakCircuitInstanceConfig { keccak, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let parameters = config.keccak.parameters();
+        let num_rows_per_round = parameters.rows_per_round;
+        let squeeze_rotation = config.keccak.squeeze_rotation();
+        config.keccak.load_aux_tables(&mut layouter)?;
+
+        let mut challenge = layouter.get_challenge(config.keccak.challenge);
+        let mut first_pass = true;
+        let mut instance_cells = vec![];
+        layouter.assign_region(
+            || "keccak circuit",
+            |mut region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+                let (witness, squeeze_digests) =
+                    multi_keccak_phase0(&self.inputs, Some(self.capacity), parameters);
+                let assigned_rows = config.keccak.assign(&mut region, &witness);
+
+                // the rows with `is_final == 1` are, in order, the last rows of each input's keccak
+                let final_offsets = witness
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(offset, row)| row.is_final.then_some(offset))
+                    .take(self.inputs.len());
+                let mut push_cell = |acell: &AssignedValue<F>| {
+                    #[cfg(feature = "halo2-axiom")]
+                    instance_cells.push(*acell.cell());
+                    #[cfg(feature = "halo2-pse")]
+                    instance_cells.push(acell.cell());
+                };
+                for offset in final_offsets {
+                    push_cell(&assigned_rows[offset].length);
+                    // the squeezed words are constrained to equal the digest on the final row
+                    for idx in 0..NUM_WORDS_TO_SQUEEZE {
+                        let squeeze_offset =
+                            offset - (idx + 1) * num_rows_per_round + squeeze_rotation;
+                        let word = assigned_rows[squeeze_offset]
+                            .squeeze_packed
+                            .as_ref()
+                            .expect("squeezed word should be assigned");
+                        push_cell(word);
+                    }
+                }
+
+                #[cfg(feature = "halo2-axiom")]
+                {
+                    region.next_phase();
+                    challenge = region.get_challenge(config.keccak.challenge);
+                }
+                multi_keccak_phase1(
+                    &mut region,
+                    &config.keccak.keccak_table,
+                    self.inputs.iter().map(|v| v.as_slice()),
+                    challenge,
+                    squeeze_digests,
+                    parameters,
+                );
+                Ok(())
+            },
+        )?;
+
+        for (i, cell) in instance_cells.into_iter().enumerate() {
+            #[cfg(feature = "halo2-axiom")]
+            layouter.constrain_instance(cell, config.instance, i);
+            #[cfg(feature = "halo2-pse")]
+            layouter.constrain_instance(cell, config.instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn gen_pk<const K: u32, const ROWS_PER_ROUND: usize>(
+    params: &ParamsKZG<Bn256>,
+    circuit: &KeccakCircuit<Fr, K, ROWS_PER_ROUND>,
+) -> Result<ProvingKey<G1Affine>, Error> {
+    let vk = keygen_vk(params, circuit)?;
+    keygen_pk(params, vk, circuit)
+}
+
+pub fn gen_proof<const K: u32, const ROWS_PER_ROUND: usize>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: KeccakCircuit<Fr, K, ROWS_PER_ROUND>,
+) -> Result<Vec<u8>, Error> {
+    let instances = circuit.instances();
+    let instances = instances.iter().map(|instance| instance.as_slice()).collect::<Vec<_>>();
+    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        _,
+        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+        _,
+    >(params, pk, &[circuit], &[&instances], OsRng, &mut transcript)?;
+    Ok(transcript.finalize())
+}
+
+pub fn check_proof(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    instances: &[Vec<Fr>],
+    proof: &[u8],
+) -> Result<(), Error> {
+    let instances = instances.iter().map(|instance| instance.as_slice()).collect::<Vec<_>>();
+    let verifier_params = params.verifier_params();
+    let strategy = SingleStrategy::new(params);
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+    verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+        SingleStrategy<'_, Bn256>,
+    >(verifier_params, vk, strategy, &[&instances], &mut transcript)
+}
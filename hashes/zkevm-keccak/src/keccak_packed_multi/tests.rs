@@ -19,6 +19,15 @@ use crate::halo2_proofs::{
     },
 };
 use rand_core::OsRng;
+use std::env::var;
+
+/// The keccak circuit in tests reads its layout from the `KECCAK_DEGREE` and `KECCAK_ROWS` env vars,
+/// since `Circuit::configure` does not take any parameters.
+fn get_test_params() -> KeccakConfigParams {
+    let k = var("KECCAK_DEGREE").unwrap_or_else(|_| "14".to_string()).parse().unwrap();
+    let rows_per_round = var("KECCAK_ROWS").unwrap_or_else(|_| "25".to_string()).parse().unwrap();
+    KeccakConfigParams { k, rows_per_round }
+}
 
 /// KeccakCircuit
 #[derive(Default, Clone, Debug)]
@@ -39,7 +48,7 @@ impl<F: Field> Circuit<F> for KeccakCircuit<F> {
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
         let challenge = meta.challenge_usable_after(FirstPhase);
-        KeccakCircuitConfig::new(meta, challenge)
+        KeccakCircuitConfig::new(meta, challenge, get_test_params())
     }
 
     fn synthesize(
@@ -57,7 +66,8 @@ impl<F: Field> Circuit<F> for KeccakCircuit<F> {
                     first_pass = false;
                     return Ok(());
                 }
-                let (witness, squeeze_digests) = multi_keccak_phase0(&self.inputs, self.capacity());
+                let (witness, squeeze_digests) =
+                    multi_keccak_phase0(&self.inputs, self.capacity(), config.parameters());
                 config.assign(&mut region, &witness);
 
                 #[cfg(feature = "halo2-axiom")]
@@ -71,6 +81,7 @@ impl<F: Field> Circuit<F> for KeccakCircuit<F> {
                     self.inputs.iter().map(|v| v.as_slice()),
                     challenge,
                     squeeze_digests,
+                    config.parameters(),
                 );
                 Ok(())
             },
@@ -89,7 +100,8 @@ impl<F: Field> KeccakCircuit<F> {
     /// The number of keccak_f's that can be done in this circuit
     pub fn capacity(&self) -> Option<usize> {
         // Subtract two for unusable rows
-        self.num_rows.map(|num_rows| num_rows / ((NUM_ROUNDS + 1) * get_num_rows_per_round()) - 2)
+        let rows_per_round = get_test_params().rows_per_round;
+        self.num_rows.map(|num_rows| num_rows / ((NUM_ROUNDS + 1) * rows_per_round) - 2)
     }
 }
 
@@ -105,7 +117,7 @@ fn verify<F: Field>(k: u32, inputs: Vec<Vec<u8>>, _success: bool) {
 fn packed_multi_keccak_simple() {
     let _ = env_logger::builder().is_test(true).try_init();
 
-    let k = 14;
+    let k = get_test_params().k;
     let inputs = vec![
         vec![],
         (0u8..1).collect::<Vec<_>>(),
@@ -120,7 +132,7 @@ fn packed_multi_keccak_simple() {
 fn packed_multi_keccak_prover() {
     let _ = env_logger::builder().is_test(true).try_init();
 
-    let k: u32 = var("KECCAK_DEGREE").unwrap_or_else(|_| "14".to_string()).parse().unwrap();
+    let k = get_test_params().k;
     let params = ParamsKZG::<Bn256>::setup(k, OsRng);
 
     let inputs = vec![
@@ -161,3 +173,49 @@ fn packed_multi_keccak_prover() {
     >(&verifier_params, pk.get_vk(), strategy, &[&[]], &mut verifier_transcript)
     .expect("failed to verify bench circuit");
 }
+
+#[test]
+fn keccak_circuit_instances() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    const K: u32 = 14;
+    let inputs = vec![
+        vec![],
+        (0u8..1).collect::<Vec<_>>(),
+        (0u8..135).collect::<Vec<_>>(),
+        (0u8..136).collect::<Vec<_>>(),
+        (0u8..200).collect::<Vec<_>>(),
+    ];
+    let capacity = circuit::KeccakCircuit::<Fr, K, 25>::max_capacity();
+    let circuit = circuit::KeccakCircuit::<Fr, K, 25>::new(inputs, capacity);
+    let instances = circuit.instances();
+    assert_eq!(instances[0].len(), 5 * circuit::NUM_INSTANCES_PER_INPUT);
+
+    MockProver::<Fr>::run(K, &circuit, instances.clone()).unwrap().assert_satisfied();
+
+    // changing a digest word should fail
+    let mut wrong_instances = instances;
+    wrong_instances[0][1] += Fr::from(1);
+    assert!(MockProver::<Fr>::run(K, &circuit, wrong_instances).unwrap().verify().is_err());
+}
+
+#[test]
+fn keccak_circuit_prover() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    const K: u32 = 12;
+    let params = ParamsKZG::<Bn256>::setup(K, OsRng);
+    let inputs = vec![vec![], (0u8..100).collect::<Vec<_>>()];
+    let capacity = circuit::KeccakCircuit::<Fr, K, 25>::max_capacity();
+    let circuit = circuit::KeccakCircuit::<Fr, K, 25>::new(inputs, capacity);
+    let instances = circuit.instances();
+
+    let pk = circuit::gen_pk(&params, &circuit).unwrap();
+    let proof = circuit::gen_proof(&params, &pk, circuit).unwrap();
+    circuit::check_proof(&params, pk.get_vk(), &instances, &proof).unwrap();
+
+    // the proof should not verify against a different digest
+    let mut wrong_instances = instances;
+    wrong_instances[0][1] += Fr::from(1);
+    assert!(circuit::check_proof(&params, pk.get_vk(), &wrong_instances, &proof).is_err());
+}